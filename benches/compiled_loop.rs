@@ -0,0 +1,51 @@
+//! Compares a tight loop whose body is walked element by element with the
+//! same loop after `bind` has compiled the body. Run with `cargo bench`.
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use libps::{FileObject, Interpreter};
+
+const ITERATIONS: u32 = 200_000;
+const RUNS: u32 = 5;
+
+// Exercises operators, name lookups of user definitions and a nested
+// procedure run by `if`
+const BODY: &str = "{ 3 mod 0 eq { /hits hits 1 add def } if /total total step def }";
+
+fn main() {
+    let walked = format!("/step {{ 1 add }} def 1 1 {ITERATIONS} {BODY} for");
+    let compiled = format!("/step {{ 1 add }} bind def 1 1 {ITERATIONS} {BODY} bind for");
+
+    let walked = measure(&walked);
+    let compiled = measure(&compiled);
+
+    println!("walked:   {walked:?}");
+    println!("compiled: {compiled:?}");
+    println!(
+        "speedup:  {:.2}x",
+        walked.as_secs_f64() / compiled.as_secs_f64()
+    );
+}
+
+/// Runs a job the given number of times and keeps the fastest run.
+fn measure(job: &str) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let mut interpreter = Interpreter::new(io::sink());
+            interpreter.push_file(FileObject::from(format!("/hits 0 def /total 0 def {job}")));
+
+            let start = Instant::now();
+            interpreter.exec().expect("benchmark job failed");
+            let elapsed = start.elapsed();
+
+            let total: i32 = interpreter.load_value("total").expect("total is defined");
+            assert_eq!(ITERATIONS, total as u32);
+
+            elapsed
+        })
+        .min()
+        .unwrap_or_default()
+}
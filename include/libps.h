@@ -0,0 +1,91 @@
+// Generated from src/ffi.rs by `LIBPS_UPDATE_HEADER=1 cargo test`.
+
+#ifndef LIBPS_H
+#define LIBPS_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct libps_interpreter libps_interpreter;
+
+typedef void (*libps_output_fn)(void *user_data, const uint8_t *data, size_t len);
+
+typedef void (*libps_page_fn)(void *user_data, int32_t number, float width, float height);
+
+#define LIBPS_OK 0
+#define LIBPS_ERROR -1
+#define LIBPS_QUIT 1
+
+// Creates an interpreter that prints to standard output. Returns NULL if it
+// could not be created. If a call panics, every later call on the
+// interpreter fails with `unregistered`, and it can only be freed.
+libps_interpreter *libps_new(void);
+
+// Destroys an interpreter created by `libps_new`. Accepts NULL.
+void libps_free(libps_interpreter *interpreter);
+
+// Runs `len` bytes of PostScript as a job. Fails if the job stops with an
+// error, after the error has been reported on the output. Returns
+// `LIBPS_QUIT` if the job ended with `quit`; the interpreter stays usable.
+int32_t libps_run(libps_interpreter *interpreter, const uint8_t *data, size_t len);
+
+// Runs the file at the UTF-8 `path` as a job, the same as `libps_run`.
+int32_t libps_run_file(libps_interpreter *interpreter, const char *path);
+
+// Sends everything jobs print to `callback` along with `user_data`, or back
+// to standard output if `callback` is NULL.
+int32_t libps_set_output(libps_interpreter *interpreter, libps_output_fn callback, void *user_data);
+
+// Calls `callback` with `user_data` for every page `showpage` transmits,
+// passing the page number and its size in points. NULL stops the calls.
+int32_t libps_set_page_handler(libps_interpreter *interpreter, libps_page_fn callback, void *user_data);
+
+// The PostScript name of the error the last call failed with, or NULL if it
+// succeeded. Valid until the next call on the interpreter.
+const char *libps_error_name(const libps_interpreter *interpreter);
+
+// A description of the error the last call failed with, or NULL if it
+// succeeded. Valid until the next call on the interpreter.
+const char *libps_error_message(const libps_interpreter *interpreter);
+
+// Pushes the integer `value`.
+int32_t libps_push_int(libps_interpreter *interpreter, int32_t value);
+
+// Pushes the real `value`.
+int32_t libps_push_real(libps_interpreter *interpreter, float value);
+
+// Pushes the boolean `value`.
+int32_t libps_push_bool(libps_interpreter *interpreter, bool value);
+
+// Pushes a string holding a copy of `len` bytes from `data`.
+int32_t libps_push_string(libps_interpreter *interpreter, const uint8_t *data, size_t len);
+
+// Pops an integer into `out`. The operand is left in place if it is not an
+// integer, which holds for the other pops as well.
+int32_t libps_pop_int(libps_interpreter *interpreter, int32_t *out);
+
+// Pops a number into `out`, converting integers.
+int32_t libps_pop_real(libps_interpreter *interpreter, float *out);
+
+// Pops a boolean into `out`.
+int32_t libps_pop_bool(libps_interpreter *interpreter, bool *out);
+
+// Stores the length of the string on top of the operand stack in `len`,
+// leaving the string in place, so callers can size the buffer they pass to
+// `libps_pop_string`.
+int32_t libps_peek_string_len(libps_interpreter *interpreter, size_t *len);
+
+// Pops a string into the `capacity` bytes at `buf`, storing its length in
+// `len`. A string that does not fit is left in place with a `rangecheck`.
+int32_t libps_pop_string(libps_interpreter *interpreter, uint8_t *buf, size_t capacity, size_t *len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -1,10 +1,15 @@
-use std::{fmt, slice};
+use std::{rc::Rc, slice};
 
-use crate::object::{Access, Mode, Object};
+use crate::{
+    bytecode::Instruction,
+    object::{Access, Mode, Object},
+    Error, ErrorKind,
+};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ArrayObject {
     access: Access,
+    compiled: Option<Rc<[Instruction]>>,
     inner: Vec<Object>,
     mode: Mode,
 }
@@ -12,8 +17,9 @@ pub struct ArrayObject {
 impl ArrayObject {
     pub fn new(objs: Vec<Object>, access: Access, mode: Mode) -> Self {
         Self {
-            inner: objs,
             access,
+            compiled: None,
+            inner: objs,
             mode,
         }
     }
@@ -22,6 +28,29 @@ impl ArrayObject {
         self.access
     }
 
+    pub(crate) fn compiled(&self) -> Option<&Rc<[Instruction]>> {
+        self.compiled.as_ref()
+    }
+
+    pub fn get(&self, index: usize) -> crate::Result<&Object> {
+        self.inner
+            .get(index)
+            .ok_or(Error::new(ErrorKind::RangeCheck, "index out of range"))
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> crate::Result<&mut Object> {
+        // Any change may leave the compiled form stale
+        self.compiled = None;
+
+        self.inner
+            .get_mut(index)
+            .ok_or(Error::new(ErrorKind::RangeCheck, "index out of range"))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
     pub fn iter(&self) -> slice::Iter<'_, Object> {
         self.inner.iter()
     }
@@ -33,22 +62,16 @@ impl ArrayObject {
     pub fn mode(&self) -> Mode {
         self.mode
     }
-}
 
-impl fmt::Display for ArrayObject {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let (l_delim, r_delim) = match self.mode() {
-            Mode::Executable => ("{ ", "}"),
-            Mode::Literal => ("[ ", "]"),
-        };
-
-        l_delim.fmt(f)?;
-        for obj in self.iter() {
-            obj.fmt(f)?;
-            " ".fmt(f)?;
-        }
-        r_delim.fmt(f)?;
+    pub fn set_access(&mut self, access: Access) {
+        self.access = access;
+    }
+
+    pub(crate) fn set_compiled(&mut self, compiled: Rc<[Instruction]>) {
+        self.compiled = Some(compiled);
+    }
 
-        Ok(())
+    pub fn value(&self) -> &[Object] {
+        &self.inner
     }
 }
@@ -0,0 +1,92 @@
+use crate::{
+    array::ArrayObject,
+    heap::{Handle, Heap},
+    name::NameObject,
+    operator::OperatorObject,
+    Object,
+};
+
+/// A procedure element translated ahead of execution. Instructions line up
+/// one-to-one with the elements they were compiled from, so a procedure can
+/// switch back to being walked element by element at any index.
+#[derive(Clone, Debug)]
+pub(crate) enum Instruction {
+    /// Calls an operator without going through object dispatch.
+    Operator(OperatorObject),
+    /// Looks up an executable name when it is reached and runs what it
+    /// resolves to, so later redefinitions still take effect.
+    Call(NameObject),
+    /// Pushes a literal, or a procedure met as data, onto the operand stack.
+    Push(Object),
+    /// Anything else, executed exactly as the element itself would be.
+    Token(Object),
+}
+
+/// Compiles a procedure along with the procedures nested inside it, so
+/// whatever runs them later, such as `if` or `for`, gets the compiled form.
+pub(crate) fn compile(proc: Handle<ArrayObject>, heap: &mut Heap) {
+    let elements = heap.get(proc).value().to_vec();
+    let compiled = elements
+        .iter()
+        .map(|obj| match obj {
+            Object::Operator((operator, mode)) if mode.is_executable() => {
+                Instruction::Operator(*operator)
+            },
+            Object::Name(name) if name.mode().is_executable() => Instruction::Call(*name),
+            Object::Array(_) => Instruction::Push(*obj),
+            obj if obj.mode(heap).is_literal() => Instruction::Push(*obj),
+            obj => Instruction::Token(*obj),
+        })
+        .collect();
+
+    heap.get_mut(proc).set_compiled(compiled);
+
+    // The outer procedure is already compiled by now, so a procedure that
+    // contains itself does not recurse forever
+    for obj in elements {
+        match obj {
+            Object::Array(nested)
+                if heap.get(nested).mode().is_executable()
+                    && heap.get(nested).compiled().is_none() =>
+            {
+                compile(nested, heap);
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::{Access, Mode};
+
+    #[test]
+    fn test_compile_nested() -> crate::Result<()> {
+        let mut heap = Heap::default();
+        let inner = heap.alloc(ArrayObject::new(
+            vec![],
+            Access::Unlimited,
+            Mode::Executable,
+        ));
+        let data = heap.alloc(ArrayObject::new(vec![], Access::Unlimited, Mode::Literal));
+        let outer = heap.alloc(ArrayObject::new(
+            vec![
+                Object::Null(Mode::Literal),
+                Object::Array(inner),
+                Object::Array(data),
+            ],
+            Access::Unlimited,
+            Mode::Executable,
+        ));
+        *heap.get_mut(outer).get_mut(0)? = Object::Array(outer);
+
+        compile(outer, &mut heap);
+
+        assert!(heap.get(outer).compiled().is_some());
+        assert!(heap.get(inner).compiled().is_some());
+        assert!(heap.get(data).compiled().is_none());
+
+        Ok(())
+    }
+}
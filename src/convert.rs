@@ -0,0 +1,244 @@
+use std::{collections::HashMap, hash::BuildHasher};
+
+use crate::{
+    array::ArrayObject,
+    dictionary::DictionaryObject,
+    heap::Heap,
+    literal_name,
+    object::{Access, Mode},
+    string::StringObject,
+    Error, ErrorKind, Object, Rooted,
+};
+
+/// A Rust value that can be stored as a PostScript object. Composite values
+/// are allocated in the VM they are pushed into.
+pub trait IntoObject {
+    fn into_object(self, heap: &mut Heap) -> Object;
+}
+
+/// A Rust value that can be read out of a PostScript object. Composite
+/// objects are copied out, so later changes to them are not reflected. Read
+/// a `Rooted` to hold on to the object itself.
+pub trait FromObject: Sized {
+    fn from_object(obj: Object, heap: &Heap) -> crate::Result<Self>;
+}
+
+impl IntoObject for Object {
+    fn into_object(self, _: &mut Heap) -> Object {
+        self
+    }
+}
+
+impl IntoObject for Rooted {
+    fn into_object(self, _: &mut Heap) -> Object {
+        *self
+    }
+}
+
+impl IntoObject for &Rooted {
+    fn into_object(self, _: &mut Heap) -> Object {
+        **self
+    }
+}
+
+impl FromObject for Rooted {
+    fn from_object(obj: Object, heap: &Heap) -> crate::Result<Self> {
+        Ok(heap.root(obj))
+    }
+}
+
+impl IntoObject for bool {
+    fn into_object(self, _: &mut Heap) -> Object {
+        Object::Boolean(self)
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(obj: Object, _: &Heap) -> crate::Result<Self> {
+        obj.into_bool()
+    }
+}
+
+impl IntoObject for i32 {
+    fn into_object(self, _: &mut Heap) -> Object {
+        Object::Integer(self)
+    }
+}
+
+impl FromObject for i32 {
+    fn from_object(obj: Object, _: &Heap) -> crate::Result<Self> {
+        obj.into_int()
+    }
+}
+
+impl IntoObject for f32 {
+    fn into_object(self, _: &mut Heap) -> Object {
+        Object::Real(self)
+    }
+}
+
+impl FromObject for f32 {
+    fn from_object(obj: Object, _: &Heap) -> crate::Result<Self> {
+        obj.into_real()
+    }
+}
+
+// Reals are single precision, so doubles lose precision on the way in
+impl IntoObject for f64 {
+    fn into_object(self, _: &mut Heap) -> Object {
+        Object::Real(self as f32)
+    }
+}
+
+impl FromObject for f64 {
+    fn from_object(obj: Object, _: &Heap) -> crate::Result<Self> {
+        obj.into_real().map(f64::from)
+    }
+}
+
+impl IntoObject for &str {
+    fn into_object(self, heap: &mut Heap) -> Object {
+        Object::String(heap.alloc(StringObject::from(self)))
+    }
+}
+
+impl IntoObject for String {
+    fn into_object(self, heap: &mut Heap) -> Object {
+        Object::String(heap.alloc(StringObject::from(self)))
+    }
+}
+
+/// Reads strings and names, which have to hold UTF-8.
+impl FromObject for String {
+    fn from_object(obj: Object, heap: &Heap) -> crate::Result<Self> {
+        let bytes = match obj {
+            Object::Name(name) => name.value(),
+            obj => return Vec::<u8>::from_object(obj, heap).and_then(into_utf8),
+        };
+
+        into_utf8(bytes.to_vec())
+    }
+}
+
+impl IntoObject for &[u8] {
+    fn into_object(self, heap: &mut Heap) -> Object {
+        self.to_vec().into_object(heap)
+    }
+}
+
+impl IntoObject for Vec<u8> {
+    fn into_object(self, heap: &mut Heap) -> Object {
+        Object::String(heap.alloc(StringObject::from(self)))
+    }
+}
+
+impl FromObject for Vec<u8> {
+    fn from_object(obj: Object, heap: &Heap) -> crate::Result<Self> {
+        let Object::String(string) = obj else {
+            return Err(Error::new(ErrorKind::TypeCheck, "expected string"));
+        };
+
+        let string = heap.get(string);
+        if !string.access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        Ok(string.value().to_vec())
+    }
+}
+
+impl<T: IntoObject> IntoObject for Vec<T> {
+    fn into_object(self, heap: &mut Heap) -> Object {
+        let objs = self
+            .into_iter()
+            .map(|value| value.into_object(heap))
+            .collect();
+
+        Object::Array(heap.alloc(ArrayObject::new(objs, Access::Unlimited, Mode::Literal)))
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(obj: Object, heap: &Heap) -> crate::Result<Self> {
+        let Object::Array(arr) = obj else {
+            return Err(Error::new(ErrorKind::TypeCheck, "expected array"));
+        };
+
+        let arr = heap.get(arr);
+        if !arr.access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        arr.iter().map(|obj| T::from_object(*obj, heap)).collect()
+    }
+}
+
+/// Keys become literal names, the same as `/key value def` would define.
+impl<T: IntoObject, S> IntoObject for HashMap<String, T, S> {
+    fn into_object(self, heap: &mut Heap) -> Object {
+        let mut dict = DictionaryObject::new(self.len(), Access::Unlimited);
+        for (key, value) in self {
+            let value = value.into_object(heap);
+            dict.insert(literal_name(&key), value);
+        }
+
+        Object::Dictionary(heap.alloc(dict))
+    }
+}
+
+impl<T: FromObject, S: BuildHasher + Default> FromObject for HashMap<String, T, S> {
+    fn from_object(obj: Object, heap: &Heap) -> crate::Result<Self> {
+        let Object::Dictionary(dict) = obj else {
+            return Err(Error::new(ErrorKind::TypeCheck, "expected dictionary"));
+        };
+
+        let dict = heap.get(dict);
+        if !dict.access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        dict.iter()
+            .map(|(key, value)| {
+                let key = match key {
+                    Object::Name(_) | Object::String(_) => String::from_object(*key, heap)?,
+                    _ => return Err(Error::new(ErrorKind::TypeCheck, "expected name key")),
+                };
+
+                Ok((key, T::from_object(*value, heap)?))
+            })
+            .collect()
+    }
+}
+
+fn into_utf8(bytes: Vec<u8>) -> crate::Result<String> {
+    String::from_utf8(bytes).or(Err(Error::new(
+        ErrorKind::TypeCheck,
+        "expected UTF-8 string",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() -> crate::Result<()> {
+        let mut heap = Heap::default();
+
+        let values = vec![vec![1, 2], vec![], vec![3]];
+        let obj = values.clone().into_object(&mut heap);
+        assert_eq!(values, Vec::<Vec<i32>>::from_object(obj, &heap)?);
+
+        let mut dict = HashMap::new();
+        dict.insert(String::from("a"), String::from("text"));
+        dict.insert(String::from("b"), String::from("more"));
+        let obj = dict.clone().into_object(&mut heap);
+        assert_eq!(dict, HashMap::<String, String>::from_object(obj, &heap)?);
+
+        let obj = b"\xff".as_slice().into_object(&mut heap);
+        assert_eq!(vec![0xff], Vec::<u8>::from_object(obj, &heap)?);
+        assert!(String::from_object(obj, &heap).is_err());
+
+        Ok(())
+    }
+}
@@ -1,42 +1,224 @@
-use std::{collections::HashMap, fmt};
+use std::{collections::HashMap, mem, slice};
 
-use crate::{Mode, Object};
+use crate::{object::Access, Mode, Object};
 
-#[derive(Debug, Default)]
+/// Entries are kept in insertion order. Removed entries leave a hole behind
+/// until enough of them pile up to be worth compacting, so `undef` does not
+/// have to shift the rest.
+#[derive(Clone, Debug, Default)]
 pub struct DictionaryObject {
-    inner: HashMap<Object, Object>,
+    access: Access,
+    capacity: usize,
+    entries: Vec<Option<(Object, Object)>>,
+    index: HashMap<Object, usize>,
+    /// How many entries have ever been added. Each slot in `entries` records
+    /// the count at the time it was filled in `serials`, which lets a
+    /// `Cursor` find its place again after compacting.
+    inserted: u64,
     mode: Mode,
+    serials: Vec<u64>,
 }
 
 impl DictionaryObject {
-    pub fn insert(&mut self, k: Object, v: Object) -> Option<Object> {
-        self.inner.insert(k, v)
+    pub fn new(capacity: usize, access: Access) -> Self {
+        Self {
+            access,
+            capacity,
+            entries: Vec::with_capacity(capacity),
+            index: HashMap::with_capacity(capacity),
+            inserted: 0,
+            mode: Mode::Literal,
+            serials: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn access(&self) -> Access {
+        self.access
     }
 
-    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, Object, Object> {
-        self.inner.iter()
+    pub fn capacity(&self) -> usize {
+        self.capacity.max(self.len())
+    }
+
+    pub fn contains_key(&self, k: &Object) -> bool {
+        self.index.contains_key(k)
     }
 
     pub fn get(&self, k: &Object) -> Option<&Object> {
-        self.inner.get(k)
+        let slot = *self.index.get(k)?;
+
+        self.entries[slot].as_ref().map(|(_, v)| v)
+    }
+
+    pub fn insert(&mut self, k: Object, v: Object) -> Option<Object> {
+        if let Some(slot) = self.index.get(&k) {
+            let (_, value) = self.entries[*slot].as_mut()?;
+
+            return Some(mem::replace(value, v));
+        }
+
+        self.index.insert(k, self.entries.len());
+        self.entries.push(Some((k, v)));
+        self.serials.push(self.inserted);
+        self.inserted += 1;
+
+        None
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            inner: self.entries.iter(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// The entry after `cursor` in insertion order, advancing the cursor past
+    /// it. Entries added in the meantime are reached in turn.
+    pub(crate) fn next_entry(&self, cursor: &mut Cursor) -> Option<(Object, Object)> {
+        let start = self
+            .serials
+            .partition_point(|serial| *serial < cursor.serial);
+        let (slot, (key, value)) = self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(start)
+            .find_map(|(slot, entry)| entry.map(|entry| (slot, entry)))?;
+        cursor.serial = self.serials[slot] + 1;
+
+        Some((key, value))
     }
 
     pub fn mode(&self) -> Mode {
         self.mode
     }
+
+    pub fn remove(&mut self, k: &Object) -> Option<Object> {
+        let slot = self.index.remove(k)?;
+        let (_, value) = self.entries[slot].take()?;
+
+        if self.entries.len() > 2 * self.index.len() {
+            self.compact();
+        }
+
+        Some(value)
+    }
+
+    pub fn set_access(&mut self, access: Access) {
+        self.access = access;
+    }
+
+    fn compact(&mut self) {
+        let mut slots = self.entries.iter().map(Option::is_some);
+        self.serials.retain(|_| slots.next().unwrap_or_default());
+        self.entries.retain(Option::is_some);
+
+        for (slot, entry) in self.entries.iter().enumerate() {
+            if let Some(index) = entry.as_ref().and_then(|(key, _)| self.index.get_mut(key)) {
+                *index = slot;
+            }
+        }
+    }
+}
+
+/// A position in a dictionary's insertion order that survives entries being
+/// added and removed.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Cursor {
+    serial: u64,
+}
+
+pub struct Iter<'a> {
+    inner: slice::Iter<'a, Option<(Object, Object)>>,
 }
 
-impl fmt::Display for DictionaryObject {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        "<< ".fmt(f)?;
-        for (k, v) in self.iter() {
-            k.fmt(f)?;
-            " ".fmt(f)?;
-            v.fmt(f)?;
-            " ".fmt(f)?;
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a Object, &'a Object);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .find_map(|entry| entry.as_ref().map(|(k, v)| (k, v)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(dict: &DictionaryObject) -> Vec<i32> {
+        dict.iter()
+            .map(|(key, _)| key.into_int().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        let mut dict = DictionaryObject::default();
+        for key in [5, 3, 9, 1] {
+            dict.insert(Object::Integer(key), Object::Null(Mode::Literal));
+        }
+
+        // Replacing a value keeps the original position
+        dict.insert(Object::Integer(3), Object::Boolean(true));
+
+        assert_eq!(vec![5, 3, 9, 1], keys(&dict));
+        assert_eq!(Some(&Object::Boolean(true)), dict.get(&Object::Integer(3)));
+    }
+
+    #[test]
+    fn test_remove_preserves_order() {
+        let mut dict = DictionaryObject::default();
+        for key in 0..10 {
+            dict.insert(Object::Integer(key), Object::Integer(key * 10));
+        }
+
+        for key in [0, 2, 4, 6, 8, 9] {
+            assert_eq!(
+                Some(Object::Integer(key * 10)),
+                dict.remove(&Object::Integer(key))
+            );
+        }
+        assert_eq!(None, dict.remove(&Object::Integer(0)));
+
+        dict.insert(Object::Integer(2), Object::Integer(20));
+
+        assert_eq!(vec![1, 3, 5, 7, 2], keys(&dict));
+        assert_eq!(5, dict.len());
+        for key in [1, 3, 5, 7, 2] {
+            assert_eq!(
+                Some(&Object::Integer(key * 10)),
+                dict.get(&Object::Integer(key))
+            );
+        }
+    }
+
+    #[test]
+    fn test_cursor_survives_compaction() {
+        let mut dict = DictionaryObject::default();
+        for key in 0..6 {
+            dict.insert(Object::Integer(key), Object::Null(Mode::Literal));
+        }
+
+        let mut cursor = Cursor::default();
+        let mut visited = Vec::new();
+        while let Some((key, _)) = dict.next_entry(&mut cursor) {
+            visited.push(key.into_int().unwrap());
+
+            // Removing every entry as it is visited compacts several times
+            dict.remove(&key);
+            if visited.len() == 2 {
+                dict.insert(Object::Integer(6), Object::Null(Mode::Literal));
+            }
         }
-        ">>".fmt(f)?;
 
-        Ok(())
+        assert_eq!(vec![0, 1, 2, 3, 4, 5, 6], visited);
+        assert!(dict.is_empty());
     }
 }
@@ -1,42 +1,113 @@
 use std::fmt;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum ErrorKind {
+    ConfigurationError,
+    DictFull,
+    DictStackOverflow,
     DictStackUnderflow,
+    ExecStackOverflow,
+    Interrupt,
     InvalidAccess,
+    InvalidContext,
+    InvalidExit,
+    InvalidFileAccess,
+    InvalidFont,
+    InvalidRestore,
     IoError,
     LimitCheck,
+    NoCurrentPoint,
     RangeCheck,
+    StackOverflow,
     StackUnderflow,
     SyntaxError,
+    Timeout,
     TypeCheck,
     Undefined,
+    UndefinedFilename,
+    UndefinedResource,
     UndefinedResult,
     UnmatchedMark,
     Unregistered,
     VmError,
 }
 
+impl ErrorKind {
+    pub const ALL: [ErrorKind; 28] = [
+        ErrorKind::ConfigurationError,
+        ErrorKind::DictFull,
+        ErrorKind::DictStackOverflow,
+        ErrorKind::DictStackUnderflow,
+        ErrorKind::ExecStackOverflow,
+        ErrorKind::Interrupt,
+        ErrorKind::InvalidAccess,
+        ErrorKind::InvalidContext,
+        ErrorKind::InvalidExit,
+        ErrorKind::InvalidFileAccess,
+        ErrorKind::InvalidFont,
+        ErrorKind::InvalidRestore,
+        ErrorKind::IoError,
+        ErrorKind::LimitCheck,
+        ErrorKind::NoCurrentPoint,
+        ErrorKind::RangeCheck,
+        ErrorKind::StackOverflow,
+        ErrorKind::StackUnderflow,
+        ErrorKind::SyntaxError,
+        ErrorKind::Timeout,
+        ErrorKind::TypeCheck,
+        ErrorKind::Undefined,
+        ErrorKind::UndefinedFilename,
+        ErrorKind::UndefinedResource,
+        ErrorKind::UndefinedResult,
+        ErrorKind::UnmatchedMark,
+        ErrorKind::Unregistered,
+        ErrorKind::VmError,
+    ];
+}
+
 impl From<ErrorKind> for &'static str {
     fn from(value: ErrorKind) -> Self {
         match value {
+            ErrorKind::ConfigurationError => "configurationerror",
+            ErrorKind::DictFull => "dictfull",
+            ErrorKind::DictStackOverflow => "dictstackoverflow",
             ErrorKind::DictStackUnderflow => "dictstackunderflow",
+            ErrorKind::ExecStackOverflow => "execstackoverflow",
+            ErrorKind::Interrupt => "interrupt",
             ErrorKind::InvalidAccess => "invalidaccess",
+            ErrorKind::InvalidContext => "invalidcontext",
+            ErrorKind::InvalidExit => "invalidexit",
+            ErrorKind::InvalidFileAccess => "invalidfileaccess",
+            ErrorKind::InvalidFont => "invalidfont",
+            ErrorKind::InvalidRestore => "invalidrestore",
             ErrorKind::IoError => "ioerror",
             ErrorKind::LimitCheck => "limitcheck",
+            ErrorKind::NoCurrentPoint => "nocurrentpoint",
             ErrorKind::RangeCheck => "rangecheck",
+            ErrorKind::StackOverflow => "stackoverflow",
             ErrorKind::StackUnderflow => "stackunderflow",
             ErrorKind::SyntaxError => "syntaxerror",
+            ErrorKind::Timeout => "timeout",
             ErrorKind::TypeCheck => "typecheck",
             ErrorKind::Undefined => "undefined",
+            ErrorKind::UndefinedFilename => "undefinedfilename",
+            ErrorKind::UndefinedResource => "undefinedresource",
             ErrorKind::UndefinedResult => "undefinedresult",
             ErrorKind::UnmatchedMark => "unmatchedmark",
             ErrorKind::Unregistered => "unregistered",
-            ErrorKind::VmError => "vmerror",
+            ErrorKind::VmError => "VMerror",
         }
     }
 }
 
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name: &str = (*self).into();
+
+        name.fmt(f)
+    }
+}
+
 #[derive(Debug)]
 pub struct Error {
     error: Box<dyn std::error::Error + Send + Sync>,
@@ -64,6 +135,10 @@ impl Error {
     pub fn kind(&self) -> ErrorKind {
         self.kind
     }
+
+    pub fn message(&self) -> String {
+        self.error.to_string()
+    }
 }
 
 impl fmt::Display for Error {
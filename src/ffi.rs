@@ -0,0 +1,631 @@
+// The C API. `include/libps.h` is generated from the signatures and doc
+// comments in this file, so keep to the types `c_type` knows about. Pointers
+// are checked for null but otherwise trusted, and panics are caught before
+// they reach the caller.
+
+use std::{
+    any::Any,
+    ffi::{c_char, c_void, CStr, CString},
+    fs,
+    io::{self, Write},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use crate::{Error, ErrorKind, FileObject, Interpreter};
+
+const OK: i32 = 0;
+const ERROR: i32 = -1;
+const QUIT: i32 = 1;
+
+pub type OutputFn = unsafe extern "C" fn(user_data: *mut c_void, data: *const u8, len: usize);
+
+pub type PageFn =
+    unsafe extern "C" fn(user_data: *mut c_void, number: i32, width: f32, height: f32);
+
+/// An interpreter along with the error of the last call that failed, kept as
+/// C strings so callers can borrow them until the next call. An interpreter
+/// that panicked may be left half way through an update, so it is poisoned
+/// and refuses every later call.
+pub struct LibpsInterpreter {
+    error: Option<(CString, CString)>,
+    interpreter: Interpreter,
+    poisoned: bool,
+}
+
+struct CallbackWriter {
+    callback: OutputFn,
+    user_data: *mut c_void,
+}
+
+impl Write for CallbackWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        unsafe { (self.callback)(self.user_data, buf.as_ptr(), buf.len()) };
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `f` on the interpreter, recording the error it fails with, if any.
+fn call<F>(interpreter: *mut LibpsInterpreter, f: F) -> i32
+where
+    F: FnOnce(&mut Interpreter) -> crate::Result<()>,
+{
+    call_status(interpreter, |interpreter| f(interpreter).map(|()| OK))
+}
+
+/// Like `call`, for calls that can succeed with a status other than `OK`.
+fn call_status<F>(interpreter: *mut LibpsInterpreter, f: F) -> i32
+where
+    F: FnOnce(&mut Interpreter) -> crate::Result<i32>,
+{
+    let Some(handle) = (unsafe { interpreter.as_mut() }) else {
+        return ERROR;
+    };
+    handle.error = None;
+
+    let result = if handle.poisoned {
+        Err(Error::new(
+            ErrorKind::Unregistered,
+            "interpreter is unusable after a panic",
+        ))
+    } else {
+        panic::catch_unwind(AssertUnwindSafe(|| f(&mut handle.interpreter))).unwrap_or_else(
+            |payload| {
+                handle.poisoned = true;
+                Err(Error::new(ErrorKind::Unregistered, panic_message(payload)))
+            },
+        )
+    };
+
+    match result {
+        Ok(status) => status,
+        Err(e) => {
+            let name: &str = e.kind().into();
+            let message = e.message().replace('\0', " ");
+            handle.error = Some((
+                CString::new(name).unwrap_or_default(),
+                CString::new(message).unwrap_or_default(),
+            ));
+
+            ERROR
+        },
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+
+    format!("panic: {message}")
+}
+
+fn run_job(interpreter: &mut Interpreter, data: Vec<u8>) -> crate::Result<i32> {
+    interpreter.push_file(FileObject::from(data));
+    interpreter.exec()?;
+
+    match interpreter.last_error() {
+        Some(e) => Err(Error::new(e.kind(), e.message())),
+        None if interpreter.has_quit() => Ok(QUIT),
+        None => Ok(OK),
+    }
+}
+
+fn out_param<'a, T>(out: *mut T) -> crate::Result<&'a mut T> {
+    unsafe { out.as_mut() }.ok_or(Error::new(ErrorKind::InvalidAccess, "null pointer"))
+}
+
+/// Creates an interpreter that prints to standard output. Returns NULL if it
+/// could not be created. If a call panics, every later call on the
+/// interpreter fails with `unregistered`, and it can only be freed.
+#[no_mangle]
+pub extern "C" fn libps_new() -> *mut LibpsInterpreter {
+    panic::catch_unwind(|| {
+        Box::into_raw(Box::new(LibpsInterpreter {
+            error: None,
+            interpreter: Interpreter::new(io::stdout()),
+            poisoned: false,
+        }))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Destroys an interpreter created by `libps_new`. Accepts NULL.
+#[no_mangle]
+pub unsafe extern "C" fn libps_free(interpreter: *mut LibpsInterpreter) {
+    if !interpreter.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(interpreter))));
+    }
+}
+
+/// Runs `len` bytes of PostScript as a job. Fails if the job stops with an
+/// error, after the error has been reported on the output. Returns
+/// `LIBPS_QUIT` if the job ended with `quit`; the interpreter stays usable.
+#[no_mangle]
+pub unsafe extern "C" fn libps_run(
+    interpreter: *mut LibpsInterpreter,
+    data: *const u8,
+    len: usize,
+) -> i32 {
+    call_status(interpreter, |interpreter| {
+        let data = if data.is_null() {
+            Vec::new()
+        } else {
+            slice::from_raw_parts(data, len).to_vec()
+        };
+
+        run_job(interpreter, data)
+    })
+}
+
+/// Runs the file at the UTF-8 `path` as a job, the same as `libps_run`.
+#[no_mangle]
+pub unsafe extern "C" fn libps_run_file(
+    interpreter: *mut LibpsInterpreter,
+    path: *const c_char,
+) -> i32 {
+    call_status(interpreter, |interpreter| {
+        if path.is_null() {
+            return Err(Error::new(ErrorKind::UndefinedFilename, "null path"));
+        }
+
+        let path = CStr::from_ptr(path)
+            .to_str()
+            .map_err(|e| Error::new(ErrorKind::UndefinedFilename, e))?;
+        let data = fs::read(path).map_err(|e| Error::new(ErrorKind::UndefinedFilename, e))?;
+
+        run_job(interpreter, data)
+    })
+}
+
+/// Sends everything jobs print to `callback` along with `user_data`, or back
+/// to standard output if `callback` is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn libps_set_output(
+    interpreter: *mut LibpsInterpreter,
+    callback: Option<OutputFn>,
+    user_data: *mut c_void,
+) -> i32 {
+    call(interpreter, |interpreter| {
+        match callback {
+            Some(callback) => interpreter.set_output(CallbackWriter {
+                callback,
+                user_data,
+            }),
+            None => interpreter.set_output(io::stdout()),
+        }
+
+        Ok(())
+    })
+}
+
+/// Calls `callback` with `user_data` for every page `showpage` transmits,
+/// passing the page number and its size in points. NULL stops the calls.
+#[no_mangle]
+pub unsafe extern "C" fn libps_set_page_handler(
+    interpreter: *mut LibpsInterpreter,
+    callback: Option<PageFn>,
+    user_data: *mut c_void,
+) -> i32 {
+    call(interpreter, |interpreter| {
+        match callback {
+            Some(callback) => interpreter.set_page_handler(move |page| {
+                callback(user_data, page.number, page.width, page.height)
+            }),
+            None => interpreter.set_page_handler(|_| {}),
+        }
+
+        Ok(())
+    })
+}
+
+/// The PostScript name of the error the last call failed with, or NULL if it
+/// succeeded. Valid until the next call on the interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn libps_error_name(interpreter: *const LibpsInterpreter) -> *const c_char {
+    match interpreter
+        .as_ref()
+        .and_then(|handle| handle.error.as_ref())
+    {
+        Some((name, _)) => name.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// A description of the error the last call failed with, or NULL if it
+/// succeeded. Valid until the next call on the interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn libps_error_message(
+    interpreter: *const LibpsInterpreter,
+) -> *const c_char {
+    match interpreter
+        .as_ref()
+        .and_then(|handle| handle.error.as_ref())
+    {
+        Some((_, message)) => message.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Pushes the integer `value`.
+#[no_mangle]
+pub unsafe extern "C" fn libps_push_int(interpreter: *mut LibpsInterpreter, value: i32) -> i32 {
+    call(interpreter, |interpreter| interpreter.push_value(value))
+}
+
+/// Pushes the real `value`.
+#[no_mangle]
+pub unsafe extern "C" fn libps_push_real(interpreter: *mut LibpsInterpreter, value: f32) -> i32 {
+    call(interpreter, |interpreter| interpreter.push_value(value))
+}
+
+/// Pushes the boolean `value`.
+#[no_mangle]
+pub unsafe extern "C" fn libps_push_bool(interpreter: *mut LibpsInterpreter, value: bool) -> i32 {
+    call(interpreter, |interpreter| interpreter.push_value(value))
+}
+
+/// Pushes a string holding a copy of `len` bytes from `data`.
+#[no_mangle]
+pub unsafe extern "C" fn libps_push_string(
+    interpreter: *mut LibpsInterpreter,
+    data: *const u8,
+    len: usize,
+) -> i32 {
+    call(interpreter, |interpreter| {
+        if data.is_null() {
+            return interpreter.push_value(Vec::<u8>::new());
+        }
+
+        interpreter.push_value(slice::from_raw_parts(data, len))
+    })
+}
+
+/// Pops an integer into `out`. The operand is left in place if it is not an
+/// integer, which holds for the other pops as well.
+#[no_mangle]
+pub unsafe extern "C" fn libps_pop_int(interpreter: *mut LibpsInterpreter, out: *mut i32) -> i32 {
+    call(interpreter, |interpreter| {
+        let out = out_param(out)?;
+        *out = interpreter.pop_value()?;
+
+        Ok(())
+    })
+}
+
+/// Pops a number into `out`, converting integers.
+#[no_mangle]
+pub unsafe extern "C" fn libps_pop_real(interpreter: *mut LibpsInterpreter, out: *mut f32) -> i32 {
+    call(interpreter, |interpreter| {
+        let out = out_param(out)?;
+        *out = interpreter.pop_value()?;
+
+        Ok(())
+    })
+}
+
+/// Pops a boolean into `out`.
+#[no_mangle]
+pub unsafe extern "C" fn libps_pop_bool(interpreter: *mut LibpsInterpreter, out: *mut bool) -> i32 {
+    call(interpreter, |interpreter| {
+        let out = out_param(out)?;
+        *out = interpreter.pop_value()?;
+
+        Ok(())
+    })
+}
+
+/// Stores the length of the string on top of the operand stack in `len`,
+/// leaving the string in place, so callers can size the buffer they pass to
+/// `libps_pop_string`.
+#[no_mangle]
+pub unsafe extern "C" fn libps_peek_string_len(
+    interpreter: *mut LibpsInterpreter,
+    len: *mut usize,
+) -> i32 {
+    call(interpreter, |interpreter| {
+        let len = out_param(len)?;
+        *len = interpreter.peek_value::<Vec<u8>>()?.len();
+
+        Ok(())
+    })
+}
+
+/// Pops a string into the `capacity` bytes at `buf`, storing its length in
+/// `len`. A string that does not fit is left in place with a `rangecheck`.
+#[no_mangle]
+pub unsafe extern "C" fn libps_pop_string(
+    interpreter: *mut LibpsInterpreter,
+    buf: *mut u8,
+    capacity: usize,
+    len: *mut usize,
+) -> i32 {
+    call(interpreter, |interpreter| {
+        let len = out_param(len)?;
+        let value: Vec<u8> = interpreter.peek_value()?;
+
+        *len = value.len();
+        if value.len() > capacity {
+            return Err(Error::new(ErrorKind::RangeCheck, "buffer too small"));
+        }
+        if !value.is_empty() {
+            if buf.is_null() {
+                return Err(Error::new(ErrorKind::InvalidAccess, "null pointer"));
+            }
+            ptr::copy_nonoverlapping(value.as_ptr(), buf, value.len());
+        }
+
+        interpreter.pop_value::<Vec<u8>>().map(|_| ())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fmt::Write};
+
+    use super::*;
+
+    const PREAMBLE: &str = "\
+#ifndef LIBPS_H
+#define LIBPS_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern \"C\" {
+#endif
+
+typedef struct libps_interpreter libps_interpreter;
+
+typedef void (*libps_output_fn)(void *user_data, const uint8_t *data, size_t len);
+
+typedef void (*libps_page_fn)(void *user_data, int32_t number, float width, float height);
+";
+
+    const POSTAMBLE: &str = "
+#ifdef __cplusplus
+}
+#endif
+
+#endif
+";
+
+    fn c_type(rust: &str) -> &'static str {
+        match rust {
+            "*mut LibpsInterpreter" => "libps_interpreter *",
+            "*const LibpsInterpreter" => "const libps_interpreter *",
+            "Option<OutputFn>" => "libps_output_fn ",
+            "Option<PageFn>" => "libps_page_fn ",
+            "*const c_char" => "const char *",
+            "*mut c_void" => "void *",
+            "*const u8" => "const uint8_t *",
+            "*mut u8" => "uint8_t *",
+            "bool" => "bool ",
+            "*mut bool" => "bool *",
+            "f32" => "float ",
+            "*mut f32" => "float *",
+            "i32" => "int32_t ",
+            "*mut i32" => "int32_t *",
+            "usize" => "size_t ",
+            "*mut usize" => "size_t *",
+            _ => panic!("no C type for {rust}"),
+        }
+    }
+
+    /// Translates the exported functions of `source` into C declarations.
+    fn header(source: &str) -> String {
+        let mut header =
+            String::from("// Generated from src/ffi.rs by `LIBPS_UPDATE_HEADER=1 cargo test`.\n\n");
+        header.push_str(PREAMBLE);
+        let _ = write!(
+            header,
+            "\n#define LIBPS_OK {OK}\n#define LIBPS_ERROR {ERROR}\n#define LIBPS_QUIT {QUIT}\n"
+        );
+
+        let mut docs = Vec::new();
+        let mut lines = source.lines().map(str::trim);
+        while let Some(line) = lines.next() {
+            if line == "#[cfg(test)]" {
+                break;
+            }
+            if let Some(doc) = line.strip_prefix("///") {
+                docs.push(doc);
+                continue;
+            }
+            if line == "#[no_mangle]" {
+                continue;
+            }
+            if !(line.starts_with("pub ") && line.contains("extern \"C\" fn"))
+                || line.starts_with("pub type")
+            {
+                docs.clear();
+                continue;
+            }
+
+            let mut signature = line.to_string();
+            while !signature.ends_with('{') {
+                signature.push_str(lines.next().unwrap_or("{"));
+            }
+
+            let (_, signature) = signature.split_once("fn ").unwrap();
+            let (name, rest) = signature.split_once('(').unwrap();
+            let (params, rest) = rest.rsplit_once(')').unwrap();
+            let ret = match rest.trim_end_matches('{').trim().strip_prefix("->") {
+                Some(ret) => c_type(ret.trim()),
+                None => "void ",
+            };
+            let params: Vec<String> = params
+                .split(',')
+                .filter(|param| !param.trim().is_empty())
+                .map(|param| {
+                    let (name, ty) = param.split_once(':').unwrap();
+                    format!("{}{}", c_type(ty.trim()), name.trim())
+                })
+                .collect();
+            let params = if params.is_empty() {
+                String::from("void")
+            } else {
+                params.join(", ")
+            };
+
+            header.push('\n');
+            for doc in docs.drain(..) {
+                let _ = writeln!(header, "//{doc}");
+            }
+            let _ = writeln!(header, "{ret}{name}({params});");
+        }
+
+        header.push_str(POSTAMBLE);
+        header
+    }
+
+    #[test]
+    fn test_header_is_current() {
+        let header = header(include_str!("ffi.rs"));
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/include/libps.h");
+        if fs::read_to_string(path).unwrap_or_default() == header {
+            return;
+        }
+
+        // Regenerating still fails the run, so a stale header can never pass
+        if env::var_os("LIBPS_UPDATE_HEADER").is_some() {
+            fs::write(path, &header).unwrap();
+            panic!("include/libps.h was out of date and has been regenerated");
+        }
+        panic!(
+            "include/libps.h is out of date, regenerate it with LIBPS_UPDATE_HEADER=1 cargo test"
+        );
+    }
+
+    unsafe extern "C" fn collect_output(user_data: *mut c_void, data: *const u8, len: usize) {
+        let output = &mut *(user_data as *mut Vec<u8>);
+        output.extend_from_slice(slice::from_raw_parts(data, len));
+    }
+
+    unsafe extern "C" fn collect_page(
+        user_data: *mut c_void,
+        number: i32,
+        width: f32,
+        height: f32,
+    ) {
+        let pages = &mut *(user_data as *mut Vec<(i32, f32, f32)>);
+        pages.push((number, width, height));
+    }
+
+    fn error_name(interpreter: *const LibpsInterpreter) -> Option<String> {
+        let name = unsafe { libps_error_name(interpreter) };
+        if name.is_null() {
+            return None;
+        }
+
+        Some(
+            unsafe { CStr::from_ptr(name) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+
+    #[test]
+    fn test_c_api() {
+        let mut output = Vec::<u8>::new();
+        let mut pages = Vec::<(i32, f32, f32)>::new();
+
+        unsafe {
+            let interpreter = libps_new();
+            let output_ptr = &mut output as *mut Vec<u8> as *mut c_void;
+            let pages_ptr = &mut pages as *mut Vec<(i32, f32, f32)> as *mut c_void;
+            assert_eq!(
+                OK,
+                libps_set_output(interpreter, Some(collect_output), output_ptr)
+            );
+            assert_eq!(
+                OK,
+                libps_set_page_handler(interpreter, Some(collect_page), pages_ptr)
+            );
+
+            let job = b"(hello) print showpage 2 add";
+            assert_eq!(OK, libps_push_int(interpreter, 40));
+            assert_eq!(OK, libps_run(interpreter, job.as_ptr(), job.len()));
+            assert_eq!(None, error_name(interpreter));
+
+            let mut value = 0;
+            assert_eq!(OK, libps_pop_int(interpreter, &mut value));
+            assert_eq!(42, value);
+            assert_eq!(ERROR, libps_pop_int(interpreter, &mut value));
+            assert_eq!(
+                Some(String::from("stackunderflow")),
+                error_name(interpreter)
+            );
+
+            let job = b"1 0 idiv";
+            assert_eq!(ERROR, libps_run(interpreter, job.as_ptr(), job.len()));
+            assert_eq!(
+                Some(String::from("undefinedresult")),
+                error_name(interpreter)
+            );
+
+            // Strings too long for the buffer stay on the stack
+            let string = b"text";
+            let mut buf = [0; 4];
+            let mut len = 0;
+            assert_eq!(
+                OK,
+                libps_push_string(interpreter, string.as_ptr(), string.len())
+            );
+            assert_eq!(OK, libps_peek_string_len(interpreter, &mut len));
+            assert_eq!(4, len);
+            assert_eq!(None, error_name(interpreter));
+            assert_eq!(
+                ERROR,
+                libps_pop_string(interpreter, buf.as_mut_ptr(), 3, &mut len)
+            );
+            assert_eq!(Some(String::from("rangecheck")), error_name(interpreter));
+            assert_eq!(
+                OK,
+                libps_pop_string(interpreter, buf.as_mut_ptr(), 4, &mut len)
+            );
+            assert_eq!(string, &buf);
+
+            // Quitting ends the job, not the host
+            let job = b"1 quit 2";
+            assert_eq!(QUIT, libps_run(interpreter, job.as_ptr(), job.len()));
+            assert_eq!(OK, libps_pop_int(interpreter, &mut value));
+            assert_eq!(1, value);
+            let job = b"3";
+            assert_eq!(OK, libps_run(interpreter, job.as_ptr(), job.len()));
+            assert_eq!(OK, libps_pop_int(interpreter, &mut value));
+            assert_eq!(3, value);
+
+            assert_eq!(ERROR, libps_push_int(ptr::null_mut(), 1));
+            libps_free(interpreter);
+        }
+
+        assert!(output.starts_with(b"hello"));
+        assert_eq!(vec![(1, 612.0, 792.0)], pages);
+    }
+
+    #[test]
+    fn test_poisoned_after_panic() {
+        unsafe {
+            let interpreter = libps_new();
+            assert_eq!(ERROR, call(interpreter, |_| panic!("broken invariant")));
+            assert_eq!(Some(String::from("unregistered")), error_name(interpreter));
+
+            // Later calls are refused rather than run on a broken interpreter
+            let job = b"1 2 add";
+            assert_eq!(ERROR, libps_run(interpreter, job.as_ptr(), job.len()));
+            assert_eq!(ERROR, libps_push_int(interpreter, 1));
+            assert_eq!(Some(String::from("unregistered")), error_name(interpreter));
+            libps_free(interpreter);
+        }
+    }
+}
@@ -2,17 +2,49 @@ use std::io;
 
 use crate::object::Mode;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct FileObject {
     cursor: usize,
     inner: Vec<u8>,
+    is_streaming: bool,
     mode: Mode,
 }
 
 impl FileObject {
+    pub fn new(inner: Vec<u8>, mode: Mode) -> Self {
+        Self {
+            cursor: 0,
+            inner,
+            is_streaming: false,
+            mode,
+        }
+    }
+
     pub fn mode(&self) -> Mode {
         self.mode
     }
+
+    pub(crate) fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub(crate) fn set_cursor(&mut self, cursor: usize) {
+        self.cursor = cursor;
+    }
+
+    /// The data that has not been read yet.
+    pub(crate) fn remaining(&self) -> &[u8] {
+        self.inner.get(self.cursor..).unwrap_or_default()
+    }
+
+    /// Whether more data may still be appended to the end of the file.
+    pub(crate) fn is_streaming(&self) -> bool {
+        self.is_streaming
+    }
+
+    pub(crate) fn set_streaming(&mut self, is_streaming: bool) {
+        self.is_streaming = is_streaming;
+    }
 }
 
 impl io::Read for FileObject {
@@ -75,6 +107,12 @@ impl io::Seek for FileObject {
 
 impl io::Write for FileObject {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Streamed data is only read forwards, so whatever has been read
+        // already is dropped rather than holding on to the whole job
+        if self.is_streaming {
+            self.inner.drain(..self.cursor.min(self.inner.len()));
+            self.cursor = 0;
+        }
         self.inner.extend_from_slice(buf);
 
         Ok(buf.len())
@@ -90,6 +128,7 @@ impl From<String> for FileObject {
         Self {
             cursor: 0,
             inner: value.bytes().collect(),
+            is_streaming: false,
             mode: Mode::default(),
         }
     }
@@ -100,6 +139,7 @@ impl From<Vec<u8>> for FileObject {
         Self {
             cursor: 0,
             inner: value,
+            is_streaming: false,
             mode: Mode::default(),
         }
     }
@@ -0,0 +1,174 @@
+use crate::{
+    array::ArrayObject,
+    context::Lock,
+    dictionary::{Cursor, DictionaryObject},
+    heap::{Handle, Heap},
+    lexer::Lexer,
+    object::Mode,
+    operator::OperatorObject,
+    Object, StringObject,
+};
+
+/// An entry on the execution stack.
+pub(crate) enum Frame {
+    /// An object waiting to be executed.
+    Object(Object),
+    /// A procedure body and the index of its next element.
+    Procedure(Handle<ArrayObject>, usize),
+    /// A file or string being scanned for tokens, alongside the object that
+    /// is being executed.
+    Lexer(Object, Lexer),
+    /// The boundary established by `stopped`.
+    Stopped,
+    /// An active `loop`, `repeat`, `for` or `forall`.
+    Loop(LoopFrame),
+    /// A lock held by `monitor`, released once the frame is popped.
+    Monitor(Handle<Lock>),
+}
+
+pub(crate) enum LoopFrame {
+    For {
+        control: f64,
+        increment: f64,
+        limit: f64,
+        is_int: bool,
+        proc: Object,
+    },
+    ForAll {
+        items: ForAllItems,
+        proc: Object,
+    },
+    Loop(Object),
+    Repeat(usize, Object),
+}
+
+/// The container `forall` walks and how far it has got. Elements are read
+/// as the loop reaches them, so the body sees changes to later ones.
+pub(crate) enum ForAllItems {
+    Array(Handle<ArrayObject>, usize),
+    Dictionary(Handle<DictionaryObject>, Cursor),
+    String(Handle<StringObject>, usize),
+}
+
+impl ForAllItems {
+    fn next(&mut self, heap: &Heap) -> Option<Vec<Object>> {
+        match self {
+            ForAllItems::Array(arr, index) => {
+                let obj = *heap.get(*arr).value().get(*index)?;
+                *index += 1;
+
+                Some(vec![obj])
+            },
+            ForAllItems::Dictionary(dict, cursor) => {
+                let (key, value) = heap.get(*dict).next_entry(cursor)?;
+
+                Some(vec![key, value])
+            },
+            ForAllItems::String(string, index) => {
+                let ch = *heap.get(*string).value().get(*index)?;
+                *index += 1;
+
+                Some(vec![Object::Integer(i32::from(ch))])
+            },
+        }
+    }
+
+    fn container(&self) -> Object {
+        match self {
+            ForAllItems::Array(arr, _) => Object::Array(*arr),
+            ForAllItems::Dictionary(dict, _) => Object::Dictionary(*dict),
+            ForAllItems::String(string, _) => Object::String(*string),
+        }
+    }
+}
+
+impl Frame {
+    pub fn is_loop(&self) -> bool {
+        matches!(self, Frame::Loop(..))
+    }
+
+    /// The object that represents this frame to `execstack`. Partially
+    /// executed procedures are represented by their remaining elements.
+    pub fn to_object(&self, heap: &mut Heap) -> Object {
+        match self {
+            Frame::Object(obj) | Frame::Lexer(obj, _) => *obj,
+            Frame::Procedure(proc, 0) => Object::Array(*proc),
+            Frame::Procedure(proc, index) => {
+                let proc = heap.get(*proc);
+                let remaining = proc.value().get(*index..).unwrap_or_default().to_vec();
+                let remaining = ArrayObject::new(remaining, proc.access(), proc.mode());
+
+                Object::Array(heap.alloc(remaining))
+            },
+            Frame::Stopped => operator(OperatorObject::Stopped),
+            Frame::Loop(LoopFrame::For { .. }) => operator(OperatorObject::For),
+            Frame::Loop(LoopFrame::ForAll { .. }) => operator(OperatorObject::ForAll),
+            Frame::Loop(LoopFrame::Loop(_)) => operator(OperatorObject::Loop),
+            Frame::Loop(LoopFrame::Repeat(..)) => operator(OperatorObject::Repeat),
+            Frame::Monitor(_) => operator(OperatorObject::Monitor),
+        }
+    }
+
+    /// Every object the frame holds on to, which must be kept alive.
+    pub fn objects(&self) -> Vec<Object> {
+        match self {
+            Frame::Object(obj) => vec![*obj],
+            Frame::Procedure(proc, _) => vec![Object::Array(*proc)],
+            Frame::Lexer(obj, lexer) => [*obj].into_iter().chain(lexer.objects()).collect(),
+            Frame::Stopped => Vec::new(),
+            Frame::Loop(LoopFrame::For { proc, .. })
+            | Frame::Loop(LoopFrame::Loop(proc))
+            | Frame::Loop(LoopFrame::Repeat(_, proc)) => vec![*proc],
+            Frame::Loop(LoopFrame::ForAll { items, proc }) => vec![items.container(), *proc],
+            Frame::Monitor(lock) => vec![Object::Lock(*lock)],
+        }
+    }
+}
+
+fn operator(operator: OperatorObject) -> Object {
+    Object::Operator((operator, Mode::Executable))
+}
+
+impl LoopFrame {
+    /// Advances the loop, returning the operands and procedure for the next
+    /// iteration, or `None` once the loop has finished.
+    pub fn next_iteration(&mut self, heap: &Heap) -> Option<(Vec<Object>, Object)> {
+        match self {
+            LoopFrame::For {
+                control,
+                increment,
+                limit,
+                is_int,
+                proc,
+            } => {
+                let is_done = if *increment >= 0.0 {
+                    *control > *limit
+                } else {
+                    *control < *limit
+                };
+                if is_done {
+                    return None;
+                }
+
+                let value = if *is_int {
+                    Object::Integer(*control as i32)
+                } else {
+                    Object::Real(*control as f32)
+                };
+                *control += *increment;
+
+                Some((vec![value], *proc))
+            },
+            LoopFrame::ForAll { items, proc } => items.next(heap).map(|item| (item, *proc)),
+            LoopFrame::Loop(proc) => Some((Vec::new(), *proc)),
+            LoopFrame::Repeat(count, proc) => {
+                if *count == 0 {
+                    return None;
+                }
+                *count -= 1;
+
+                Some((Vec::new(), *proc))
+            },
+        }
+    }
+}
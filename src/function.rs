@@ -0,0 +1,1363 @@
+use std::io::Read;
+
+use crate::{
+    array::ArrayObject,
+    dictionary::DictionaryObject,
+    heap::{Handle, Heap},
+    literal_name,
+    visit::Visits,
+    Error, ErrorKind, FromObject, Object,
+};
+
+// Matches the operand stack limit the calculator subset is specified with
+const MAX_CALCULATOR_STACK: usize = 100;
+
+/// A PostScript function dictionary, validated and ready to evaluate. Inputs are clipped to
+/// the function's domain and outputs to its range.
+#[derive(Debug)]
+pub struct Function {
+    domain: Vec<(f64, f64)>,
+    kind: FunctionKind,
+    range: Option<Vec<(f64, f64)>>,
+}
+
+#[derive(Debug)]
+enum FunctionKind {
+    Sampled(Sampled),
+    Exponential {
+        c0: Vec<f64>,
+        c1: Vec<f64>,
+        n: f64,
+    },
+    Stitching {
+        bounds: Vec<f64>,
+        encode: Vec<(f64, f64)>,
+        functions: Vec<Function>,
+    },
+    Calculator(Vec<Instruction>),
+}
+
+impl Function {
+    /// The number of values `evaluate` takes.
+    pub fn inputs(&self) -> usize {
+        self.domain.len()
+    }
+
+    /// The number of values `evaluate` produces.
+    pub fn outputs(&self) -> usize {
+        match &self.kind {
+            FunctionKind::Exponential { c0, .. } => c0.len(),
+            FunctionKind::Stitching { functions, .. } => functions[0].outputs(),
+            FunctionKind::Sampled(_) | FunctionKind::Calculator(_) => {
+                self.range.as_ref().map_or(0, Vec::len)
+            },
+        }
+    }
+
+    /// Computes the function at `input` into `output`, whose lengths must
+    /// match `inputs` and `outputs`.
+    pub fn evaluate(&self, input: &[f64], output: &mut [f64]) -> crate::Result<()> {
+        if input.len() != self.inputs() {
+            return Err(Error::new(
+                ErrorKind::RangeCheck,
+                format!("expected {} inputs", self.inputs()),
+            ));
+        }
+        if output.len() != self.outputs() {
+            return Err(Error::new(
+                ErrorKind::RangeCheck,
+                format!("expected {} outputs", self.outputs()),
+            ));
+        }
+
+        let input: Vec<f64> = input
+            .iter()
+            .zip(&self.domain)
+            .map(|(x, domain)| clip(*x, *domain))
+            .collect();
+
+        match &self.kind {
+            FunctionKind::Sampled(sampled) => sampled.evaluate(&self.domain, &input, output),
+            FunctionKind::Exponential { c0, c1, n } => {
+                let x = input[0].powf(*n);
+                for (y, (c0, c1)) in output.iter_mut().zip(c0.iter().zip(c1)) {
+                    *y = c0 + x * (c1 - c0);
+                }
+            },
+            FunctionKind::Stitching {
+                bounds,
+                encode,
+                functions,
+            } => {
+                let x = input[0];
+                let (domain_min, domain_max) = self.domain[0];
+
+                // Each subdomain is closed below and open above, except the last
+                let i = bounds.partition_point(|bound| *bound <= x);
+                let low = if i == 0 { domain_min } else { bounds[i - 1] };
+                let high = bounds.get(i).copied().unwrap_or(domain_max);
+
+                let x = interpolate(x, (low, high), encode[i]);
+                functions[i].evaluate(&[x], output)?;
+            },
+            FunctionKind::Calculator(program) => {
+                let mut stack = input.into_iter().map(Value::Real).collect();
+                execute(program, &mut stack)?;
+
+                let Some(start) = stack.len().checked_sub(output.len()) else {
+                    return Err(Error::from(ErrorKind::StackUnderflow));
+                };
+                for (y, value) in output.iter_mut().zip(&stack[start..]) {
+                    *y = value.real()?;
+                }
+            },
+        }
+
+        if let Some(range) = &self.range {
+            for (y, range) in output.iter_mut().zip(range) {
+                *y = clip(*y, *range);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Hosts build functions by reading a function dictionary out of the VM, for
+/// example with `Interpreter::pop_value` or `Interpreter::load_value`.
+impl FromObject for Function {
+    fn from_object(obj: Object, heap: &Heap) -> crate::Result<Self> {
+        let Object::Dictionary(dict) = obj else {
+            return Err(Error::new(ErrorKind::TypeCheck, "expected dictionary"));
+        };
+
+        Self::new(heap.get(dict), heap)
+    }
+}
+
+impl Function {
+    pub(crate) fn new(dict: &DictionaryObject, heap: &Heap) -> crate::Result<Self> {
+        Self::build(dict, heap, &mut Visits::default())
+    }
+
+    fn build(dict: &DictionaryObject, heap: &Heap, visits: &mut Visits) -> crate::Result<Self> {
+        if !dict.access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        let function_type = required(dict, "FunctionType")?.into_int()?;
+        let domain = required_intervals(dict, heap, "Domain")?;
+        let range = intervals(dict, heap, "Range")?;
+
+        let kind = match function_type {
+            0 => {
+                let range = range
+                    .as_deref()
+                    .ok_or(Error::new(ErrorKind::Undefined, "Range"))?;
+
+                FunctionKind::Sampled(Sampled::new(dict, heap, &domain, range)?)
+            },
+            2 => exponential(dict, heap, &domain)?,
+            3 => stitching(dict, heap, &domain, visits)?,
+            4 => {
+                if range.is_none() {
+                    return Err(Error::new(ErrorKind::Undefined, "Range"));
+                }
+
+                let Some(Object::Array(program)) = dict.get(&literal_name("Function")) else {
+                    return Err(Error::new(ErrorKind::TypeCheck, "expected procedure"));
+                };
+
+                FunctionKind::Calculator(compile(*program, heap, visits)?)
+            },
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::RangeCheck,
+                    format!("unsupported FunctionType {function_type}"),
+                ))
+            },
+        };
+
+        let function = Self {
+            domain,
+            kind,
+            range,
+        };
+
+        if function
+            .range
+            .as_ref()
+            .is_some_and(|range| range.len() != function.outputs())
+        {
+            return Err(Error::new(
+                ErrorKind::RangeCheck,
+                "Range does not match the number of outputs",
+            ));
+        }
+
+        Ok(function)
+    }
+}
+
+#[derive(Debug)]
+struct Sampled {
+    encode: Vec<(f64, f64)>,
+    outputs: usize,
+    /// Samples with Decode already applied, first input varying fastest.
+    samples: Vec<f64>,
+    size: Vec<usize>,
+}
+
+impl Sampled {
+    fn new(
+        dict: &DictionaryObject,
+        heap: &Heap,
+        domain: &[(f64, f64)],
+        range: &[(f64, f64)],
+    ) -> crate::Result<Self> {
+        let size = numbers(dict, heap, "Size")?.ok_or(Error::new(ErrorKind::Undefined, "Size"))?;
+        if size.len() != domain.len() {
+            return Err(Error::new(
+                ErrorKind::RangeCheck,
+                "Size does not match Domain",
+            ));
+        }
+        let size = size
+            .into_iter()
+            .map(|size| {
+                if size.fract() != 0.0 || size < 1.0 {
+                    return Err(Error::new(
+                        ErrorKind::RangeCheck,
+                        "Size must contain positive integers",
+                    ));
+                }
+
+                Ok(size as usize)
+            })
+            .collect::<crate::Result<Vec<usize>>>()?;
+
+        let bits_per_sample = required(dict, "BitsPerSample")?.into_int()?;
+        if ![1, 2, 4, 8, 12, 16, 24, 32].contains(&bits_per_sample) {
+            return Err(Error::new(ErrorKind::RangeCheck, "invalid BitsPerSample"));
+        }
+
+        // Order 3 asks for cubic spline interpolation, which consumers are allowed to
+        // approximate linearly
+        match dict.get(&literal_name("Order")) {
+            None | Some(Object::Integer(1 | 3)) => {},
+            Some(Object::Integer(_)) => {
+                return Err(Error::new(ErrorKind::RangeCheck, "Order must be 1 or 3"))
+            },
+            Some(_) => return Err(Error::new(ErrorKind::TypeCheck, "expected integer")),
+        }
+
+        let encode = match intervals(dict, heap, "Encode")? {
+            Some(encode) => encode,
+            None => size.iter().map(|size| (0.0, (size - 1) as f64)).collect(),
+        };
+        if encode.len() != domain.len() {
+            return Err(Error::new(
+                ErrorKind::RangeCheck,
+                "Encode does not match Domain",
+            ));
+        }
+
+        let decode = intervals(dict, heap, "Decode")?.unwrap_or_else(|| range.to_vec());
+        if decode.len() != range.len() {
+            return Err(Error::new(
+                ErrorKind::RangeCheck,
+                "Decode does not match Range",
+            ));
+        }
+
+        let count = size
+            .iter()
+            .try_fold(range.len(), |count, size| count.checked_mul(*size))
+            .ok_or(Error::from(ErrorKind::LimitCheck))?;
+        let data = data_source(dict, heap)?;
+        let bits_per_sample = bits_per_sample as usize;
+        if data.len().saturating_mul(8) / bits_per_sample < count {
+            return Err(Error::new(
+                ErrorKind::RangeCheck,
+                "DataSource holds too few samples",
+            ));
+        }
+
+        let max_sample = ((1u64 << bits_per_sample) - 1) as f64;
+        let samples = (0..count)
+            .map(|i| {
+                let sample = read_sample(&data, i * bits_per_sample, bits_per_sample) as f64;
+
+                interpolate(sample, (0.0, max_sample), decode[i % range.len()])
+            })
+            .collect();
+
+        Ok(Self {
+            encode,
+            outputs: range.len(),
+            samples,
+            size,
+        })
+    }
+
+    fn evaluate(&self, domain: &[(f64, f64)], input: &[f64], output: &mut [f64]) {
+        let mut base = 0;
+        let mut stride = self.outputs;
+        // Only the inputs that fall between two samples contribute corners
+        let mut between = Vec::new();
+
+        for ((x, domain), (encode, size)) in input
+            .iter()
+            .zip(domain)
+            .zip(self.encode.iter().zip(&self.size))
+        {
+            let e = clip(interpolate(*x, *domain, *encode), (0.0, (size - 1) as f64));
+            let index = e.floor();
+            let fraction = e - index;
+
+            base += index as usize * stride;
+            if fraction > 0.0 {
+                between.push((stride, fraction));
+            }
+            stride *= size;
+        }
+
+        output.fill(0.0);
+        for corner in 0..1usize << between.len() {
+            let mut offset = base;
+            let mut weight = 1.0;
+
+            for (bit, (stride, fraction)) in between.iter().enumerate() {
+                if corner & (1 << bit) == 0 {
+                    weight *= 1.0 - fraction;
+                } else {
+                    offset += stride;
+                    weight *= fraction;
+                }
+            }
+
+            for (y, sample) in output.iter_mut().zip(&self.samples[offset..]) {
+                *y += weight * sample;
+            }
+        }
+    }
+}
+
+fn exponential(
+    dict: &DictionaryObject,
+    heap: &Heap,
+    domain: &[(f64, f64)],
+) -> crate::Result<FunctionKind> {
+    let [(domain_min, domain_max)] = domain else {
+        return Err(Error::new(ErrorKind::RangeCheck, "expected a single input"));
+    };
+
+    let c0 = numbers(dict, heap, "C0")?.unwrap_or(vec![0.0]);
+    let c1 = numbers(dict, heap, "C1")?.unwrap_or(vec![1.0]);
+    if c0.len() != c1.len() {
+        return Err(Error::new(
+            ErrorKind::RangeCheck,
+            "C0 and C1 must be the same length",
+        ));
+    }
+
+    let n = f64::from(required(dict, "N")?.into_real()?);
+    if n.fract() != 0.0 && *domain_min < 0.0 {
+        return Err(Error::new(
+            ErrorKind::RangeCheck,
+            "Domain must be non-negative for a non-integer N",
+        ));
+    }
+    if n < 0.0 && *domain_min <= 0.0 && *domain_max >= 0.0 {
+        return Err(Error::new(
+            ErrorKind::RangeCheck,
+            "Domain must exclude 0 for a negative N",
+        ));
+    }
+
+    Ok(FunctionKind::Exponential { c0, c1, n })
+}
+
+fn stitching(
+    dict: &DictionaryObject,
+    heap: &Heap,
+    domain: &[(f64, f64)],
+    visits: &mut Visits,
+) -> crate::Result<FunctionKind> {
+    let [(domain_min, domain_max)] = domain else {
+        return Err(Error::new(ErrorKind::RangeCheck, "expected a single input"));
+    };
+
+    let Some(Object::Array(subfunctions)) = dict.get(&literal_name("Functions")) else {
+        return Err(Error::new(ErrorKind::TypeCheck, "expected array"));
+    };
+    let subfunctions = heap.get(*subfunctions);
+    if !subfunctions.access().is_readable() {
+        return Err(Error::from(ErrorKind::InvalidAccess));
+    }
+    if subfunctions.is_empty() {
+        return Err(Error::new(ErrorKind::RangeCheck, "Functions is empty"));
+    }
+
+    let functions = subfunctions
+        .iter()
+        .map(|obj| {
+            let Object::Dictionary(subfunction) = obj else {
+                return Err(Error::new(ErrorKind::TypeCheck, "expected dictionary"));
+            };
+
+            let Some(mut visit) = visits.enter(*subfunction) else {
+                return Err(Error::new(
+                    ErrorKind::RangeCheck,
+                    "function refers to itself",
+                ));
+            };
+
+            Function::build(heap.get(*subfunction), heap, &mut visit)
+        })
+        .collect::<crate::Result<Vec<Function>>>()?;
+
+    let outputs = functions[0].outputs();
+    if functions
+        .iter()
+        .any(|function| function.inputs() != 1 || function.outputs() != outputs)
+    {
+        return Err(Error::new(
+            ErrorKind::RangeCheck,
+            "Functions must take one input and agree on outputs",
+        ));
+    }
+
+    let bounds =
+        numbers(dict, heap, "Bounds")?.ok_or(Error::new(ErrorKind::Undefined, "Bounds"))?;
+    if bounds.len() != functions.len() - 1 {
+        return Err(Error::new(
+            ErrorKind::RangeCheck,
+            "expected one fewer Bounds than Functions",
+        ));
+    }
+    let is_ordered = [*domain_min]
+        .iter()
+        .chain(&bounds)
+        .zip(bounds.iter().chain([domain_max]))
+        .all(|(low, high)| low <= high);
+    if !is_ordered {
+        return Err(Error::new(
+            ErrorKind::RangeCheck,
+            "Bounds must increase within Domain",
+        ));
+    }
+
+    let encode = required_intervals(dict, heap, "Encode")?;
+    if encode.len() != functions.len() {
+        return Err(Error::new(
+            ErrorKind::RangeCheck,
+            "Encode does not match Functions",
+        ));
+    }
+
+    Ok(FunctionKind::Stitching {
+        bounds,
+        encode,
+        functions,
+    })
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Value {
+    Boolean(bool),
+    Integer(i32),
+    Real(f64),
+}
+
+impl Value {
+    fn bool(self) -> crate::Result<bool> {
+        match self {
+            Value::Boolean(b) => Ok(b),
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected boolean")),
+        }
+    }
+
+    fn int(self) -> crate::Result<i32> {
+        match self {
+            Value::Integer(i) => Ok(i),
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected int")),
+        }
+    }
+
+    fn real(self) -> crate::Result<f64> {
+        match self {
+            Value::Integer(i) => Ok(f64::from(i)),
+            Value::Real(r) => Ok(r),
+            Value::Boolean(_) => Err(Error::new(ErrorKind::TypeCheck, "expected number")),
+        }
+    }
+
+    fn usize(self) -> crate::Result<usize> {
+        usize::try_from(self.int()?).or(Err(Error::new(
+            ErrorKind::RangeCheck,
+            "expected non-negative int",
+        )))
+    }
+}
+
+#[derive(Debug)]
+enum Instruction {
+    If(Vec<Instruction>),
+    IfElse(Vec<Instruction>, Vec<Instruction>),
+    Operator(CalculatorOperator),
+    Push(Value),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum CalculatorOperator {
+    // Arithmetic
+    Abs,
+    Add,
+    Atan,
+    Ceiling,
+    Cos,
+    Cvi,
+    Cvr,
+    Div,
+    Exp,
+    Floor,
+    Idiv,
+    Ln,
+    Log,
+    Mod,
+    Mul,
+    Neg,
+    Round,
+    Sin,
+    Sqrt,
+    Sub,
+    Truncate,
+
+    // Relational, boolean and bitwise
+    And,
+    Bitshift,
+    Eq,
+    Ge,
+    Gt,
+    Le,
+    Lt,
+    Ne,
+    Not,
+    Or,
+    Xor,
+
+    // Stack
+    Copy,
+    Dup,
+    Exch,
+    Index,
+    Pop,
+    Roll,
+}
+
+const CALCULATOR_OPERATORS: &[(&str, CalculatorOperator)] = &[
+    ("abs", CalculatorOperator::Abs),
+    ("add", CalculatorOperator::Add),
+    ("and", CalculatorOperator::And),
+    ("atan", CalculatorOperator::Atan),
+    ("bitshift", CalculatorOperator::Bitshift),
+    ("ceiling", CalculatorOperator::Ceiling),
+    ("copy", CalculatorOperator::Copy),
+    ("cos", CalculatorOperator::Cos),
+    ("cvi", CalculatorOperator::Cvi),
+    ("cvr", CalculatorOperator::Cvr),
+    ("div", CalculatorOperator::Div),
+    ("dup", CalculatorOperator::Dup),
+    ("eq", CalculatorOperator::Eq),
+    ("exch", CalculatorOperator::Exch),
+    ("exp", CalculatorOperator::Exp),
+    ("floor", CalculatorOperator::Floor),
+    ("ge", CalculatorOperator::Ge),
+    ("gt", CalculatorOperator::Gt),
+    ("idiv", CalculatorOperator::Idiv),
+    ("index", CalculatorOperator::Index),
+    ("le", CalculatorOperator::Le),
+    ("ln", CalculatorOperator::Ln),
+    ("log", CalculatorOperator::Log),
+    ("lt", CalculatorOperator::Lt),
+    ("mod", CalculatorOperator::Mod),
+    ("mul", CalculatorOperator::Mul),
+    ("ne", CalculatorOperator::Ne),
+    ("neg", CalculatorOperator::Neg),
+    ("not", CalculatorOperator::Not),
+    ("or", CalculatorOperator::Or),
+    ("pop", CalculatorOperator::Pop),
+    ("roll", CalculatorOperator::Roll),
+    ("round", CalculatorOperator::Round),
+    ("sin", CalculatorOperator::Sin),
+    ("sqrt", CalculatorOperator::Sqrt),
+    ("sub", CalculatorOperator::Sub),
+    ("truncate", CalculatorOperator::Truncate),
+    ("xor", CalculatorOperator::Xor),
+];
+
+// Translates a procedure into calculator instructions. Procedures may only appear as the
+// operands of a directly following if or ifelse.
+fn compile(
+    proc: Handle<ArrayObject>,
+    heap: &Heap,
+    visits: &mut Visits,
+) -> crate::Result<Vec<Instruction>> {
+    let Some(mut visit) = visits.enter(proc) else {
+        return Err(Error::new(
+            ErrorKind::RangeCheck,
+            "procedure refers to itself",
+        ));
+    };
+    let proc = heap.get(proc);
+
+    if !proc.mode().is_executable() {
+        return Err(Error::new(ErrorKind::TypeCheck, "expected procedure"));
+    }
+
+    let mut program = Vec::new();
+    let mut procs: Vec<Vec<Instruction>> = Vec::new();
+
+    for obj in proc.iter() {
+        let name = match obj {
+            Object::Boolean(b) => {
+                program.push(Instruction::Push(Value::Boolean(*b)));
+                continue;
+            },
+            Object::Integer(i) => {
+                program.push(Instruction::Push(Value::Integer(*i)));
+                continue;
+            },
+            Object::Real(r) => {
+                program.push(Instruction::Push(Value::Real(f64::from(*r))));
+                continue;
+            },
+            Object::Array(arr) if heap.get(*arr).mode().is_executable() => {
+                procs.push(compile(*arr, heap, &mut visit)?);
+                continue;
+            },
+            Object::Name(name) if name.mode().is_executable() => name.to_string(),
+            Object::Operator((op, _)) => op.to_string(),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::TypeCheck,
+                    format!(
+                        "{:?} is not allowed in a calculator function",
+                        heap.format(obj)
+                    ),
+                ))
+            },
+        };
+
+        let instruction = match (name.as_str(), procs.len()) {
+            ("if", 1) => Instruction::If(procs.remove(0)),
+            ("ifelse", 2) => {
+                let otherwise = procs.remove(1);
+
+                Instruction::IfElse(procs.remove(0), otherwise)
+            },
+            ("true", 0) => Instruction::Push(Value::Boolean(true)),
+            ("false", 0) => Instruction::Push(Value::Boolean(false)),
+            (name, 0) => {
+                let Some((_, op)) = CALCULATOR_OPERATORS.iter().find(|(key, _)| *key == name)
+                else {
+                    return Err(Error::new(
+                        ErrorKind::Undefined,
+                        format!("{name} is not a calculator operator"),
+                    ));
+                };
+
+                Instruction::Operator(*op)
+            },
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::SyntaxError,
+                    "procedures must be followed by if or ifelse",
+                ))
+            },
+        };
+        program.push(instruction);
+    }
+
+    if !procs.is_empty() {
+        return Err(Error::new(
+            ErrorKind::SyntaxError,
+            "procedures must be followed by if or ifelse",
+        ));
+    }
+
+    Ok(program)
+}
+
+fn execute(program: &[Instruction], stack: &mut Vec<Value>) -> crate::Result<()> {
+    for instruction in program {
+        match instruction {
+            Instruction::If(proc) => {
+                if pop(stack)?.bool()? {
+                    execute(proc, stack)?;
+                }
+            },
+            Instruction::IfElse(proc, otherwise) => {
+                if pop(stack)?.bool()? {
+                    execute(proc, stack)?;
+                } else {
+                    execute(otherwise, stack)?;
+                }
+            },
+            Instruction::Operator(op) => apply(*op, stack)?,
+            Instruction::Push(value) => push(stack, *value)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn apply(op: CalculatorOperator, stack: &mut Vec<Value>) -> crate::Result<()> {
+    let value = match op {
+        CalculatorOperator::Abs => match pop(stack)? {
+            Value::Integer(i) => integer_or_real(i.checked_abs(), f64::from(i).abs()),
+            value => Value::Real(value.real()?.abs()),
+        },
+        CalculatorOperator::Add => arithmetic(stack, i32::checked_add, |a, b| a + b)?,
+        CalculatorOperator::Atan => {
+            let den = pop(stack)?.real()?;
+            let num = pop(stack)?.real()?;
+            if num == 0.0 && den == 0.0 {
+                return Err(Error::from(ErrorKind::UndefinedResult));
+            }
+
+            Value::Real(num.atan2(den).to_degrees().rem_euclid(360.0))
+        },
+        CalculatorOperator::Ceiling => rounding(stack, f64::ceil)?,
+        CalculatorOperator::Cos => Value::Real(pop(stack)?.real()?.to_radians().cos()),
+        CalculatorOperator::Cvi => match pop(stack)? {
+            Value::Integer(i) => Value::Integer(i),
+            value => {
+                let r = value.real()?.trunc();
+                if r < f64::from(i32::MIN) || r > f64::from(i32::MAX) {
+                    return Err(Error::from(ErrorKind::RangeCheck));
+                }
+
+                Value::Integer(r as i32)
+            },
+        },
+        CalculatorOperator::Cvr => Value::Real(pop(stack)?.real()?),
+        CalculatorOperator::Div => {
+            let den = pop(stack)?.real()?;
+            let num = pop(stack)?.real()?;
+            if den == 0.0 {
+                return Err(Error::from(ErrorKind::UndefinedResult));
+            }
+
+            Value::Real(num / den)
+        },
+        CalculatorOperator::Exp => {
+            let exponent = pop(stack)?.real()?;
+            let base = pop(stack)?.real()?;
+            let result = base.powf(exponent);
+            if !result.is_finite() {
+                return Err(Error::from(ErrorKind::UndefinedResult));
+            }
+
+            Value::Real(result)
+        },
+        CalculatorOperator::Floor => rounding(stack, f64::floor)?,
+        CalculatorOperator::Idiv => {
+            let den = pop(stack)?.int()?;
+            let num = pop(stack)?.int()?;
+
+            Value::Integer(
+                num.checked_div(den)
+                    .ok_or(Error::from(ErrorKind::UndefinedResult))?,
+            )
+        },
+        CalculatorOperator::Ln => Value::Real(logarithm(stack, f64::ln)?),
+        CalculatorOperator::Log => Value::Real(logarithm(stack, f64::log10)?),
+        CalculatorOperator::Mod => {
+            let den = pop(stack)?.int()?;
+            let num = pop(stack)?.int()?;
+
+            Value::Integer(
+                num.checked_rem(den)
+                    .ok_or(Error::from(ErrorKind::UndefinedResult))?,
+            )
+        },
+        CalculatorOperator::Mul => arithmetic(stack, i32::checked_mul, |a, b| a * b)?,
+        CalculatorOperator::Neg => match pop(stack)? {
+            Value::Integer(i) => integer_or_real(i.checked_neg(), -f64::from(i)),
+            value => Value::Real(-value.real()?),
+        },
+        // Halves round up, as they do for the round operator
+        CalculatorOperator::Round => rounding(stack, |r| (r + 0.5).floor())?,
+        CalculatorOperator::Sin => Value::Real(pop(stack)?.real()?.to_radians().sin()),
+        CalculatorOperator::Sqrt => {
+            let r = pop(stack)?.real()?;
+            if r < 0.0 {
+                return Err(Error::from(ErrorKind::RangeCheck));
+            }
+
+            Value::Real(r.sqrt())
+        },
+        CalculatorOperator::Sub => arithmetic(stack, i32::checked_sub, |a, b| a - b)?,
+        CalculatorOperator::Truncate => rounding(stack, f64::trunc)?,
+        CalculatorOperator::And => logical(stack, |a, b| a & b, |a, b| a & b)?,
+        CalculatorOperator::Bitshift => {
+            let shift = pop(stack)?.int()?;
+            let i = pop(stack)?.int()? as u32;
+
+            let shifted = match shift {
+                0..=31 => i << shift,
+                -31..=-1 => i >> -shift,
+                _ => 0,
+            };
+
+            Value::Integer(shifted as i32)
+        },
+        CalculatorOperator::Eq => {
+            let rhs = pop(stack)?;
+            let lhs = pop(stack)?;
+
+            Value::Boolean(equals(lhs, rhs))
+        },
+        CalculatorOperator::Ge => comparison(stack, |a, b| a >= b)?,
+        CalculatorOperator::Gt => comparison(stack, |a, b| a > b)?,
+        CalculatorOperator::Le => comparison(stack, |a, b| a <= b)?,
+        CalculatorOperator::Lt => comparison(stack, |a, b| a < b)?,
+        CalculatorOperator::Ne => {
+            let rhs = pop(stack)?;
+            let lhs = pop(stack)?;
+
+            Value::Boolean(!equals(lhs, rhs))
+        },
+        CalculatorOperator::Not => match pop(stack)? {
+            Value::Boolean(b) => Value::Boolean(!b),
+            Value::Integer(i) => Value::Integer(!i),
+            Value::Real(_) => {
+                return Err(Error::new(ErrorKind::TypeCheck, "expected boolean or int"))
+            },
+        },
+        CalculatorOperator::Or => logical(stack, |a, b| a | b, |a, b| a | b)?,
+        CalculatorOperator::Xor => logical(stack, |a, b| a ^ b, |a, b| a ^ b)?,
+        CalculatorOperator::Copy => {
+            let n = pop(stack)?.usize()?;
+            let Some(start) = stack.len().checked_sub(n) else {
+                return Err(Error::from(ErrorKind::RangeCheck));
+            };
+            if stack.len() + n > MAX_CALCULATOR_STACK {
+                return Err(Error::from(ErrorKind::StackOverflow));
+            }
+
+            stack.extend_from_within(start..);
+
+            return Ok(());
+        },
+        CalculatorOperator::Dup => {
+            let value = pop(stack)?;
+            push(stack, value)?;
+
+            value
+        },
+        CalculatorOperator::Exch => {
+            let first = pop(stack)?;
+            let second = pop(stack)?;
+            push(stack, first)?;
+
+            second
+        },
+        CalculatorOperator::Index => {
+            let n = pop(stack)?.usize()?;
+            let Some(index) = stack.len().checked_sub(n + 1) else {
+                return Err(Error::from(ErrorKind::RangeCheck));
+            };
+
+            stack[index]
+        },
+        CalculatorOperator::Pop => {
+            pop(stack)?;
+
+            return Ok(());
+        },
+        CalculatorOperator::Roll => {
+            let j = pop(stack)?.int()?;
+            let n = pop(stack)?.usize()?;
+            let Some(start) = stack.len().checked_sub(n) else {
+                return Err(Error::from(ErrorKind::RangeCheck));
+            };
+
+            if n > 0 {
+                let j = j.rem_euclid(n as i32) as usize;
+                stack[start..].rotate_right(j);
+            }
+
+            return Ok(());
+        },
+    };
+
+    push(stack, value)
+}
+
+fn pop(stack: &mut Vec<Value>) -> crate::Result<Value> {
+    stack.pop().ok_or(Error::from(ErrorKind::StackUnderflow))
+}
+
+fn push(stack: &mut Vec<Value>, value: Value) -> crate::Result<()> {
+    if stack.len() >= MAX_CALCULATOR_STACK {
+        return Err(Error::from(ErrorKind::StackOverflow));
+    }
+
+    stack.push(value);
+
+    Ok(())
+}
+
+fn integer_or_real(i: Option<i32>, r: f64) -> Value {
+    i.map_or(Value::Real(r), Value::Integer)
+}
+
+// Integer arithmetic that overflows falls back to reals, as it does for the
+// arithmetic operators
+fn arithmetic(
+    stack: &mut Vec<Value>,
+    integer: fn(i32, i32) -> Option<i32>,
+    real: fn(f64, f64) -> f64,
+) -> crate::Result<Value> {
+    let rhs = pop(stack)?;
+    let lhs = pop(stack)?;
+
+    match (lhs, rhs) {
+        (Value::Integer(lhs), Value::Integer(rhs)) => Ok(integer_or_real(
+            integer(lhs, rhs),
+            real(f64::from(lhs), f64::from(rhs)),
+        )),
+        (lhs, rhs) => Ok(Value::Real(real(lhs.real()?, rhs.real()?))),
+    }
+}
+
+fn rounding(stack: &mut Vec<Value>, round: fn(f64) -> f64) -> crate::Result<Value> {
+    match pop(stack)? {
+        Value::Integer(i) => Ok(Value::Integer(i)),
+        value => Ok(Value::Real(round(value.real()?))),
+    }
+}
+
+fn logarithm(stack: &mut Vec<Value>, log: fn(f64) -> f64) -> crate::Result<f64> {
+    let r = pop(stack)?.real()?;
+    if r <= 0.0 {
+        return Err(Error::from(ErrorKind::RangeCheck));
+    }
+
+    Ok(log(r))
+}
+
+fn logical(
+    stack: &mut Vec<Value>,
+    boolean: fn(bool, bool) -> bool,
+    integer: fn(i32, i32) -> i32,
+) -> crate::Result<Value> {
+    let rhs = pop(stack)?;
+    let lhs = pop(stack)?;
+
+    match (lhs, rhs) {
+        (Value::Boolean(lhs), Value::Boolean(rhs)) => Ok(Value::Boolean(boolean(lhs, rhs))),
+        (Value::Integer(lhs), Value::Integer(rhs)) => Ok(Value::Integer(integer(lhs, rhs))),
+        _ => Err(Error::new(
+            ErrorKind::TypeCheck,
+            "expected booleans or ints",
+        )),
+    }
+}
+
+fn comparison(stack: &mut Vec<Value>, compare: fn(f64, f64) -> bool) -> crate::Result<Value> {
+    let rhs = pop(stack)?.real()?;
+    let lhs = pop(stack)?.real()?;
+
+    Ok(Value::Boolean(compare(lhs, rhs)))
+}
+
+fn equals(lhs: Value, rhs: Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Boolean(lhs), Value::Boolean(rhs)) => lhs == rhs,
+        (Value::Boolean(_), _) | (_, Value::Boolean(_)) => false,
+        (lhs, rhs) => lhs.real().ok() == rhs.real().ok(),
+    }
+}
+
+fn interpolate(x: f64, (x_min, x_max): (f64, f64), (y_min, y_max): (f64, f64)) -> f64 {
+    if x_max == x_min {
+        return y_min;
+    }
+
+    y_min + (x - x_min) * (y_max - y_min) / (x_max - x_min)
+}
+
+fn clip(x: f64, (min, max): (f64, f64)) -> f64 {
+    x.max(min).min(max)
+}
+
+// Samples are packed most significant bit first with no padding between them
+fn read_sample(data: &[u8], bit: usize, bits_per_sample: usize) -> u32 {
+    (bit..bit + bits_per_sample).fold(0, |sample, bit| {
+        let set = data[bit / 8] >> (7 - bit % 8) & 1;
+
+        (sample << 1) | u32::from(set)
+    })
+}
+
+fn data_source(dict: &DictionaryObject, heap: &Heap) -> crate::Result<Vec<u8>> {
+    match required(dict, "DataSource")? {
+        Object::String(string) => {
+            let string = heap.get(*string);
+            if !string.access().is_readable() {
+                return Err(Error::from(ErrorKind::InvalidAccess));
+            }
+
+            Ok(string.value().to_vec())
+        },
+        Object::File(file) => {
+            let mut data = Vec::new();
+            heap.get(*file)
+                .clone()
+                .read_to_end(&mut data)
+                .or(Err(Error::from(ErrorKind::IoError)))?;
+
+            Ok(data)
+        },
+        _ => Err(Error::new(ErrorKind::TypeCheck, "expected string or file")),
+    }
+}
+
+fn required<'a>(dict: &'a DictionaryObject, key: &str) -> crate::Result<&'a Object> {
+    dict.get(&literal_name(key))
+        .ok_or(Error::new(ErrorKind::Undefined, key))
+}
+
+fn numbers(dict: &DictionaryObject, heap: &Heap, key: &str) -> crate::Result<Option<Vec<f64>>> {
+    let Some(obj) = dict.get(&literal_name(key)) else {
+        return Ok(None);
+    };
+
+    let Object::Array(arr) = obj else {
+        return Err(Error::new(
+            ErrorKind::TypeCheck,
+            format!("expected array for {key}"),
+        ));
+    };
+    let arr = heap.get(*arr);
+    if !arr.access().is_readable() {
+        return Err(Error::from(ErrorKind::InvalidAccess));
+    }
+
+    arr.iter()
+        .map(|obj| obj.into_real().map(f64::from))
+        .collect::<crate::Result<Vec<f64>>>()
+        .map(Some)
+}
+
+// Reads an array of numbers as (min, max) pairs
+fn intervals(
+    dict: &DictionaryObject,
+    heap: &Heap,
+    key: &str,
+) -> crate::Result<Option<Vec<(f64, f64)>>> {
+    let Some(numbers) = numbers(dict, heap, key)? else {
+        return Ok(None);
+    };
+
+    if numbers.is_empty() || !numbers.len().is_multiple_of(2) {
+        return Err(Error::new(
+            ErrorKind::RangeCheck,
+            format!("{key} must hold pairs of numbers"),
+        ));
+    }
+
+    let intervals: Vec<(f64, f64)> = numbers.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+
+    // Encode and Decode may run backwards, but bounds on values may not
+    if matches!(key, "Domain" | "Range") && intervals.iter().any(|(min, max)| min > max) {
+        return Err(Error::new(
+            ErrorKind::RangeCheck,
+            format!("{key} minimums must not exceed maximums"),
+        ));
+    }
+
+    Ok(Some(intervals))
+}
+
+fn required_intervals(
+    dict: &DictionaryObject,
+    heap: &Heap,
+    key: &str,
+) -> crate::Result<Vec<(f64, f64)>> {
+    intervals(dict, heap, key)?.ok_or(Error::new(ErrorKind::Undefined, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lexer::Lexer,
+        object::{Access, Mode},
+        LanguageLevel, StringObject,
+    };
+
+    fn array(heap: &mut Heap, values: &[f64]) -> Object {
+        let objs = values
+            .iter()
+            .map(|value| Object::Real(*value as f32))
+            .collect();
+
+        Object::from(heap.alloc(ArrayObject::new(objs, Access::Unlimited, Mode::Literal)))
+    }
+
+    fn procedure(heap: &mut Heap, source: &str) -> Object {
+        let mut lexer = Lexer::new(source.to_string(), LanguageLevel::Level3);
+
+        lexer.next_object(heap).unwrap().unwrap()
+    }
+
+    fn function_dict(entries: Vec<(&str, Object)>) -> DictionaryObject {
+        entries
+            .into_iter()
+            .fold(DictionaryObject::default(), |mut dict, (key, obj)| {
+                dict.insert(literal_name(key), obj);
+                dict
+            })
+    }
+
+    fn evaluate(function: &Function, input: &[f64]) -> Vec<f64> {
+        let mut output = vec![0.0; function.outputs()];
+        function.evaluate(input, &mut output).unwrap();
+
+        output
+    }
+
+    fn sampled(
+        heap: &mut Heap,
+        size: &[f64],
+        bits_per_sample: i32,
+        data: Vec<u8>,
+    ) -> DictionaryObject {
+        function_dict(vec![
+            ("FunctionType", Object::Integer(0)),
+            ("Domain", array(heap, &[0.0, 1.0].repeat(size.len()))),
+            ("Range", array(heap, &[0.0, 1.0])),
+            ("Size", array(heap, size)),
+            ("BitsPerSample", Object::Integer(bits_per_sample)),
+            (
+                "DataSource",
+                Object::from(heap.alloc(StringObject::from(data))),
+            ),
+        ])
+    }
+
+    fn exponential(heap: &mut Heap, c0: &[f64], c1: &[f64], n: f64) -> DictionaryObject {
+        function_dict(vec![
+            ("FunctionType", Object::Integer(2)),
+            ("Domain", array(heap, &[0.0, 1.0])),
+            ("C0", array(heap, c0)),
+            ("C1", array(heap, c1)),
+            ("N", Object::Real(n as f32)),
+        ])
+    }
+
+    fn calculator(source: &str) -> crate::Result<Function> {
+        let mut heap = Heap::default();
+        let dict = function_dict(vec![
+            ("FunctionType", Object::Integer(4)),
+            ("Domain", array(&mut heap, &[-10.0, 10.0])),
+            ("Range", array(&mut heap, &[-100.0, 100.0])),
+            ("Function", procedure(&mut heap, source)),
+        ]);
+
+        Function::new(&dict, &heap)
+    }
+
+    #[test]
+    fn test_sampled() {
+        let mut heap = Heap::default();
+        let dict = sampled(&mut heap, &[3.0], 8, vec![0, 255, 0]);
+        let function = Function::new(&dict, &heap).unwrap();
+
+        assert_eq!(vec![0.0], evaluate(&function, &[0.0]));
+        assert_eq!(vec![0.5], evaluate(&function, &[0.25]));
+        assert_eq!(vec![1.0], evaluate(&function, &[0.5]));
+        assert_eq!(vec![0.0], evaluate(&function, &[2.0]));
+    }
+
+    #[test]
+    fn test_sampled_multilinear() {
+        let mut heap = Heap::default();
+        let dict = sampled(&mut heap, &[2.0, 2.0], 8, vec![0, 255, 255, 255]);
+        let function = Function::new(&dict, &heap).unwrap();
+
+        assert_eq!(vec![0.75], evaluate(&function, &[0.5, 0.5]));
+        assert_eq!(vec![0.5], evaluate(&function, &[0.5, 0.0]));
+        assert_eq!(vec![1.0], evaluate(&function, &[0.0, 1.0]));
+    }
+
+    #[test]
+    fn test_sampled_encode_decode() {
+        let mut heap = Heap::default();
+        let mut dict = sampled(&mut heap, &[2.0], 4, vec![0x0F]);
+        dict.insert(literal_name("Encode"), array(&mut heap, &[1.0, 0.0]));
+        dict.insert(literal_name("Decode"), array(&mut heap, &[1.0, 0.0]));
+        let function = Function::new(&dict, &heap).unwrap();
+
+        assert_eq!(vec![0.0], evaluate(&function, &[0.0]));
+        assert_eq!(vec![0.25], evaluate(&function, &[0.25]));
+    }
+
+    #[test]
+    fn test_sampled_too_few_samples() {
+        let mut heap = Heap::default();
+        let dict = sampled(&mut heap, &[4.0], 16, vec![0; 7]);
+        let error = Function::new(&dict, &heap).unwrap_err();
+
+        assert_eq!(ErrorKind::RangeCheck, error.kind());
+    }
+
+    #[test]
+    fn test_exponential() {
+        let mut heap = Heap::default();
+        let dict = exponential(&mut heap, &[0.0, 1.0], &[1.0, 3.0], 2.0);
+        let function = Function::new(&dict, &heap).unwrap();
+
+        assert_eq!(vec![0.25, 1.5], evaluate(&function, &[0.5]));
+        assert_eq!(vec![1.0, 3.0], evaluate(&function, &[2.0]));
+    }
+
+    #[test]
+    fn test_exponential_domain() {
+        let mut heap = Heap::default();
+        let mut dict = exponential(&mut heap, &[0.0], &[1.0], -1.0);
+
+        assert!(Function::new(&dict, &heap).is_err());
+
+        dict.insert(literal_name("Domain"), array(&mut heap, &[0.5, 1.0]));
+        let function = Function::new(&dict, &heap).unwrap();
+
+        assert_eq!(vec![1.0], evaluate(&function, &[1.0]));
+    }
+
+    #[test]
+    fn test_stitching() {
+        let mut heap = Heap::default();
+        let first = exponential(&mut heap, &[0.0], &[1.0], 1.0);
+        let second = exponential(&mut heap, &[1.0], &[0.0], 1.0);
+        let functions = vec![
+            Object::from(heap.alloc(first)),
+            Object::from(heap.alloc(second)),
+        ];
+        let functions = heap.alloc(ArrayObject::new(
+            functions,
+            Access::Unlimited,
+            Mode::Literal,
+        ));
+        let dict = function_dict(vec![
+            ("FunctionType", Object::Integer(3)),
+            ("Domain", array(&mut heap, &[0.0, 1.0])),
+            ("Functions", Object::from(functions)),
+            ("Bounds", array(&mut heap, &[0.5])),
+            ("Encode", array(&mut heap, &[0.0, 1.0, 0.0, 1.0])),
+        ]);
+        let function = Function::new(&dict, &heap).unwrap();
+
+        assert_eq!(vec![0.5], evaluate(&function, &[0.25]));
+        assert_eq!(vec![1.0], evaluate(&function, &[0.5]));
+        assert_eq!(vec![0.5], evaluate(&function, &[0.75]));
+        assert_eq!(vec![0.0], evaluate(&function, &[1.0]));
+    }
+
+    #[test]
+    fn test_stitching_cycle() {
+        let mut heap = Heap::default();
+        let functions = heap.alloc(ArrayObject::new(
+            vec![Object::Null(Mode::Literal)],
+            Access::Unlimited,
+            Mode::Literal,
+        ));
+        let dict = function_dict(vec![
+            ("FunctionType", Object::Integer(3)),
+            ("Domain", array(&mut heap, &[0.0, 1.0])),
+            ("Functions", Object::from(functions)),
+            ("Bounds", array(&mut heap, &[])),
+            ("Encode", array(&mut heap, &[0.0, 1.0])),
+        ]);
+        let dict = heap.alloc(dict);
+        *heap.get_mut(functions).get_mut(0).unwrap() = Object::from(dict);
+
+        let error = Function::new(heap.get(dict), &heap).unwrap_err();
+
+        assert_eq!(ErrorKind::RangeCheck, error.kind());
+    }
+
+    #[test]
+    fn test_calculator() {
+        let cases = [
+            ("{ 2 mul 1 add }", 3.0, 7.0),
+            ("{ dup 0 lt { neg } if }", -4.0, 4.0),
+            ("{ 0 gt { 1 } { -1 } ifelse }", 5.0, 1.0),
+            ("{ cvi 3 idiv }", 7.5, 2.0),
+            ("{ cvi 5 exch bitshift }", 2.0, 20.0),
+            ("{ 1 copy mul 3 add }", 3.0, 12.0),
+            ("{ 1 2 3 1 roll pop pop }", 5.0, 2.0),
+            ("{ true { 1 } if add }", 1.0, 2.0),
+            ("{ 10 mul 1000 add }", 1.0, 100.0),
+        ];
+
+        for (source, input, expected) in cases {
+            let function = calculator(source).unwrap();
+
+            assert_eq!(vec![expected], evaluate(&function, &[input]), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_calculator_errors() {
+        let cases = [
+            ("{ exec }", ErrorKind::Undefined),
+            ("{ { 1 } }", ErrorKind::SyntaxError),
+            ("{ { 1 } { 2 } if }", ErrorKind::SyntaxError),
+            ("{ (str) }", ErrorKind::TypeCheck),
+        ];
+
+        for (source, expected) in cases {
+            let error = calculator(source).unwrap_err();
+
+            assert_eq!(expected, error.kind(), "{source}");
+        }
+
+        let function = calculator("{ pop pop }").unwrap();
+        let error = function.evaluate(&[1.0], &mut [0.0]).unwrap_err();
+
+        assert_eq!(ErrorKind::StackUnderflow, error.kind());
+    }
+
+    #[test]
+    fn test_invalid_dicts() {
+        let mut heap = Heap::default();
+        let mut dict = exponential(&mut heap, &[0.0], &[1.0], 1.0);
+        dict.insert(literal_name("FunctionType"), Object::Integer(1));
+
+        assert_eq!(
+            ErrorKind::RangeCheck,
+            Function::new(&dict, &heap).unwrap_err().kind()
+        );
+
+        dict.insert(literal_name("FunctionType"), Object::Integer(2));
+        dict.remove(&literal_name("Domain"));
+
+        assert_eq!(
+            ErrorKind::Undefined,
+            Function::new(&dict, &heap).unwrap_err().kind()
+        );
+
+        dict.insert(literal_name("Domain"), array(&mut heap, &[1.0, 0.0]));
+
+        assert_eq!(
+            ErrorKind::RangeCheck,
+            Function::new(&dict, &heap).unwrap_err().kind()
+        );
+    }
+}
@@ -0,0 +1,414 @@
+use std::{fmt, hash, marker::PhantomData};
+
+use crate::{
+    array::ArrayObject,
+    context::{Condition, Lock},
+    dictionary::DictionaryObject,
+    file::FileObject,
+    object::{GState, PackedArray},
+    rooted::{Pins, Rooted},
+    save::Save,
+    string::StringObject,
+    Object,
+};
+
+/// A reference to a composite object in a `Heap`. Handles are plain indices,
+/// so copying one shares the object rather than copying it.
+pub struct Handle<T> {
+    index: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    /// A handle to the slot at `index`, which the caller has to check holds a
+    /// `T` before it is dereferenced.
+    pub(crate) fn from_index(index: u32) -> Self {
+        Self {
+            index,
+            marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn index(self) -> u32 {
+        self.index
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({})", self.index)
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> hash::Hash for Handle<T> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+pub(crate) enum Slot {
+    Free,
+    Array(ArrayObject),
+    Condition(Condition),
+    Dictionary(DictionaryObject),
+    File(FileObject),
+    GState(GState),
+    Lock(Lock),
+    PackedArray(PackedArray),
+    Save(Save),
+    String(StringObject),
+}
+
+/// A type that can be stored in a `Heap`.
+pub(crate) trait Composite: Sized {
+    fn into_slot(self) -> Slot;
+
+    fn from_slot(slot: &Slot) -> Option<&Self>;
+
+    fn from_slot_mut(slot: &mut Slot) -> Option<&mut Self>;
+}
+
+macro_rules! composite {
+    ($($variant:ident($ty:ty)),* $(,)?) => {
+        $(
+            impl Composite for $ty {
+                fn into_slot(self) -> Slot {
+                    Slot::$variant(self)
+                }
+
+                fn from_slot(slot: &Slot) -> Option<&Self> {
+                    match slot {
+                        Slot::$variant(value) => Some(value),
+                        _ => None,
+                    }
+                }
+
+                fn from_slot_mut(slot: &mut Slot) -> Option<&mut Self> {
+                    match slot {
+                        Slot::$variant(value) => Some(value),
+                        _ => None,
+                    }
+                }
+            }
+
+            impl From<Handle<$ty>> for Object {
+                fn from(value: Handle<$ty>) -> Self {
+                    Object::$variant(value)
+                }
+            }
+        )*
+    };
+}
+
+composite!(
+    Array(ArrayObject),
+    Condition(Condition),
+    Dictionary(DictionaryObject),
+    File(FileObject),
+    GState(GState),
+    Lock(Lock),
+    PackedArray(PackedArray),
+    Save(Save),
+    String(StringObject),
+);
+
+/// The VM every composite object of an interpreter lives in. Slots freed by
+/// `collect` are reused by later allocations. Each object belongs to either
+/// local or global VM, depending on the allocation mode it was created in.
+#[derive(Default)]
+pub struct Heap {
+    allocations: usize,
+    /// The serial number each slot's object was allocated under.
+    created: Vec<u64>,
+    free: Vec<u32>,
+    /// Whether new objects go in global VM, as set by `setglobal`.
+    global: bool,
+    /// Whether each slot's object is in global VM.
+    globals: Vec<bool>,
+    /// Slots held by the host through `Rooted` guards.
+    pins: Pins,
+    serial: u64,
+    slots: Vec<Slot>,
+    threshold: Option<usize>,
+}
+
+impl Heap {
+    /// Rebuilds a heap from the slots of another, as written to an image,
+    /// along with which of them are in global VM.
+    pub(crate) fn from_slots(slots: Vec<Slot>, globals: Vec<bool>) -> Self {
+        let free = (0..slots.len() as u32)
+            .rev()
+            .filter(|index| matches!(slots[*index as usize], Slot::Free))
+            .collect();
+
+        Self {
+            allocations: 0,
+            created: vec![0; slots.len()],
+            free,
+            global: false,
+            globals,
+            pins: Pins::default(),
+            serial: 0,
+            slots,
+            threshold: None,
+        }
+    }
+
+    pub(crate) fn alloc<T: Composite>(&mut self, value: T) -> Handle<T> {
+        self.allocations += 1;
+        self.serial += 1;
+
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.slots[index as usize] = value.into_slot();
+                self.created[index as usize] = self.serial;
+                self.globals[index as usize] = self.global;
+                index
+            },
+            None => {
+                let index = u32::try_from(self.slots.len()).expect("VM exhausted");
+                self.slots.push(value.into_slot());
+                self.created.push(self.serial);
+                self.globals.push(self.global);
+                index
+            },
+        };
+
+        Handle {
+            index,
+            marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn get<T: Composite>(&self, handle: Handle<T>) -> &T {
+        T::from_slot(&self.slots[handle.index as usize]).expect("dangling handle")
+    }
+
+    pub(crate) fn get_mut<T: Composite>(&mut self, handle: Handle<T>) -> &mut T {
+        T::from_slot_mut(&mut self.slots[handle.index as usize]).expect("dangling handle")
+    }
+
+    /// A handle to the slot at `index`, if it holds a `T`.
+    pub(crate) fn handle<T: Composite>(&self, index: u32) -> Option<Handle<T>> {
+        T::from_slot(self.slots.get(index as usize)?)?;
+
+        Some(Handle::from_index(index))
+    }
+
+    /// Whether a composite object refers to a slot holding its type.
+    pub(crate) fn is_valid(&self, obj: &Object) -> bool {
+        let Some(index) = index(obj) else {
+            return true;
+        };
+
+        matches!(
+            (obj, self.slots.get(index as usize)),
+            (Object::Array(_), Some(Slot::Array(_)))
+                | (Object::Condition(_), Some(Slot::Condition(_)))
+                | (Object::Dictionary(_), Some(Slot::Dictionary(_)))
+                | (Object::File(_), Some(Slot::File(_)))
+                | (Object::GState(_), Some(Slot::GState(_)))
+                | (Object::Lock(_), Some(Slot::Lock(_)))
+                | (Object::PackedArray(_), Some(Slot::PackedArray(_)))
+                | (Object::Save(_), Some(Slot::Save(_)))
+                | (Object::String(_), Some(Slot::String(_)))
+        )
+    }
+
+    /// Keeps `obj` from being collected for as long as the returned guard
+    /// is alive.
+    pub(crate) fn root(&self, obj: Object) -> Rooted {
+        Rooted::new(obj, index(&obj), &self.pins)
+    }
+
+    /// The serial number of the latest allocation. Objects allocated from now
+    /// on are newer than it.
+    pub(crate) fn serial(&self) -> u64 {
+        self.serial
+    }
+
+    /// Whether new objects are allocated in global VM.
+    pub(crate) fn global(&self) -> bool {
+        self.global
+    }
+
+    pub(crate) fn set_global(&mut self, global: bool) {
+        self.global = global;
+    }
+
+    /// Whether `obj` lives in global VM. Simple objects belong to neither,
+    /// and count as global since they can be stored anywhere.
+    pub(crate) fn is_global(&self, obj: &Object) -> bool {
+        index(obj).is_none_or(|index| self.globals[index as usize])
+    }
+
+    pub(crate) fn globals(&self) -> &[bool] {
+        &self.globals
+    }
+
+    /// Whether a composite object was allocated after `serial` was taken.
+    pub(crate) fn is_newer(&self, obj: &Object, serial: u64) -> bool {
+        index(obj).is_some_and(|index| self.created[index as usize] > serial)
+    }
+
+    /// The number of objects currently allocated.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub(crate) fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    /// Sets how many allocations may happen between collections, or turns
+    /// collection off with `None`.
+    pub(crate) fn set_threshold(&mut self, threshold: Option<usize>) {
+        self.threshold = threshold;
+    }
+
+    pub(crate) fn needs_collection(&self) -> bool {
+        self.threshold
+            .is_some_and(|threshold| self.allocations >= threshold)
+    }
+
+    /// Frees every object that cannot be reached from `roots`.
+    pub(crate) fn collect<I>(&mut self, roots: I)
+    where
+        I: IntoIterator<Item = Object>,
+    {
+        let mut is_marked = vec![false; self.slots.len()];
+        let mut pending: Vec<u32> = roots.into_iter().filter_map(|obj| index(&obj)).collect();
+        pending.extend(self.pins.borrow().keys());
+
+        while let Some(index) = pending.pop() {
+            let is_marked = &mut is_marked[index as usize];
+            if *is_marked {
+                continue;
+            }
+            *is_marked = true;
+
+            match &self.slots[index as usize] {
+                Slot::Array(arr) => pending.extend(arr.iter().filter_map(self::index)),
+                Slot::PackedArray(arr) => {
+                    pending.extend(arr.value().iter().filter_map(self::index))
+                },
+                Slot::Dictionary(dict) => pending.extend(
+                    dict.iter()
+                        .flat_map(|(key, value)| [key, value])
+                        .filter_map(self::index),
+                ),
+                Slot::Save(save) => pending.extend(save.objects().iter().filter_map(self::index)),
+                _ => {},
+            }
+        }
+
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if !is_marked[index] && !matches!(slot, Slot::Free) {
+                *slot = Slot::Free;
+                self.free.push(index as u32);
+            }
+        }
+
+        self.allocations = 0;
+    }
+}
+
+/// The slot a composite object lives in.
+pub(crate) fn index(obj: &Object) -> Option<u32> {
+    match obj {
+        Object::Array(arr) => Some(arr.index()),
+        Object::Condition(condition) => Some(condition.index()),
+        Object::Dictionary(dict) => Some(dict.index()),
+        Object::File(file) => Some(file.index()),
+        Object::GState(gstate) => Some(gstate.index()),
+        Object::Lock(lock) => Some(lock.index()),
+        Object::PackedArray(arr) => Some(arr.index()),
+        Object::Save(save) => Some(save.index()),
+        Object::String(string) => Some(string.index()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::{Access, Mode};
+
+    #[test]
+    fn test_collect_reuses_slots() {
+        let mut heap = Heap::default();
+
+        let kept = heap.alloc(StringObject::from("kept"));
+        let dropped = heap.alloc(StringObject::from("dropped"));
+        heap.collect([Object::String(kept)]);
+
+        assert_eq!(1, heap.len());
+        assert_eq!(b"kept", heap.get(kept).value());
+
+        let reused = heap.alloc(StringObject::from("reused"));
+        assert_eq!(dropped.index(), reused.index());
+        assert_eq!(2, heap.len());
+    }
+
+    #[test]
+    fn test_collect_follows_references() {
+        let mut heap = Heap::default();
+
+        let string = heap.alloc(StringObject::from("inner"));
+        let arr = heap.alloc(ArrayObject::new(
+            vec![Object::String(string)],
+            Access::Unlimited,
+            Mode::Literal,
+        ));
+        let mut dict = DictionaryObject::default();
+        dict.insert(Object::Integer(1), Object::Array(arr));
+        let dict = heap.alloc(dict);
+
+        // Cycles are collected as a whole once nothing else refers to them
+        let cycle = heap.alloc(ArrayObject::new(
+            vec![Object::Null(Mode::Literal)],
+            Access::Unlimited,
+            Mode::Literal,
+        ));
+        *heap.get_mut(cycle).get_mut(0).unwrap() = Object::Array(cycle);
+
+        heap.collect([Object::Dictionary(dict)]);
+
+        assert_eq!(3, heap.len());
+        assert_eq!(b"inner", heap.get(string).value());
+    }
+
+    #[test]
+    fn test_collect_keeps_rooted() {
+        let mut heap = Heap::default();
+
+        let string = Object::String(heap.alloc(StringObject::from("rooted")));
+        let rooted = heap.root(string);
+        let copy = rooted.clone();
+
+        drop(rooted);
+        heap.collect([]);
+        assert_eq!(1, heap.len());
+
+        drop(copy);
+        heap.collect([]);
+        assert_eq!(0, heap.len());
+    }
+}
@@ -0,0 +1,21 @@
+/// How the interpreter identifies itself to PostScript programs through
+/// `product`, `version`, `revision` and `serialnumber`. Hosts can replace
+/// it to pose as a particular printer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identity {
+    pub product: String,
+    pub revision: i32,
+    pub serial_number: i32,
+    pub version: String,
+}
+
+impl Default for Identity {
+    fn default() -> Self {
+        Self {
+            product: String::from("libPS"),
+            revision: 0,
+            serial_number: 0,
+            version: String::from(env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
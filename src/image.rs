@@ -0,0 +1,673 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use crate::{
+    array::ArrayObject,
+    bytecode,
+    context::{Condition, Lock},
+    dictionary::DictionaryObject,
+    heap::{Handle, Heap, Slot},
+    name::NameObject,
+    object::{Access, GState},
+    operator::OperatorObject,
+    save::Save,
+    string::StringObject,
+    Error, ErrorKind, Identity, LanguageLevel, Mode, Object, MAX_DICT_LENGTH, SYSTEM_OPERATORS,
+};
+
+const MAGIC: &[u8; 8] = b"libpsimg";
+const FORMAT_VERSION: u32 = 4;
+
+// Operators that live outside of systemdict, so `SYSTEM_OPERATORS` does not
+// list them
+const OTHER_OPERATORS: &[OperatorObject] = &[
+    OperatorObject::CheckPassword,
+    OperatorObject::DefaultTimeouts,
+    OperatorObject::DuplexMode,
+    OperatorObject::JobTimeout,
+    OperatorObject::PageCount,
+    OperatorObject::PageParams,
+    OperatorObject::PaperTray,
+    OperatorObject::PrinterName,
+    OperatorObject::SetDuplexMode,
+    OperatorObject::SetJobTimeout,
+    OperatorObject::SetPageParams,
+    OperatorObject::SetPaperTray,
+    OperatorObject::SetTumble,
+    OperatorObject::Tumble,
+    OperatorObject::ExitServer,
+    OperatorObject::Assert,
+    OperatorObject::AssertDeepEq,
+    OperatorObject::AssertEq,
+    OperatorObject::AssertError,
+    OperatorObject::AssertNe,
+];
+
+/// The VM an interpreter starts out with: the heap and every object jobs
+/// reach it through. This is what an image holds.
+pub(crate) struct Vm {
+    pub(crate) device_params: DictionaryObject,
+    pub(crate) dict_stack: Vec<Handle<DictionaryObject>>,
+    pub(crate) error_dict: Handle<DictionaryObject>,
+    pub(crate) error_info: Handle<DictionaryObject>,
+    pub(crate) font_directory: Handle<DictionaryObject>,
+    pub(crate) global_dict: Handle<DictionaryObject>,
+    pub(crate) heap: Heap,
+    pub(crate) identity: Identity,
+    pub(crate) display_postscript: bool,
+    pub(crate) language_level: LanguageLevel,
+    pub(crate) page_device: Handle<DictionaryObject>,
+    pub(crate) resources: Handle<DictionaryObject>,
+    pub(crate) status_dict: Handle<DictionaryObject>,
+    pub(crate) system_params: DictionaryObject,
+    pub(crate) user_dict: Handle<DictionaryObject>,
+    pub(crate) user_params: DictionaryObject,
+}
+
+/// Writes `vm` as an image. Images are only read back by the same build of
+/// the crate, so the encoding is free to follow the in-memory layout.
+pub(crate) fn write<W: Write>(mut writer: W, vm: &Vm) -> crate::Result<()> {
+    let mut body = Encoder::default();
+
+    body.u32(vm.heap.slots().len() as u32);
+    for (slot, is_global) in vm.heap.slots().iter().zip(vm.heap.globals()) {
+        body.slot(slot)?;
+        body.u8(u8::from(*is_global));
+    }
+
+    body.u32(vm.dict_stack.len() as u32);
+    for dict in &vm.dict_stack {
+        body.u32(dict.index());
+    }
+    for dict in [
+        vm.error_dict,
+        vm.error_info,
+        vm.font_directory,
+        vm.global_dict,
+        vm.page_device,
+        vm.resources,
+        vm.status_dict,
+        vm.user_dict,
+    ] {
+        body.u32(dict.index());
+    }
+    for dict in [&vm.device_params, &vm.system_params, &vm.user_params] {
+        body.dict(dict)?;
+    }
+
+    body.bytes(vm.identity.product.as_bytes());
+    body.i32(vm.identity.revision);
+    body.i32(vm.identity.serial_number);
+    body.bytes(vm.identity.version.as_bytes());
+    body.i32(i32::from(vm.language_level));
+    body.u8(u8::from(vm.display_postscript));
+
+    // Names are interned per process, so they are written out by value and
+    // objects refer to them by their position in the table
+    let mut header = Encoder::default();
+    header.buf.extend_from_slice(MAGIC);
+    header.u32(FORMAT_VERSION);
+    header.bytes(env!("CARGO_PKG_VERSION").as_bytes());
+    header.u64(fingerprint());
+    header.u32(body.names.len() as u32);
+    for name in &body.names {
+        header.bytes(name.value());
+    }
+
+    writer
+        .write_all(&header.buf)
+        .and_then(|_| writer.write_all(&body.buf))
+        .map_err(|e| Error::new(ErrorKind::IoError, e))
+}
+
+/// Reads an image written by `write`, rejecting images from other builds.
+pub(crate) fn read<R: Read>(mut reader: R) -> crate::Result<Vm> {
+    let mut data = Vec::new();
+    reader
+        .read_to_end(&mut data)
+        .map_err(|e| Error::new(ErrorKind::IoError, e))?;
+
+    let mut decoder = Decoder {
+        data: &data,
+        names: Vec::new(),
+    };
+
+    if decoder.take(MAGIC.len())? != MAGIC {
+        return Err(Error::new(ErrorKind::VmError, "not an image"));
+    }
+    if decoder.u32()? != FORMAT_VERSION
+        || decoder.bytes()? != env!("CARGO_PKG_VERSION").as_bytes()
+        || decoder.u64()? != fingerprint()
+    {
+        return Err(Error::new(
+            ErrorKind::VmError,
+            "image was written by an incompatible version",
+        ));
+    }
+
+    let name_count = decoder.u32()?;
+    for _ in 0..name_count {
+        let name = NameObject::from_bytes(decoder.bytes()?, Mode::Literal);
+        decoder.names.push(name);
+    }
+
+    let slot_count = decoder.u32()?;
+    let mut slots = Vec::new();
+    let mut globals = Vec::new();
+    let mut compiled = Vec::new();
+    for index in 0..slot_count {
+        let (slot, is_compiled) = decoder.slot()?;
+        if is_compiled {
+            compiled.push(index);
+        }
+        slots.push(slot);
+        globals.push(decoder.u8()? != 0);
+    }
+    let mut heap = Heap::from_slots(slots, globals);
+
+    let dict_stack_len = decoder.u32()?;
+    let dict_stack = (0..dict_stack_len)
+        .map(|_| decoder.handle(&heap))
+        .collect::<crate::Result<Vec<_>>>()?;
+    let error_dict = decoder.handle(&heap)?;
+    let error_info = decoder.handle(&heap)?;
+    let font_directory = decoder.handle(&heap)?;
+    let global_dict = decoder.handle(&heap)?;
+    let page_device = decoder.handle(&heap)?;
+    let resources = decoder.handle(&heap)?;
+    let status_dict = decoder.handle(&heap)?;
+    let user_dict = decoder.handle(&heap)?;
+    let device_params = decoder.dict()?;
+    let system_params = decoder.dict()?;
+    let user_params = decoder.dict()?;
+
+    let identity = Identity {
+        product: decoder.string()?,
+        revision: decoder.i32()?,
+        serial_number: decoder.i32()?,
+        version: decoder.string()?,
+    };
+    let language_level = LanguageLevel::try_from(decoder.i32()?)?;
+    let display_postscript = decoder.u8()? != 0;
+
+    if dict_stack.is_empty() || !decoder.data.is_empty() {
+        return Err(Error::new(ErrorKind::VmError, "malformed image"));
+    }
+
+    // Handles are only checked once every slot is known, since objects may
+    // refer to slots that come after them
+    let params = [&device_params, &system_params, &user_params];
+    let is_valid = heap.slots().iter().all(|slot| match slot {
+        Slot::Array(arr) => arr.iter().all(|obj| heap.is_valid(obj)),
+        Slot::Dictionary(dict) => is_valid_dict(dict, &heap),
+        _ => true,
+    }) && params.iter().all(|dict| is_valid_dict(dict, &heap));
+    if !is_valid {
+        return Err(Error::new(ErrorKind::VmError, "malformed image"));
+    }
+
+    for index in compiled {
+        if let Some(proc) = heap.handle(index) {
+            bytecode::compile(proc, &mut heap);
+        }
+    }
+
+    Ok(Vm {
+        device_params,
+        dict_stack,
+        error_dict,
+        error_info,
+        font_directory,
+        global_dict,
+        heap,
+        identity,
+        display_postscript,
+        language_level,
+        page_device,
+        resources,
+        status_dict,
+        system_params,
+        user_dict,
+        user_params,
+    })
+}
+
+fn is_valid_dict(dict: &DictionaryObject, heap: &Heap) -> bool {
+    dict.iter()
+        .all(|(key, value)| heap.is_valid(key) && heap.is_valid(value))
+}
+
+fn operators() -> impl Iterator<Item = &'static OperatorObject> {
+    SYSTEM_OPERATORS.iter().chain(OTHER_OPERATORS)
+}
+
+/// Identifies the operator encoding, so that images written before an
+/// operator was added or moved are rejected rather than misread.
+fn fingerprint() -> u64 {
+    let names = operators()
+        .map(ToString::to_string)
+        .chain(ErrorKind::ALL.map(|kind| <&str>::from(kind).to_string()));
+
+    // FNV-1a, which unlike the standard library's hashers is stable
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in names.flat_map(|name| name.into_bytes().into_iter().chain([0])) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    hash
+}
+
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+    name_ids: HashMap<NameObject, u32>,
+    names: Vec<NameObject>,
+}
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.u32(value.len() as u32);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn access(&mut self, access: Access) {
+        self.u8(match access {
+            Access::Unlimited => 0,
+            Access::ReadOnly => 1,
+            Access::ExecuteOnly => 2,
+            Access::None => 3,
+        });
+    }
+
+    fn mode(&mut self, mode: Mode) {
+        self.u8(u8::from(mode.is_executable()));
+    }
+
+    fn name(&mut self, name: NameObject) {
+        let id = *self.name_ids.entry(name).or_insert_with(|| {
+            self.names.push(name);
+            self.names.len() as u32 - 1
+        });
+
+        self.mode(name.mode());
+        self.u32(id);
+    }
+
+    fn operator(&mut self, operator: OperatorObject) -> crate::Result<()> {
+        let error_index = |kind| {
+            ErrorKind::ALL
+                .iter()
+                .position(|other| *other == kind)
+                .unwrap_or_default() as u32
+        };
+
+        match operator {
+            OperatorObject::Error(kind) => {
+                self.u8(1);
+                self.u32(error_index(kind));
+            },
+            OperatorObject::RecoverFromError(kind) => {
+                self.u8(2);
+                self.u32(error_index(kind));
+            },
+            operator => {
+                let Some(index) = operators().position(|other| *other == operator) else {
+                    return Err(Error::new(
+                        ErrorKind::VmError,
+                        format!("{operator} cannot be stored in an image"),
+                    ));
+                };
+
+                self.u8(0);
+                self.u32(index as u32);
+            },
+        }
+
+        Ok(())
+    }
+
+    fn object(&mut self, obj: &Object) -> crate::Result<()> {
+        match obj {
+            Object::Boolean(value) => {
+                self.u8(0);
+                self.u8(u8::from(*value));
+            },
+            Object::FontID => self.u8(1),
+            Object::Integer(value) => {
+                self.u8(2);
+                self.i32(*value);
+            },
+            Object::Mark => self.u8(3),
+            Object::Name(name) => {
+                self.u8(4);
+                self.name(*name);
+            },
+            Object::Null(mode) => {
+                self.u8(5);
+                self.mode(*mode);
+            },
+            Object::Operator((operator, mode)) => {
+                self.u8(6);
+                self.mode(*mode);
+                self.operator(*operator)?;
+            },
+            Object::Real(value) => {
+                self.u8(7);
+                self.buf.extend_from_slice(&value.to_le_bytes());
+            },
+            Object::Array(arr) => {
+                self.u8(8);
+                self.u32(arr.index());
+            },
+            Object::Condition(condition) => {
+                self.u8(9);
+                self.u32(condition.index());
+            },
+            Object::Dictionary(dict) => {
+                self.u8(10);
+                self.u32(dict.index());
+            },
+            Object::GState(gstate) => {
+                self.u8(11);
+                self.u32(gstate.index());
+            },
+            Object::Lock(lock) => {
+                self.u8(12);
+                self.u32(lock.index());
+            },
+            Object::Save(save) => {
+                self.u8(13);
+                self.u32(save.index());
+            },
+            Object::String(string) => {
+                self.u8(14);
+                self.u32(string.index());
+            },
+            Object::File(_) => {
+                return Err(Error::new(
+                    ErrorKind::VmError,
+                    "files cannot be stored in an image",
+                ))
+            },
+            Object::PackedArray(_) => {
+                return Err(Error::new(
+                    ErrorKind::VmError,
+                    "packed arrays cannot be stored in an image",
+                ))
+            },
+        }
+
+        Ok(())
+    }
+
+    fn dict(&mut self, dict: &DictionaryObject) -> crate::Result<()> {
+        self.access(dict.access());
+        self.u32(dict.capacity() as u32);
+        self.u32(dict.len() as u32);
+        for (key, value) in dict.iter() {
+            self.object(key)?;
+            self.object(value)?;
+        }
+
+        Ok(())
+    }
+
+    fn slot(&mut self, slot: &Slot) -> crate::Result<()> {
+        match slot {
+            Slot::Free => self.u8(0),
+            Slot::Array(arr) => {
+                self.u8(1);
+                self.access(arr.access());
+                self.mode(arr.mode());
+                self.u8(u8::from(arr.compiled().is_some()));
+                self.u32(arr.len() as u32);
+                for obj in arr.iter() {
+                    self.object(obj)?;
+                }
+            },
+            Slot::Condition(_) => self.u8(2),
+            Slot::Dictionary(dict) => {
+                self.u8(3);
+                self.dict(dict)?;
+            },
+            Slot::GState(_) => self.u8(4),
+            Slot::Lock(_) => self.u8(5),
+            // Only saves that have been restored or discarded are left once
+            // jobs have finished, so there is no journal worth keeping
+            Slot::Save(_) => self.u8(6),
+            Slot::String(string) => {
+                self.u8(7);
+                self.access(string.access());
+                self.mode(string.mode());
+                self.bytes(string.value());
+            },
+            Slot::File(_) | Slot::PackedArray(_) => {
+                return Err(Error::new(
+                    ErrorKind::VmError,
+                    "files and packed arrays cannot be stored in an image",
+                ))
+            },
+        }
+
+        Ok(())
+    }
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    names: Vec<NameObject>,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> crate::Result<&'a [u8]> {
+        if len > self.data.len() {
+            return Err(Error::new(ErrorKind::VmError, "truncated image"));
+        }
+
+        let (value, rest) = self.data.split_at(len);
+        self.data = rest;
+
+        Ok(value)
+    }
+
+    fn array<const N: usize>(&mut self) -> crate::Result<[u8; N]> {
+        let mut buf = [0; N];
+        buf.copy_from_slice(self.take(N)?);
+
+        Ok(buf)
+    }
+
+    fn u8(&mut self) -> crate::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> crate::Result<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> crate::Result<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> crate::Result<i32> {
+        self.array().map(i32::from_le_bytes)
+    }
+
+    fn bytes(&mut self) -> crate::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+
+        self.take(len)
+    }
+
+    fn string(&mut self) -> crate::Result<String> {
+        String::from_utf8(self.bytes()?.to_vec())
+            .or(Err(Error::new(ErrorKind::VmError, "malformed image")))
+    }
+
+    fn access(&mut self) -> crate::Result<Access> {
+        match self.u8()? {
+            0 => Ok(Access::Unlimited),
+            1 => Ok(Access::ReadOnly),
+            2 => Ok(Access::ExecuteOnly),
+            3 => Ok(Access::None),
+            _ => Err(Error::new(ErrorKind::VmError, "malformed image")),
+        }
+    }
+
+    fn mode(&mut self) -> crate::Result<Mode> {
+        match self.u8()? {
+            0 => Ok(Mode::Literal),
+            1 => Ok(Mode::Executable),
+            _ => Err(Error::new(ErrorKind::VmError, "malformed image")),
+        }
+    }
+
+    /// Reads an index into a table of `len` entries.
+    fn index(&mut self, len: usize) -> crate::Result<usize> {
+        let index = self.u32()? as usize;
+        if index >= len {
+            return Err(Error::new(ErrorKind::VmError, "malformed image"));
+        }
+
+        Ok(index)
+    }
+
+    fn handle<T>(&mut self, heap: &Heap) -> crate::Result<Handle<T>>
+    where
+        T: crate::heap::Composite,
+    {
+        let index = self.u32()?;
+
+        heap.handle(index)
+            .ok_or(Error::new(ErrorKind::VmError, "malformed image"))
+    }
+
+    fn operator(&mut self) -> crate::Result<OperatorObject> {
+        let tag = self.u8()?;
+        let operator = match tag {
+            1 | 2 => {
+                let kind = ErrorKind::ALL[self.index(ErrorKind::ALL.len())?];
+                if tag == 1 {
+                    OperatorObject::Error(kind)
+                } else {
+                    OperatorObject::RecoverFromError(kind)
+                }
+            },
+            0 => {
+                let index = self.index(operators().count())?;
+                *operators().nth(index).unwrap_or(&OperatorObject::Null)
+            },
+            _ => return Err(Error::new(ErrorKind::VmError, "malformed image")),
+        };
+
+        Ok(operator)
+    }
+
+    /// Reads an object. Handles are checked against the heap separately.
+    fn object(&mut self) -> crate::Result<Object> {
+        let obj = match self.u8()? {
+            0 => Object::Boolean(self.u8()? != 0),
+            1 => Object::FontID,
+            2 => Object::Integer(self.i32()?),
+            3 => Object::Mark,
+            4 => {
+                let mode = self.mode()?;
+                let index = self.index(self.names.len())?;
+
+                Object::Name(self.names[index].with_mode(mode))
+            },
+            5 => Object::Null(self.mode()?),
+            6 => {
+                let mode = self.mode()?;
+                Object::Operator((self.operator()?, mode))
+            },
+            7 => Object::Real(f32::from_le_bytes(self.array()?)),
+            8 => Object::Array(Handle::from_index(self.u32()?)),
+            9 => Object::Condition(Handle::from_index(self.u32()?)),
+            10 => Object::Dictionary(Handle::from_index(self.u32()?)),
+            11 => Object::GState(Handle::from_index(self.u32()?)),
+            12 => Object::Lock(Handle::from_index(self.u32()?)),
+            13 => Object::Save(Handle::from_index(self.u32()?)),
+            14 => Object::String(Handle::from_index(self.u32()?)),
+            _ => return Err(Error::new(ErrorKind::VmError, "malformed image")),
+        };
+
+        Ok(obj)
+    }
+
+    fn dict(&mut self) -> crate::Result<DictionaryObject> {
+        let access = self.access()?;
+        let capacity = self.u32()? as usize;
+        if capacity > MAX_DICT_LENGTH {
+            return Err(Error::new(ErrorKind::VmError, "malformed image"));
+        }
+
+        let len = self.u32()?;
+        let mut dict = DictionaryObject::new(capacity, Access::Unlimited);
+        for _ in 0..len {
+            let key = self.object()?;
+            let value = self.object()?;
+            dict.insert(key, value);
+        }
+        dict.set_access(access);
+
+        Ok(dict)
+    }
+
+    /// Reads a slot, and whether it holds a procedure that was compiled.
+    fn slot(&mut self) -> crate::Result<(Slot, bool)> {
+        let slot = match self.u8()? {
+            0 => Slot::Free,
+            1 => {
+                let access = self.access()?;
+                let mode = self.mode()?;
+                let is_compiled = self.u8()? != 0;
+                let len = self.u32()?;
+                let objs = (0..len)
+                    .map(|_| self.object())
+                    .collect::<crate::Result<Vec<_>>>()?;
+
+                return Ok((
+                    Slot::Array(ArrayObject::new(objs, access, mode)),
+                    is_compiled,
+                ));
+            },
+            2 => Slot::Condition(Condition),
+            3 => Slot::Dictionary(self.dict()?),
+            4 => Slot::GState(GState {}),
+            5 => Slot::Lock(Lock::default()),
+            6 => Slot::Save(Save::default()),
+            7 => {
+                let access = self.access()?;
+                let mode = self.mode()?;
+                let mut string = StringObject::from(self.bytes()?.to_vec());
+                string.set_access(access);
+                string.set_mode(mode);
+
+                Slot::String(string)
+            },
+            _ => return Err(Error::new(ErrorKind::VmError, "malformed image")),
+        };
+
+        Ok((slot, false))
+    }
+}
@@ -0,0 +1,735 @@
+use std::{
+    fmt::Write,
+    str::{self, Utf8Error},
+};
+
+use crate::{
+    array::ArrayObject,
+    dictionary::DictionaryObject,
+    heap::{self, Heap},
+    name::NameObject,
+    object::{Access, Mode},
+    string::StringObject,
+    Error, ErrorKind, Object, MAX_ARRAY_LENGTH, MAX_DICT_LENGTH,
+};
+
+// Deep enough for any sensible document, shallow enough that a hostile one
+// cannot overflow the Rust stack
+const MAX_DEPTH: usize = 256;
+
+/// How names are written to JSON, and whether JSON strings are read back as
+/// names.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum JsonNames {
+    /// Names are written as plain strings and cannot be told apart from
+    /// strings afterwards.
+    #[default]
+    String,
+    /// Names are written as strings with a leading `/`, and strings that start
+    /// with `/` are read back as literal names.
+    Slashed,
+    /// Names are rejected with a `typecheck`.
+    Error,
+}
+
+/// How procedures are written to JSON.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum JsonProcedures {
+    /// Procedures are rejected with a `typecheck`.
+    #[default]
+    Error,
+    /// Procedures are written as `null`.
+    Null,
+    /// Procedures are written as strings holding their source, the way `==`
+    /// prints them.
+    Source,
+}
+
+/// How strings that do not hold UTF-8 are written to JSON.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum JsonBinary {
+    /// Such strings are rejected with a `typecheck`.
+    #[default]
+    Error,
+    /// Invalid sequences are replaced with U+FFFD.
+    Lossy,
+    /// Such strings are written as arrays of their bytes.
+    Bytes,
+}
+
+/// Controls how objects with no direct JSON equivalent are converted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct JsonOptions {
+    pub binary: JsonBinary,
+    pub names: JsonNames,
+    pub procedures: JsonProcedures,
+}
+
+/// Parses `json` into objects allocated in `heap`. Numbers that fit an int
+/// become integers and the rest reals.
+pub(crate) fn parse(json: &str, options: &JsonOptions, heap: &mut Heap) -> crate::Result<Object> {
+    let mut parser = Parser {
+        depth: 0,
+        heap,
+        input: json.as_bytes(),
+        options,
+        pos: 0,
+    };
+
+    let obj = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < parser.input.len() {
+        return Err(parser.error("trailing characters"));
+    }
+
+    Ok(obj)
+}
+
+pub(crate) fn write(obj: &Object, options: &JsonOptions, heap: &Heap) -> crate::Result<String> {
+    let mut writer = Writer {
+        heap,
+        options,
+        out: String::new(),
+        parents: Vec::new(),
+    };
+    writer.value(obj)?;
+
+    Ok(writer.out)
+}
+
+struct Parser<'a> {
+    depth: usize,
+    heap: &'a mut Heap,
+    input: &'a [u8],
+    options: &'a JsonOptions,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> Error {
+        Error::new(
+            ErrorKind::SyntaxError,
+            format!("{message} at offset {}", self.pos),
+        )
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str) -> crate::Result<()> {
+        if !self.input[self.pos..].starts_with(literal.as_bytes()) {
+            return Err(self.error("unexpected token"));
+        }
+        self.pos += literal.len();
+
+        Ok(())
+    }
+
+    fn value(&mut self) -> crate::Result<Object> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b'{') => self.nested(Self::object),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'"') => {
+                let value = self.string()?;
+
+                match value.strip_prefix('/') {
+                    Some(name) if self.options.names == JsonNames::Slashed => {
+                        Ok(Object::Name(NameObject::new(name, Mode::Literal)))
+                    },
+                    _ => Ok(Object::String(self.heap.alloc(StringObject::from(value)))),
+                }
+            },
+            Some(b't') => self.expect("true").map(|_| Object::Boolean(true)),
+            Some(b'f') => self.expect("false").map(|_| Object::Boolean(false)),
+            Some(b'n') => self.expect("null").map(|_| Object::Null(Mode::Literal)),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> crate::Result<Object>) -> crate::Result<Object> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Error::new(ErrorKind::LimitCheck, "JSON nested too deeply"));
+        }
+
+        self.depth += 1;
+        let obj = parse(self);
+        self.depth -= 1;
+
+        obj
+    }
+
+    fn array(&mut self) -> crate::Result<Object> {
+        self.pos += 1;
+
+        let mut objs = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+        } else {
+            loop {
+                objs.push(self.value()?);
+                if objs.len() > MAX_ARRAY_LENGTH {
+                    return Err(Error::from(ErrorKind::LimitCheck));
+                }
+
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(b',') => self.pos += 1,
+                    Some(b']') => {
+                        self.pos += 1;
+                        break;
+                    },
+                    _ => return Err(self.error("expected ',' or ']'")),
+                }
+            }
+        }
+
+        let arr = ArrayObject::new(objs, Access::Unlimited, Mode::Literal);
+
+        Ok(Object::Array(self.heap.alloc(arr)))
+    }
+
+    fn object(&mut self) -> crate::Result<Object> {
+        self.pos += 1;
+
+        let mut dict = DictionaryObject::default();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+        } else {
+            loop {
+                self.skip_whitespace();
+                if self.peek() != Some(b'"') {
+                    return Err(self.error("expected string key"));
+                }
+                let key = self.string()?;
+
+                self.skip_whitespace();
+                if self.peek() != Some(b':') {
+                    return Err(self.error("expected ':'"));
+                }
+                self.pos += 1;
+
+                let value = self.value()?;
+                dict.insert(Object::Name(NameObject::new(&key, Mode::Literal)), value);
+                if dict.len() > MAX_DICT_LENGTH {
+                    return Err(Error::from(ErrorKind::LimitCheck));
+                }
+
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(b',') => self.pos += 1,
+                    Some(b'}') => {
+                        self.pos += 1;
+                        break;
+                    },
+                    _ => return Err(self.error("expected ',' or '}'")),
+                }
+            }
+        }
+
+        Ok(Object::Dictionary(self.heap.alloc(dict)))
+    }
+
+    fn string(&mut self) -> crate::Result<String> {
+        self.pos += 1;
+
+        let mut value = String::new();
+        loop {
+            let start = self.pos;
+            while !matches!(self.peek(), Some(b'"' | b'\\') | None) {
+                if self.input[self.pos] < 0x20 {
+                    return Err(self.error("control character in string"));
+                }
+                self.pos += 1;
+            }
+            value.push_str(self.utf8(start)?);
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(value);
+                },
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let ch = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\x08',
+                        Some(b'f') => '\x0c',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    self.pos += 1;
+                    value.push(ch);
+                },
+                _ => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn utf8(&self, start: usize) -> crate::Result<&str> {
+        // The input started out as a str, so this only fails if a slice
+        // boundary splits a character, which the scan above never does
+        str::from_utf8(&self.input[start..self.pos])
+            .map_err(|e: Utf8Error| Error::new(ErrorKind::SyntaxError, e))
+    }
+
+    /// Reads the digits of a `\u` escape, combining surrogate pairs. Leaves
+    /// the position on the last digit.
+    fn unicode_escape(&mut self) -> crate::Result<char> {
+        let high = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).ok_or(self.error("invalid code point"));
+        }
+
+        self.pos += 1;
+        self.expect("\\u")?;
+        self.pos -= 1;
+        let low = self.hex4()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return Err(self.error("unpaired surrogate"));
+        }
+
+        let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+
+        char::from_u32(code).ok_or(self.error("invalid code point"))
+    }
+
+    fn hex4(&mut self) -> crate::Result<u32> {
+        let digits = self
+            .input
+            .get(self.pos + 1..self.pos + 5)
+            .and_then(|digits| str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or(self.error("invalid unicode escape"))?;
+        self.pos += 4;
+
+        Ok(digits)
+    }
+
+    fn number(&mut self) -> crate::Result<Object> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let start = parser.pos;
+            while matches!(parser.peek(), Some(b'0'..=b'9')) {
+                parser.pos += 1;
+            }
+
+            parser.pos > start
+        };
+
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'0') {
+            self.pos += 1;
+        } else if !digits(self) {
+            return Err(self.error("invalid number"));
+        }
+
+        let mut is_integer = true;
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            is_integer = false;
+            if !digits(self) {
+                return Err(self.error("invalid number"));
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            is_integer = false;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("invalid number"));
+            }
+        }
+
+        let text = self.utf8(start)?;
+        if is_integer {
+            if let Ok(value) = text.parse() {
+                return Ok(Object::Integer(value));
+            }
+        }
+
+        match text.parse::<f32>() {
+            Ok(value) if value.is_finite() => Ok(Object::Real(value)),
+            _ => Err(Error::new(ErrorKind::LimitCheck, "number out of range")),
+        }
+    }
+}
+
+struct Writer<'a> {
+    heap: &'a Heap,
+    options: &'a JsonOptions,
+    out: String,
+    parents: Vec<u32>,
+}
+
+impl Writer<'_> {
+    fn value(&mut self, obj: &Object) -> crate::Result<()> {
+        match obj {
+            Object::Boolean(value) => {
+                let _ = write!(self.out, "{value}");
+            },
+            Object::Integer(value) => {
+                let _ = write!(self.out, "{value}");
+            },
+            Object::Real(value) if value.is_finite() => {
+                // Debug formatting keeps a fraction, so reals come back as reals
+                let _ = write!(self.out, "{value:?}");
+            },
+            Object::Real(_) => {
+                return Err(Error::new(ErrorKind::RangeCheck, "non-finite real"));
+            },
+            Object::Null(_) => self.out.push_str("null"),
+            Object::Name(name) => match self.options.names {
+                JsonNames::String => self.bytes(name.value())?,
+                JsonNames::Slashed => {
+                    let mut value = b"/".to_vec();
+                    value.extend_from_slice(name.value());
+                    self.bytes(&value)?;
+                },
+                JsonNames::Error => {
+                    return Err(Error::new(ErrorKind::TypeCheck, "name in JSON"));
+                },
+            },
+            Object::String(string) => {
+                let string = self.heap.get(*string);
+                if !string.access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
+
+                self.bytes(string.value())?;
+            },
+            Object::Array(_) | Object::PackedArray(_) => {
+                let heap = self.heap;
+                let (mode, objs) = heap.elements(obj).unwrap_or_default();
+
+                if mode.is_executable() {
+                    match self.options.procedures {
+                        JsonProcedures::Error => {
+                            return Err(Error::new(ErrorKind::TypeCheck, "procedure in JSON"));
+                        },
+                        JsonProcedures::Null => self.out.push_str("null"),
+                        JsonProcedures::Source => {
+                            let source = format!("{:?}", heap.format(obj));
+                            self.string(&source);
+                        },
+                    }
+
+                    return Ok(());
+                }
+
+                self.enter(obj)?;
+
+                // Packed arrays are always read-only, so only arrays can
+                // deny reading
+                if let Object::Array(arr) = obj {
+                    if !heap.get(*arr).access().is_readable() {
+                        return Err(Error::from(ErrorKind::InvalidAccess));
+                    }
+                }
+
+                self.out.push('[');
+                for (index, obj) in objs.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push(',');
+                    }
+                    self.value(obj)?;
+                }
+                self.out.push(']');
+
+                self.parents.pop();
+            },
+            Object::Dictionary(dict) => {
+                self.enter(obj)?;
+
+                let dict = self.heap.get(*dict);
+                if !dict.access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
+
+                self.out.push('{');
+                for (index, (key, value)) in dict.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push(',');
+                    }
+                    match key {
+                        Object::Name(name) => self.bytes(name.value())?,
+                        Object::String(_) => self.value(key)?,
+                        _ => {
+                            return Err(Error::new(
+                                ErrorKind::TypeCheck,
+                                "JSON keys must be names or strings",
+                            ));
+                        },
+                    }
+                    self.out.push(':');
+                    self.value(value)?;
+                }
+                self.out.push('}');
+
+                self.parents.pop();
+            },
+            obj => {
+                let type_name: &str = obj.into();
+
+                return Err(Error::new(
+                    ErrorKind::TypeCheck,
+                    format!("{type_name} has no JSON equivalent"),
+                ));
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Guards against arrays and dictionaries that contain themselves, which
+    /// JSON cannot express, and against nesting deeper than the parser would
+    /// read back.
+    fn enter(&mut self, obj: &Object) -> crate::Result<()> {
+        let Some(index) = heap::index(obj) else {
+            return Ok(());
+        };
+
+        if self.parents.contains(&index) {
+            return Err(Error::new(ErrorKind::LimitCheck, "cyclic object in JSON"));
+        }
+        if self.parents.len() >= MAX_DEPTH {
+            return Err(Error::new(
+                ErrorKind::LimitCheck,
+                "object nested too deeply for JSON",
+            ));
+        }
+        self.parents.push(index);
+
+        Ok(())
+    }
+
+    fn bytes(&mut self, value: &[u8]) -> crate::Result<()> {
+        if let Ok(value) = str::from_utf8(value) {
+            self.string(value);
+            return Ok(());
+        }
+
+        match self.options.binary {
+            JsonBinary::Error => Err(Error::new(ErrorKind::TypeCheck, "string is not UTF-8")),
+            JsonBinary::Lossy => {
+                self.string(&String::from_utf8_lossy(value));
+                Ok(())
+            },
+            JsonBinary::Bytes => {
+                self.out.push('[');
+                for (index, byte) in value.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push(',');
+                    }
+                    let _ = write!(self.out, "{byte}");
+                }
+                self.out.push(']');
+                Ok(())
+            },
+        }
+    }
+
+    fn string(&mut self, value: &str) {
+        self.out.push('"');
+        for ch in value.chars() {
+            match ch {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                '\x08' => self.out.push_str("\\b"),
+                '\x0c' => self.out.push_str("\\f"),
+                ch if u32::from(ch) < 0x20 => {
+                    let _ = write!(self.out, "\\u{:04x}", u32::from(ch));
+                },
+                ch => self.out.push(ch),
+            }
+        }
+        self.out.push('"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::PackedArray;
+
+    fn round_trip(json: &str, options: &JsonOptions) -> crate::Result<String> {
+        let mut heap = Heap::default();
+        let obj = parse(json, options, &mut heap)?;
+
+        write(&obj, options, &heap)
+    }
+
+    #[test]
+    fn test_round_trip() -> crate::Result<()> {
+        let options = JsonOptions::default();
+        let cases = [
+            r#"{"a":[1,-2,3.5,0.25],"b":{"c":null,"d":true,"e":false}}"#,
+            r#""quote \" slash \\ tab \t nul \u0000 é 😀""#,
+            "[]",
+            "{}",
+            "2.0",
+        ];
+
+        for json in cases {
+            assert_eq!(json, round_trip(json, &options)?);
+        }
+
+        // Escapes are decoded, and only written back when required
+        assert_eq!(
+            r#""é😀/""#,
+            round_trip(r#""\u00e9\ud83d\ude00\/""#, &options)?
+        );
+        assert_eq!("1e20", round_trip("1e20", &options)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let options = JsonOptions::default();
+        let mut heap = Heap::default();
+
+        for json in [
+            "",
+            "[1,]",
+            "{\"a\" 1}",
+            "01",
+            "-",
+            "\"\\ud800\"",
+            "[1] 2",
+            "tru",
+        ] {
+            let result = parse(json, &options, &mut heap);
+            assert_eq!(
+                Some(ErrorKind::SyntaxError),
+                result.err().map(|e| e.kind()),
+                "{json}"
+            );
+        }
+
+        let deep = "[".repeat(MAX_DEPTH + 1);
+        let result = parse(&deep, &options, &mut heap);
+        assert_eq!(Some(ErrorKind::LimitCheck), result.err().map(|e| e.kind()));
+    }
+
+    #[test]
+    fn test_write_too_deep() -> crate::Result<()> {
+        let options = JsonOptions::default();
+        let mut heap = Heap::default();
+
+        let mut obj = Object::Integer(1);
+        for _ in 0..MAX_DEPTH {
+            obj = Object::Array(heap.alloc(ArrayObject::new(
+                vec![obj],
+                Access::Unlimited,
+                Mode::Literal,
+            )));
+        }
+        assert_eq!(2 * MAX_DEPTH + 1, write(&obj, &options, &heap)?.len());
+
+        let obj = Object::Array(heap.alloc(ArrayObject::new(
+            vec![obj],
+            Access::Unlimited,
+            Mode::Literal,
+        )));
+        let result = write(&obj, &options, &heap);
+        assert_eq!(Some(ErrorKind::LimitCheck), result.err().map(|e| e.kind()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_arrays() -> crate::Result<()> {
+        let mut heap = Heap::default();
+        let packed = Object::PackedArray(heap.alloc(PackedArray::new(
+            vec![Object::Integer(1), Object::Boolean(true)],
+            Mode::Literal,
+        )));
+        let proc = Object::PackedArray(
+            heap.alloc(PackedArray::new(vec![Object::Integer(1)], Mode::Executable)),
+        );
+
+        let options = JsonOptions::default();
+        assert_eq!("[1,true]", write(&packed, &options, &heap)?);
+        assert!(write(&proc, &options, &heap).is_err());
+
+        let options = JsonOptions {
+            procedures: JsonProcedures::Source,
+            ..JsonOptions::default()
+        };
+        assert_eq!("\"{1}\"", write(&proc, &options, &heap)?);
+
+        let options = JsonOptions {
+            procedures: JsonProcedures::Null,
+            ..JsonOptions::default()
+        };
+        assert_eq!("null", write(&proc, &options, &heap)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_options() -> crate::Result<()> {
+        let mut heap = Heap::default();
+        let name = Object::Name(NameObject::new("key", Mode::Literal));
+        let binary = Object::String(heap.alloc(StringObject::from(vec![b'a', 0xff])));
+        let proc = Object::Array(heap.alloc(ArrayObject::new(
+            vec![Object::Integer(1), name],
+            Access::Unlimited,
+            Mode::Executable,
+        )));
+
+        let options = JsonOptions::default();
+        assert_eq!("\"key\"", write(&name, &options, &heap)?);
+        assert!(write(&binary, &options, &heap).is_err());
+        assert!(write(&proc, &options, &heap).is_err());
+
+        let options = JsonOptions {
+            binary: JsonBinary::Bytes,
+            names: JsonNames::Slashed,
+            procedures: JsonProcedures::Source,
+        };
+        assert_eq!("\"/key\"", write(&name, &options, &heap)?);
+        assert_eq!("[97,255]", write(&binary, &options, &heap)?);
+        assert_eq!("\"{1 /key}\"", write(&proc, &options, &heap)?);
+        assert!(matches!(
+            parse("\"/key\"", &options, &mut heap)?,
+            Object::Name(name) if name == *"key"
+        ));
+
+        let options = JsonOptions {
+            binary: JsonBinary::Lossy,
+            names: JsonNames::Error,
+            procedures: JsonProcedures::Null,
+        };
+        assert!(write(&name, &options, &heap).is_err());
+        assert_eq!("\"a\u{fffd}\"", write(&binary, &options, &heap)?);
+        assert_eq!("null", write(&proc, &options, &heap)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+
+use crate::{Error, ErrorKind};
+
+/// The PostScript LanguageLevel an interpreter emulates. Operators,
+/// resources and syntax introduced by later levels are unavailable.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LanguageLevel {
+    Level1 = 1,
+    Level2 = 2,
+    #[default]
+    Level3 = 3,
+}
+
+impl fmt::Display for LanguageLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        i32::from(*self).fmt(f)
+    }
+}
+
+impl From<LanguageLevel> for i32 {
+    fn from(value: LanguageLevel) -> Self {
+        value as i32
+    }
+}
+
+impl TryFrom<i32> for LanguageLevel {
+    type Error = Error;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(LanguageLevel::Level1),
+            2 => Ok(LanguageLevel::Level2),
+            3 => Ok(LanguageLevel::Level3),
+            _ => Err(Error::new(
+                ErrorKind::RangeCheck,
+                "unsupported language level",
+            )),
+        }
+    }
+}
@@ -1,18 +1,18 @@
 use std::{
-    cell::RefCell,
-    io::{Read, Seek, SeekFrom},
-    rc::Rc,
-    string,
+    io::{self, Read, Seek, SeekFrom},
+    mem, string,
 };
 
 use crate::{
     array::ArrayObject,
-    encoding::{decode_ascii85, decode_hex},
+    encoding::{decode_ascii85, decode_hex, SYSTEM_NAMES},
     file::FileObject,
+    heap::{Handle, Heap},
     name::NameObject,
     object::{Access, Mode, Object},
+    operator::OperatorObject,
     string::StringObject,
-    Error, ErrorKind,
+    Error, ErrorKind, LanguageLevel,
 };
 
 const FORM_FEED: u8 = b'\x0C';
@@ -20,12 +20,138 @@ const BACKSPACE: u8 = b'\x08';
 
 pub(crate) struct Lexer {
     input: FileObject,
+    is_at_end: bool,
+    is_waiting: bool,
+    language_level: LanguageLevel,
+    pending: Option<Object>,
+    /// The bodies of the procedures scanning is inside of, outermost first.
+    /// They are kept between calls, so a procedure that is still arriving
+    /// over a stream is not scanned again from its start.
+    procs: Vec<Vec<Object>>,
+    shared: Option<Handle<FileObject>>,
 }
 
-impl Iterator for Lexer {
-    type Item = crate::Result<Object>;
+impl Lexer {
+    pub fn new<F: Into<FileObject>>(input: F, language_level: LanguageLevel) -> Self {
+        Self {
+            input: input.into(),
+            is_at_end: false,
+            is_waiting: false,
+            language_level,
+            pending: None,
+            procs: Vec::new(),
+            shared: None,
+        }
+    }
+
+    /// Creates a lexer that scans a file in the heap. Anything else reading
+    /// the file picks up where the lexer left off, and the other way around.
+    pub fn shared(file: Handle<FileObject>, language_level: LanguageLevel) -> Self {
+        Self {
+            input: FileObject::default(),
+            is_at_end: false,
+            is_waiting: false,
+            language_level,
+            pending: None,
+            procs: Vec::new(),
+            shared: Some(file),
+        }
+    }
+
+    /// Scans the next object, allocating any composite objects it makes in
+    /// `heap`.
+    pub fn next_object(&mut self, heap: &mut Heap) -> Option<crate::Result<Object>> {
+        if let Some(obj) = self.pending.take() {
+            return Some(Ok(obj));
+        }
+
+        let Some(file) = self.shared else {
+            return self.scan(heap);
+        };
+
+        // The file is moved out of the heap while scanning, rather than
+        // copied, so its cursor advances in place
+        self.input = mem::take(heap.get_mut(file));
+
+        self.is_at_end = false;
+        let obj = self.scan(heap);
+        self.is_waiting = obj.is_none() && self.is_at_end && self.input.is_streaming();
+
+        *heap.get_mut(file) = mem::take(&mut self.input);
+
+        obj
+    }
+
+    /// The objects scanned so far that have not been returned yet.
+    pub fn objects(&self) -> impl Iterator<Item = Object> + '_ {
+        self.pending
+            .into_iter()
+            .chain(self.procs.iter().flatten().copied())
+    }
+
+    /// Whether the last call to `next_object` ran out of streamed input,
+    /// rather than reaching the end of the file.
+    pub fn is_waiting(&self) -> bool {
+        self.is_waiting
+    }
+
+    fn scan(&mut self, heap: &mut Heap) -> Option<crate::Result<Object>> {
+        loop {
+            let obj = match self.pending.take() {
+                Some(obj) => obj,
+                None => {
+                    let start = self.input.cursor();
+                    let obj = self.scan_token(heap);
+
+                    // A token that runs into the end of a streamed file may
+                    // continue in data that has not arrived yet, so it is
+                    // scanned again later
+                    if self.is_at_end && self.input.is_streaming() {
+                        self.input.set_cursor(start);
+                        return None;
+                    }
+
+                    match obj {
+                        Some(Ok(obj)) => obj,
+                        None if self.procs.is_empty() => return None,
+                        None => {
+                            self.procs.clear();
+                            return Some(Err(Error::new(
+                                ErrorKind::SyntaxError,
+                                "unterminated procedure",
+                            )));
+                        },
+                        Some(Err(e)) => {
+                            self.procs.clear();
+                            return Some(Err(e));
+                        },
+                    }
+                },
+            };
+
+            let obj = match obj {
+                Object::Name(ref name) if name == "{" => {
+                    self.procs.push(Vec::new());
+                    continue;
+                },
+                Object::Name(ref name) if name == "}" => match self.procs.pop() {
+                    Some(objs) => {
+                        let arr = ArrayObject::new(objs, Access::Unlimited, Mode::Executable);
+                        Object::Array(heap.alloc(arr))
+                    },
+                    None => obj,
+                },
+                obj => obj,
+            };
+
+            match self.procs.last_mut() {
+                Some(proc) => proc.push(obj),
+                None => return Some(Ok(obj)),
+            }
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    fn scan_token(&mut self, heap: &mut Heap) -> Option<crate::Result<Object>> {
         loop {
             if self.next_is_whitespace() {
                 self.next_char()?;
@@ -38,23 +164,58 @@ impl Iterator for Lexer {
                     Ok(_) => continue,
                     Err(e) => Some(Err(e)),
                 },
-                b'-' | b'.' | b'0'..=b'9' => Some(self.lex_numeric()),
-                b'(' => Some(self.lex_string_literal()),
-                b'<' => Some(self.lex_gt()),
-                b'{' => Some(self.lex_procedure()),
+                b'-' | b'.' | b'0'..=b'9' => {
+                    let obj = self.lex_numeric();
+                    self.skip_terminator();
+
+                    Some(obj)
+                },
+                b'(' => Some(self.lex_string_literal(heap)),
+                b'<' => Some(self.lex_gt(heap)),
+                b'>' if self.language_level < LanguageLevel::Level2 => {
+                    let _ = self.next_char();
+                    if self.peek_char() == Some(b'>') {
+                        let _ = self.next_char();
+                    }
+
+                    Some(Err(Error::new(
+                        ErrorKind::SyntaxError,
+                        "dictionary syntax requires LanguageLevel 2",
+                    )))
+                },
+                b'{' => {
+                    let _ = self.next_char();
+                    Some(Ok(Object::Name(NameObject::from("{"))))
+                },
+                128..=159 if self.language_level >= LanguageLevel::Level2 => {
+                    Some(self.lex_binary_token(heap))
+                },
                 _ => {
                     let name = String::new();
-                    Some(self.lex_name(name))
+                    let obj = self.lex_name(name);
+                    self.skip_terminator();
+
+                    Some(obj)
                 },
             };
         }
     }
-}
 
-impl Lexer {
-    pub fn new<F: Into<FileObject>>(input: F) -> Self {
-        Self {
-            input: input.into(),
+    /// Consumes the white-space character that ends a name or number, so
+    /// that data read from the file afterwards starts right after it. A
+    /// carriage return and line feed count as one character.
+    fn skip_terminator(&mut self) {
+        match self.peek_char() {
+            Some(b'\r') => {
+                let _ = self.next_char();
+                if self.peek_char() == Some(b'\n') {
+                    let _ = self.next_char();
+                }
+            },
+            Some(ch) if is_whitespace(ch) => {
+                let _ = self.next_char();
+            },
+            _ => {},
         }
     }
 
@@ -74,7 +235,7 @@ impl Lexer {
         Ok(())
     }
 
-    fn lex_gt(&mut self) -> crate::Result<Object> {
+    fn lex_gt(&mut self, heap: &mut Heap) -> crate::Result<Object> {
         self.expect_char(b'<')?;
 
         let Some(ch) = self.peek_char() else {
@@ -85,12 +246,26 @@ impl Lexer {
         };
 
         match ch {
+            b'<' if self.language_level < LanguageLevel::Level2 => {
+                let _ = self.next_char();
+                Err(Error::new(
+                    ErrorKind::SyntaxError,
+                    "dictionary syntax requires LanguageLevel 2",
+                ))
+            },
             b'<' => {
                 let _ = self.next_char();
                 Ok(Object::Name(NameObject::from("<<")))
             },
-            b'~' => self.lex_string_base85(),
-            b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F' => self.lex_string_hex(),
+            b'~' if self.language_level < LanguageLevel::Level2 => {
+                let _ = self.lex_string_base85(heap);
+                Err(Error::new(
+                    ErrorKind::SyntaxError,
+                    "base85 strings require LanguageLevel 2",
+                ))
+            },
+            b'~' => self.lex_string_base85(heap),
+            b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F' => self.lex_string_hex(heap),
             _ => self.lex_name("<".to_string()),
         }
     }
@@ -192,31 +367,7 @@ impl Lexer {
         }
     }
 
-    fn lex_procedure(&mut self) -> crate::Result<Object> {
-        self.expect_char(b'{')?;
-
-        let mut objs = Vec::new();
-
-        loop {
-            let obj = self
-                .next()
-                .ok_or(Error::new(ErrorKind::SyntaxError, "unterminated procedure"))??;
-
-            if let Object::Name(ref n) = obj {
-                if n == "}" {
-                    break;
-                }
-            }
-
-            objs.push(obj);
-        }
-
-        let arr = ArrayObject::new(objs, Access::ExecuteOnly, Mode::Executable);
-
-        Ok(Object::Array(Rc::new(RefCell::new(arr))))
-    }
-
-    fn lex_string_base85(&mut self) -> crate::Result<Object> {
+    fn lex_string_base85(&mut self, heap: &mut Heap) -> crate::Result<Object> {
         let mut string = string::String::new();
 
         loop {
@@ -234,7 +385,10 @@ impl Lexer {
                             "unterminated base85 string",
                         ))
                     },
-                    Some(b'>') => break,
+                    Some(b'>') => {
+                        let _ = self.next_char();
+                        break;
+                    },
                     _ => continue,
                 },
                 Some(ch) => string.push(ch as char),
@@ -243,10 +397,10 @@ impl Lexer {
 
         let string = StringObject::new(decode_ascii85(&string)?, Mode::Literal);
 
-        Ok(Object::String(Rc::new(RefCell::new(string))))
+        Ok(Object::String(heap.alloc(string)))
     }
 
-    fn lex_string_hex(&mut self) -> crate::Result<Object> {
+    fn lex_string_hex(&mut self, heap: &mut Heap) -> crate::Result<Object> {
         let mut string = String::new();
 
         loop {
@@ -268,13 +422,13 @@ impl Lexer {
 
         let string = StringObject::new(decode_hex(&string)?, Mode::Literal);
 
-        Ok(Object::String(Rc::new(RefCell::new(string))))
+        Ok(Object::String(heap.alloc(string)))
     }
 
-    fn lex_string_literal(&mut self) -> crate::Result<Object> {
+    fn lex_string_literal(&mut self, heap: &mut Heap) -> crate::Result<Object> {
         self.expect_char(b'(')?;
 
-        let mut string = String::new();
+        let mut string = Vec::new();
         let mut active_parenthesis = 0;
 
         loop {
@@ -284,14 +438,14 @@ impl Lexer {
 
             match ch {
                 b'(' => {
-                    string.push('(');
+                    string.push(b'(');
                     active_parenthesis += 1;
                 },
                 b')' => {
                     if active_parenthesis < 1 {
                         break;
                     }
-                    string.push(')');
+                    string.push(b')');
                     active_parenthesis -= 1;
                 },
                 b'\\' => {
@@ -301,14 +455,14 @@ impl Lexer {
                     }?;
                     match next_ch {
                         b'\n' => continue,
-                        b'r' => string.push('\r'),
-                        b'n' => string.push('\n'),
-                        b't' => string.push('\t'),
-                        b'b' => string.push(BACKSPACE as char),
-                        b'f' => string.push(FORM_FEED as char),
-                        b'\\' => string.push('\\'),
-                        b'(' => string.push('('),
-                        b')' => string.push(')'),
+                        b'r' => string.push(b'\r'),
+                        b'n' => string.push(b'\n'),
+                        b't' => string.push(b'\t'),
+                        b'b' => string.push(BACKSPACE),
+                        b'f' => string.push(FORM_FEED),
+                        b'\\' => string.push(b'\\'),
+                        b'(' => string.push(b'('),
+                        b')' => string.push(b')'),
                         b'\r' => match self.peek_char() {
                             None => {
                                 return Err(Error::new(
@@ -321,36 +475,223 @@ impl Lexer {
                             },
                             _ => {},
                         },
-                        b'0'..=b'9' => {
-                            let mut octal: u8 = 0;
-                            octal |= next_ch << 6;
-
-                            let ch1 = self.next_char();
-                            let ch2 = self.next_char();
-                            let _ = self.input.seek(SeekFrom::Current(-2));
-
-                            if let (Some(ch1), Some(ch2)) = (ch1, ch2) {
-                                octal |= ch1 << 3;
-                                octal |= ch2;
-                            } else {
-                                return Err(Error::new(
-                                    ErrorKind::SyntaxError,
-                                    "invalid octal number",
-                                ));
+                        b'0'..=b'7' => {
+                            // Up to three octal digits, with overflow ignored
+                            let mut octal = next_ch - b'0';
+                            for _ in 0..2 {
+                                match self.peek_char() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        let _ = self.next_char();
+                                        octal = octal.wrapping_mul(8).wrapping_add(digit - b'0');
+                                    },
+                                    _ => break,
+                                }
                             }
 
-                            string.push(octal.into());
+                            string.push(octal);
                         },
-                        _ => string.push(next_ch as char),
+                        _ => string.push(next_ch),
                     }
                 },
-                _ => string.push(ch as char),
+                _ => string.push(ch),
             }
         }
 
-        let string = StringObject::new(string, Mode::Literal);
+        let string = StringObject::from(string);
+
+        Ok(Object::String(heap.alloc(string)))
+    }
+
+    fn lex_binary_token(&mut self, heap: &mut Heap) -> crate::Result<Object> {
+        let token_type = self.next_byte()?;
+
+        match token_type {
+            128..=131 => self.lex_binary_object_sequence(token_type, heap),
+            132 => Ok(Object::Integer(i32::from_be_bytes(self.next_bytes()?))),
+            133 => Ok(Object::Integer(i32::from_le_bytes(self.next_bytes()?))),
+            134 => Ok(Object::Integer(
+                i16::from_be_bytes(self.next_bytes()?).into(),
+            )),
+            135 => Ok(Object::Integer(
+                i16::from_le_bytes(self.next_bytes()?).into(),
+            )),
+            136 => Ok(Object::Integer(
+                i8::from_be_bytes(self.next_bytes()?).into(),
+            )),
+            137 => {
+                let representation = self.next_byte()?;
+                if representation & 0x7F >= 48 {
+                    return Err(Error::new(
+                        ErrorKind::SyntaxError,
+                        "invalid fixed point representation",
+                    ));
+                }
 
-        Ok(Object::String(Rc::new(RefCell::new(string))))
+                self.lex_binary_number(representation)
+            },
+            138 => Ok(Object::Real(f32::from_be_bytes(self.next_bytes()?))),
+            139 => Ok(Object::Real(f32::from_le_bytes(self.next_bytes()?))),
+            140 => Ok(Object::Real(f32::from_ne_bytes(self.next_bytes()?))),
+            141 => match self.next_byte()? {
+                0 => Ok(Object::Boolean(false)),
+                1 => Ok(Object::Boolean(true)),
+                _ => Err(Error::new(ErrorKind::SyntaxError, "invalid boolean")),
+            },
+            142..=144 => {
+                let len = match token_type {
+                    142 => usize::from(self.next_byte()?),
+                    143 => usize::from(u16::from_be_bytes(self.next_bytes()?)),
+                    _ => usize::from(u16::from_le_bytes(self.next_bytes()?)),
+                };
+
+                let mut buf = vec![0; len];
+                self.read_exact(&mut buf).or(Err(Error::new(
+                    ErrorKind::SyntaxError,
+                    "unterminated string",
+                )))?;
+
+                Ok(Object::String(heap.alloc(StringObject::from(buf))))
+            },
+            145 | 146 => {
+                let mode = if token_type == 145 {
+                    Mode::Literal
+                } else {
+                    Mode::Executable
+                };
+
+                system_name(usize::from(self.next_byte()?), mode)
+            },
+            147 | 148 => Err(Error::new(
+                ErrorKind::Undefined,
+                "user names are not supported",
+            )),
+            149 => {
+                let representation = self.next_byte()?;
+                let len = if representation >= 128 {
+                    u16::from_le_bytes(self.next_bytes()?)
+                } else {
+                    u16::from_be_bytes(self.next_bytes()?)
+                };
+
+                let objs = (0..len)
+                    .map(|_| self.lex_binary_number(representation))
+                    .collect::<crate::Result<Vec<Object>>>()?;
+
+                let arr = ArrayObject::new(objs, Access::Unlimited, Mode::Literal);
+
+                Ok(Object::Array(heap.alloc(arr)))
+            },
+            _ => Err(Error::new(
+                ErrorKind::SyntaxError,
+                "unassigned binary token",
+            )),
+        }
+    }
+
+    /// Reads a number in the representation shared by fixed point binary
+    /// tokens and homogeneous number arrays.
+    fn lex_binary_number(&mut self, representation: u8) -> crate::Result<Object> {
+        let (value, scale) = match representation {
+            0..=31 => (i32::from_be_bytes(self.next_bytes()?), representation),
+            32..=47 => (
+                i16::from_be_bytes(self.next_bytes()?).into(),
+                representation - 32,
+            ),
+            48 => return Ok(Object::Real(f32::from_be_bytes(self.next_bytes()?))),
+            49 | 177 => return Ok(Object::Real(f32::from_ne_bytes(self.next_bytes()?))),
+            128..=159 => (i32::from_le_bytes(self.next_bytes()?), representation - 128),
+            160..=175 => (
+                i16::from_le_bytes(self.next_bytes()?).into(),
+                representation - 160,
+            ),
+            176 => return Ok(Object::Real(f32::from_le_bytes(self.next_bytes()?))),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::SyntaxError,
+                    "invalid number representation",
+                ))
+            },
+        };
+
+        Ok(fixed_point(value, scale))
+    }
+
+    fn lex_binary_object_sequence(
+        &mut self,
+        token_type: u8,
+        heap: &mut Heap,
+    ) -> crate::Result<Object> {
+        let is_little_endian = token_type % 2 == 1;
+        let read_u16 = |bytes: [u8; 2]| {
+            if is_little_endian {
+                u16::from_le_bytes(bytes)
+            } else {
+                u16::from_be_bytes(bytes)
+            }
+        };
+
+        let (len, header_len, total_len) = match self.next_byte()? {
+            0 => {
+                let len = read_u16(self.next_bytes()?);
+                let total_len = self.next_bytes()?;
+                let total_len = if is_little_endian {
+                    u32::from_le_bytes(total_len)
+                } else {
+                    u32::from_be_bytes(total_len)
+                };
+
+                (usize::from(len), 8, total_len as usize)
+            },
+            len => (
+                usize::from(len),
+                4,
+                usize::from(read_u16(self.next_bytes()?)),
+            ),
+        };
+
+        let Some(body_len) = total_len.checked_sub(header_len) else {
+            return Err(Error::new(
+                ErrorKind::SyntaxError,
+                "invalid binary object sequence",
+            ));
+        };
+
+        let mut body = vec![0; body_len];
+        self.read_exact(&mut body).or(Err(Error::new(
+            ErrorKind::SyntaxError,
+            "unterminated binary object sequence",
+        )))?;
+
+        let sequence = BinaryObjectSequence {
+            body,
+            is_little_endian,
+        };
+        let objs = sequence.array(0, len, heap, &mut Vec::new())?;
+
+        // The top level array is executed as soon as it is scanned rather
+        // than deferred like a procedure
+        self.pending = Some(Object::Operator((OperatorObject::Exec, Mode::Executable)));
+
+        let arr = ArrayObject::new(objs, Access::Unlimited, Mode::Executable);
+
+        Ok(Object::Array(heap.alloc(arr)))
+    }
+
+    fn next_byte(&mut self) -> crate::Result<u8> {
+        self.next_char().ok_or(Error::new(
+            ErrorKind::SyntaxError,
+            "unterminated binary token",
+        ))
+    }
+
+    fn next_bytes<const N: usize>(&mut self) -> crate::Result<[u8; N]> {
+        let mut buf = [0; N];
+        self.read_exact(&mut buf).or(Err(Error::new(
+            ErrorKind::SyntaxError,
+            "unterminated binary token",
+        )))?;
+
+        Ok(buf)
     }
 
     fn expect_char(&mut self, ch: u8) -> crate::Result<()> {
@@ -364,10 +705,20 @@ impl Lexer {
         let mut buf: [u8; 1] = [0];
         match self.input.read(&mut buf) {
             Ok(1) => Some(buf[0]),
-            _ => None,
+            _ => {
+                self.is_at_end = true;
+                None
+            },
         }
     }
 
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let result = self.input.read_exact(buf);
+        self.is_at_end |= result.is_err();
+
+        result
+    }
+
     fn peek_char(&mut self) -> Option<u8> {
         if let Some(ch) = self.next_char() {
             let _ = self.input.seek(SeekFrom::Current(-1));
@@ -378,11 +729,17 @@ impl Lexer {
     }
 
     fn next_is_delimiter(&mut self) -> bool {
-        self.peek_char().is_some_and(is_delimiter)
+        self.peek_char()
+            .is_some_and(|ch| is_delimiter(ch) || self.is_binary_token(ch))
     }
 
     fn next_is_regular(&mut self) -> bool {
-        self.peek_char().is_some_and(is_regular)
+        self.peek_char()
+            .is_some_and(|ch| is_regular(ch) && !self.is_binary_token(ch))
+    }
+
+    fn is_binary_token(&self, ch: u8) -> bool {
+        self.language_level >= LanguageLevel::Level2 && (128..=159).contains(&ch)
     }
 
     fn next_is_whitespace(&mut self) -> bool {
@@ -390,6 +747,144 @@ impl Lexer {
     }
 }
 
+struct BinaryObjectSequence {
+    body: Vec<u8>,
+    is_little_endian: bool,
+}
+
+impl BinaryObjectSequence {
+    fn array(
+        &self,
+        offset: usize,
+        len: usize,
+        heap: &mut Heap,
+        active: &mut Vec<usize>,
+    ) -> crate::Result<Vec<Object>> {
+        // Arrays refer to each other by offset, so a malformed sequence can
+        // contain itself
+        if active.contains(&offset) {
+            return Err(Error::new(
+                ErrorKind::SyntaxError,
+                "recursive binary object sequence",
+            ));
+        }
+
+        active.push(offset);
+        let objs = (0..len)
+            .map(|index| self.object(offset + index * 8, heap, active))
+            .collect();
+        active.pop();
+
+        objs
+    }
+
+    fn object(
+        &self,
+        offset: usize,
+        heap: &mut Heap,
+        active: &mut Vec<usize>,
+    ) -> crate::Result<Object> {
+        let Some(bytes) = self.body.get(offset..offset + 8) else {
+            return Err(invalid_sequence());
+        };
+
+        let mode = if bytes[0] & 0x80 == 0 {
+            Mode::Literal
+        } else {
+            Mode::Executable
+        };
+        let len = self.u16([bytes[2], bytes[3]]);
+        let value = [bytes[4], bytes[5], bytes[6], bytes[7]];
+        let int = if self.is_little_endian {
+            i32::from_le_bytes(value)
+        } else {
+            i32::from_be_bytes(value)
+        };
+
+        match bytes[0] & 0x7F {
+            0 => Ok(Object::Null(mode)),
+            1 => Ok(Object::Integer(int)),
+            2 if len == 0 => Ok(Object::Real(if self.is_little_endian {
+                f32::from_le_bytes(value)
+            } else {
+                f32::from_be_bytes(value)
+            })),
+            2 if len < 32 => Ok(fixed_point(int, len as u8)),
+            3 if len == 0 => system_name(int as usize, mode),
+            3 if len == u16::MAX => Err(Error::new(
+                ErrorKind::Undefined,
+                "user names are not supported",
+            )),
+            3 => {
+                let name = self.text(int, len)?;
+                let name = String::from_utf8_lossy(name);
+
+                Ok(Object::Name(NameObject::new(&name, mode)))
+            },
+            4 => Ok(Object::Boolean(int != 0)),
+            5 => {
+                let string = StringObject::from(self.text(int, len)?.to_vec());
+
+                Ok(Object::String(heap.alloc(string)))
+            },
+            6 => Err(Error::new(
+                ErrorKind::Undefined,
+                "immediately evaluated names are not supported",
+            )),
+            9 => {
+                let offset = usize::try_from(int).or(Err(invalid_sequence()))?;
+                let objs = self.array(offset, usize::from(len), heap, active)?;
+
+                Ok(Object::Array(heap.alloc(ArrayObject::new(
+                    objs,
+                    Access::Unlimited,
+                    mode,
+                ))))
+            },
+            10 => Ok(Object::Mark),
+            _ => Err(invalid_sequence()),
+        }
+    }
+
+    fn text(&self, offset: i32, len: u16) -> crate::Result<&[u8]> {
+        let offset = usize::try_from(offset).or(Err(invalid_sequence()))?;
+
+        self.body
+            .get(offset..offset + usize::from(len))
+            .ok_or(invalid_sequence())
+    }
+
+    fn u16(&self, bytes: [u8; 2]) -> u16 {
+        if self.is_little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        }
+    }
+}
+
+fn invalid_sequence() -> Error {
+    Error::new(ErrorKind::SyntaxError, "invalid binary object sequence")
+}
+
+fn fixed_point(value: i32, scale: u8) -> Object {
+    if scale == 0 {
+        return Object::Integer(value);
+    }
+
+    Object::Real((f64::from(value) / f64::from(1_u32 << scale)) as f32)
+}
+
+fn system_name(index: usize, mode: Mode) -> crate::Result<Object> {
+    match SYSTEM_NAMES.get(index) {
+        Some(name) => Ok(Object::Name(NameObject::new(name, mode))),
+        None => Err(Error::new(
+            ErrorKind::Undefined,
+            format!("system name {index}"),
+        )),
+    }
+}
+
 fn is_delimiter(ch: u8) -> bool {
     matches!(
         ch,
@@ -407,3 +902,70 @@ fn is_whitespace(ch: u8) -> bool {
         b'\0' | b' ' | b'\t' | b'\r' | b'\n' | BACKSPACE | FORM_FEED
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_procedure() -> crate::Result<()> {
+        let mut heap = Heap::default();
+        let file = heap.alloc(FileObject::from(String::from("{ 1 { 2 } add } (tail)")));
+
+        let mut lexer = Lexer::shared(file, LanguageLevel::Level3);
+        let Some(Object::Array(proc)) = lexer.next_object(&mut heap).transpose()? else {
+            panic!("expected a procedure");
+        };
+        assert_eq!(3, heap.get(proc).len());
+
+        // Anything else reading the file picks up after the procedure body
+        let mut reader = Lexer::shared(file, LanguageLevel::Level3);
+        let Some(Object::String(string)) = reader.next_object(&mut heap).transpose()? else {
+            panic!("expected a string");
+        };
+        assert_eq!(b"tail", heap.get(string).value());
+        assert!(lexer.next_object(&mut heap).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_streamed_procedure() -> crate::Result<()> {
+        let mut heap = Heap::default();
+        let mut input = FileObject::default();
+        input.set_streaming(true);
+        let file = heap.alloc(input);
+        let mut lexer = Lexer::shared(file, LanguageLevel::Level3);
+
+        for chunk in ["{ 1 (a", ") { 2 }", " ad", "d } "] {
+            assert!(lexer.next_object(&mut heap).is_none());
+            assert!(lexer.is_waiting());
+            let _ = io::Write::write_all(heap.get_mut(file), chunk.as_bytes());
+        }
+
+        let Some(Object::Array(proc)) = lexer.next_object(&mut heap).transpose()? else {
+            panic!("expected a procedure");
+        };
+        assert_eq!(4, heap.get(proc).len());
+
+        // Tokens that were complete are only scanned once, so nothing is
+        // left over besides the file, the string and the two procedures
+        assert_eq!(4, heap.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_octal_escapes() -> crate::Result<()> {
+        let mut heap = Heap::default();
+        let file = heap.alloc(FileObject::from(String::from(r"(\000\377\1a\12\0059\8)")));
+
+        let mut lexer = Lexer::shared(file, LanguageLevel::Level3);
+        let Some(Object::String(string)) = lexer.next_object(&mut heap).transpose()? else {
+            panic!("expected a string");
+        };
+        assert_eq!(b"\0\xFF\x01a\n\x0598", heap.get(string).value());
+
+        Ok(())
+    }
+}
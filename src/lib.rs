@@ -1,191 +1,1414 @@
 use std::{
     cell::RefCell,
-    io::{StdoutLock, Write},
-    process,
-    rc::Rc,
-    result,
+    collections::HashMap,
+    io::{Read, Write},
+    mem, result,
+    sync::{
+        atomic::{self, AtomicBool},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+use array::ArrayObject;
+use bytecode::Instruction;
+use context::Context;
+pub use convert::{FromObject, IntoObject};
 use dictionary::DictionaryObject;
 pub use error::{Error, ErrorKind};
 pub use file::FileObject;
+use frame::Frame;
+pub use function::Function;
+use heap::{Handle, Heap};
+pub use identity::Identity;
+use image::Vm;
+pub use json::{JsonBinary, JsonNames, JsonOptions, JsonProcedures};
+pub use language_level::LanguageLevel;
 use lexer::Lexer;
+pub use limits::Limits;
+use name::NameObject;
+use object::Access;
 pub use object::{Mode, Object};
 use operator::OperatorObject;
+pub use page::Page;
+pub use rooted::Rooted;
+use save::Save;
 use string::StringObject;
 
 mod array;
+mod bytecode;
+mod context;
+mod convert;
 mod dictionary;
 mod encoding;
 mod error;
+mod ffi;
 mod file;
+mod frame;
+mod function;
+mod heap;
+mod identity;
+mod image;
+mod json;
+mod language_level;
 mod lexer;
+mod limits;
 mod name;
 mod object;
 mod operator;
+mod operators;
+mod page;
+mod rooted;
+mod save;
 mod string;
+mod visit;
 
 type Result<T> = result::Result<T, Error>;
 
+const MAX_ARRAY_LENGTH: usize = 65535;
+const MAX_DICT_LENGTH: usize = 65535;
+const MAX_DICT_STACK: usize = 250;
+const MAX_EXECUTION_STACK: usize = 250;
+const MAX_OPERAND_STACK: usize = 500;
+const MAX_SAVE_LEVEL: usize = 15;
+const DETERMINISTIC_SEED: i32 = 1;
+
+/// The PostScript run whenever an interpreter is created, defining the
+/// procedures of systemdict and statusdict that are simpler to write in
+/// PostScript.
+pub const PRELUDE: &[u8] = include_bytes!("prelude.ps");
+
+// Room left in systemdict for the prelude's definitions, since dictionaries
+// cannot grow past their capacity at LanguageLevel 1
+const PRELUDE_CAPACITY: usize = 100;
+
+const SYSTEM_OPERATORS: &[OperatorObject] = &[
+    OperatorObject::Clear,
+    OperatorObject::ClearToMark,
+    OperatorObject::Copy,
+    OperatorObject::Count,
+    OperatorObject::CountToMark,
+    OperatorObject::Dup,
+    OperatorObject::Exch,
+    OperatorObject::Index,
+    OperatorObject::Mark,
+    OperatorObject::Pop,
+    OperatorObject::Roll,
+    OperatorObject::Abs,
+    OperatorObject::Add,
+    OperatorObject::Atan,
+    OperatorObject::Ceiling,
+    OperatorObject::Cos,
+    OperatorObject::Div,
+    OperatorObject::Exp,
+    OperatorObject::Floor,
+    OperatorObject::Idiv,
+    OperatorObject::Ln,
+    OperatorObject::Log,
+    OperatorObject::Mod,
+    OperatorObject::Mul,
+    OperatorObject::Neg,
+    OperatorObject::Rand,
+    OperatorObject::Round,
+    OperatorObject::RRand,
+    OperatorObject::Sin,
+    OperatorObject::Sqrt,
+    OperatorObject::SRand,
+    OperatorObject::Sub,
+    OperatorObject::Truncate,
+    OperatorObject::Aload,
+    OperatorObject::Array,
+    OperatorObject::ArrayEnd,
+    OperatorObject::ArrayStart,
+    OperatorObject::Astore,
+    OperatorObject::ForAll,
+    OperatorObject::Get,
+    OperatorObject::GetInterval,
+    OperatorObject::Length,
+    OperatorObject::Put,
+    OperatorObject::PutInterval,
+    OperatorObject::Begin,
+    OperatorObject::ClearDictStack,
+    OperatorObject::CountDictStack,
+    OperatorObject::CurrentDict,
+    OperatorObject::Def,
+    OperatorObject::Dict,
+    OperatorObject::DictEnd,
+    OperatorObject::DictStack,
+    OperatorObject::DictStart,
+    OperatorObject::End,
+    OperatorObject::Known,
+    OperatorObject::Load,
+    OperatorObject::MaxLength,
+    OperatorObject::Store,
+    OperatorObject::Undef,
+    OperatorObject::Where,
+    OperatorObject::Eq,
+    OperatorObject::Ne,
+    OperatorObject::Null,
+    OperatorObject::Type,
+    OperatorObject::CountExecStack,
+    OperatorObject::Exec,
+    OperatorObject::ExecStack,
+    OperatorObject::Exit,
+    OperatorObject::For,
+    OperatorObject::If,
+    OperatorObject::IfElse,
+    OperatorObject::Loop,
+    OperatorObject::Quit,
+    OperatorObject::Repeat,
+    OperatorObject::Stop,
+    OperatorObject::Stopped,
+    OperatorObject::CurrentFile,
+    OperatorObject::File,
+    OperatorObject::Flush,
+    OperatorObject::PStack,
+    OperatorObject::Print,
+    OperatorObject::PrintSyntax,
+    OperatorObject::PrintValue,
+    OperatorObject::Read,
+    OperatorObject::ReadLine,
+    OperatorObject::ReadString,
+    OperatorObject::Run,
+    OperatorObject::Stack,
+    OperatorObject::DefineFont,
+    OperatorObject::DefineResource,
+    OperatorObject::FindFont,
+    OperatorObject::FindResource,
+    OperatorObject::ResourceStatus,
+    OperatorObject::UndefineResource,
+    OperatorObject::CurrentGlobal,
+    OperatorObject::DefineUserObject,
+    OperatorObject::ExecUserObject,
+    OperatorObject::GCheck,
+    OperatorObject::Restore,
+    OperatorObject::Save,
+    OperatorObject::SetGlobal,
+    OperatorObject::UndefineUserObject,
+    OperatorObject::CurrentDevParams,
+    OperatorObject::CurrentSystemParams,
+    OperatorObject::CurrentUserParams,
+    OperatorObject::SetDevParams,
+    OperatorObject::SetSystemParams,
+    OperatorObject::SetUserParams,
+    OperatorObject::CurrentPageDevice,
+    OperatorObject::SetPageDevice,
+    OperatorObject::ShowPage,
+    OperatorObject::Condition,
+    OperatorObject::CurrentContext,
+    OperatorObject::Detach,
+    OperatorObject::DeviceInfo,
+    OperatorObject::Fork,
+    OperatorObject::Join,
+    OperatorObject::Lock,
+    OperatorObject::Monitor,
+    OperatorObject::Notify,
+    OperatorObject::Wait,
+    OperatorObject::Yield,
+    OperatorObject::StartJob,
+    OperatorObject::Bind,
+    OperatorObject::LanguageLevel,
+    OperatorObject::Product,
+    OperatorObject::RealTime,
+    OperatorObject::Revision,
+    OperatorObject::SerialNumber,
+    OperatorObject::UserTime,
+    OperatorObject::Version,
+    OperatorObject::HandleError,
+];
+
+const RESOURCE_CATEGORIES: &[(&str, LanguageLevel)] = &[
+    ("CIDFont", LanguageLevel::Level3),
+    ("CMap", LanguageLevel::Level3),
+    ("ColorRendering", LanguageLevel::Level2),
+    ("ColorSpace", LanguageLevel::Level2),
+    ("ControlLanguage", LanguageLevel::Level3),
+    ("Encoding", LanguageLevel::Level2),
+    ("FontSet", LanguageLevel::Level3),
+    ("Form", LanguageLevel::Level2),
+    ("Generic", LanguageLevel::Level2),
+    ("HWOptions", LanguageLevel::Level3),
+    ("Halftone", LanguageLevel::Level2),
+    ("IdiomSet", LanguageLevel::Level3),
+    ("InkParams", LanguageLevel::Level3),
+    ("Localization", LanguageLevel::Level3),
+    ("OutputDevice", LanguageLevel::Level3),
+    ("PDL", LanguageLevel::Level3),
+    ("Pattern", LanguageLevel::Level2),
+    ("ProcSet", LanguageLevel::Level2),
+    ("TrapParams", LanguageLevel::Level3),
+];
+
 pub struct Interpreter {
-    dict_stack: Vec<Rc<RefCell<DictionaryObject>>>,
+    awaiting_input: bool,
+    contexts: Vec<Context>,
+    current_context: i32,
+    deadline: Option<Instant>,
+    deterministic: bool,
+    device_params: DictionaryObject,
+    dict_stack: Vec<Handle<DictionaryObject>>,
+    display_postscript: bool,
+    error_dict: Handle<DictionaryObject>,
+    error_info: Handle<DictionaryObject>,
+    execution_stack: Vec<Frame>,
+    font_directory: Handle<DictionaryObject>,
+    global_dict: Handle<DictionaryObject>,
+    heap: Heap,
+    host_limits: Limits,
+    identity: Identity,
+    interrupt: Arc<AtomicBool>,
+    job_save: Option<Handle<Save>>,
+    job_server: bool,
+    job_timeout: Option<Duration>,
+    language_level: LanguageLevel,
+    last_error: Option<Error>,
+    limits: Limits,
+    name_cache: RefCell<HashMap<NameObject, Object>>,
+    next_context_id: i32,
     operand_stack: Vec<Object>,
-    stdout: StdoutLock<'static>,
+    page_count: i32,
+    page_device: Handle<DictionaryObject>,
+    page_handler: Option<Box<dyn FnMut(Page)>>,
+    quit: bool,
+    random_seed: i32,
+    resources: Handle<DictionaryObject>,
+    save_stack: Vec<Handle<Save>>,
+    start_job_password: Vec<u8>,
+    start_time: Instant,
+    status_dict: Handle<DictionaryObject>,
+    stdout: Box<dyn Write>,
+    streamed_job: Option<StreamedJob>,
+    system_params: DictionaryObject,
+    system_params_password: Vec<u8>,
     transaction_stack: Vec<Object>,
+    user_dict: Handle<DictionaryObject>,
+    user_params: DictionaryObject,
 }
 
 impl Interpreter {
-    pub fn new(stdout: StdoutLock<'static>) -> Self {
-        let mut system_dict = DictionaryObject::default();
-        system_dict.insert(
-            Object::String(Rc::new(RefCell::new("exec".into()))),
-            Object::Operator((OperatorObject::Exec, Mode::Executable)),
-        );
-        system_dict.insert(
-            Object::String(Rc::new(RefCell::new("flush".into()))),
-            Object::Operator((OperatorObject::Flush, Mode::Executable)),
-        );
-        system_dict.insert(
-            Object::String(Rc::new(RefCell::new("print".into()))),
-            Object::Operator((OperatorObject::Print, Mode::Executable)),
-        );
-        system_dict.insert(
-            Object::String(Rc::new(RefCell::new("quit".into()))),
-            Object::Operator((OperatorObject::Quit, Mode::Executable)),
+    pub fn new<W: Write + 'static>(stdout: W) -> Self {
+        Self::with_language_level(stdout, LanguageLevel::default())
+    }
+
+    pub fn with_language_level<W: Write + 'static>(
+        stdout: W,
+        language_level: LanguageLevel,
+    ) -> Self {
+        Self::with_prelude(stdout, language_level, PRELUDE).expect("bundled prelude failed")
+    }
+
+    /// Creates an interpreter that runs `prelude` in place of the bundled
+    /// `PRELUDE`. Its definitions land in systemdict, which is read-only
+    /// afterwards. An error in the prelude is returned rather than reported.
+    pub fn with_prelude<W: Write + 'static>(
+        stdout: W,
+        language_level: LanguageLevel,
+        prelude: &[u8],
+    ) -> crate::Result<Self> {
+        let mut heap = Heap::default();
+
+        let error_dict = heap.alloc(error_dict());
+        let error_info = heap.alloc(error_info_dict());
+        let status_dict = heap.alloc(status_dict());
+        let user_dict = heap.alloc(DictionaryObject::new(200, Access::Unlimited));
+
+        // systemdict and the dictionaries shared by every job are in global
+        // VM, so `restore` leaves them alone
+        heap.set_global(true);
+        let font_directory = heap.alloc(DictionaryObject::new(200, Access::Unlimited));
+        let global_dict = heap.alloc(DictionaryObject::new(200, Access::Unlimited));
+        let resources = resource_dict(&mut heap, font_directory);
+        let resources = heap.alloc(resources);
+
+        let system_dict = system_dict(
+            &mut heap,
+            error_dict,
+            error_info,
+            font_directory,
+            status_dict,
+            user_dict,
         );
+        let system_dict = heap.alloc(system_dict);
+        heap.get_mut(system_dict)
+            .insert(literal_name("systemdict"), Object::Dictionary(system_dict));
+        heap.set_global(false);
+
+        let vm = Vm {
+            device_params: device_params(&mut heap),
+            dict_stack: vec![system_dict, global_dict, user_dict],
+            display_postscript: false,
+            error_dict,
+            error_info,
+            font_directory,
+            global_dict,
+            identity: Identity::default(),
+            language_level,
+            page_device: page_device(&mut heap),
+            resources,
+            status_dict,
+            system_params: system_params(&mut heap),
+            user_dict,
+            user_params: user_params(&mut heap),
+            heap,
+        };
+
+        let mut interpreter = Self::from_vm(Box::new(stdout), vm);
+        interpreter.set_language_level(language_level);
+        interpreter.set_identity(Identity::default());
+        interpreter.run_prelude(prelude)?;
+
+        Ok(interpreter)
+    }
+
+    /// Starts an interpreter from an image written by `dump_image`, which
+    /// skips building systemdict and the rest of the initial VM. Images from
+    /// other versions of the crate are rejected with a `VMerror`.
+    pub fn load_image<W: Write + 'static, R: Read>(stdout: W, reader: R) -> crate::Result<Self> {
+        let vm = image::read(reader)?;
+
+        Ok(Self::from_vm(Box::new(stdout), vm))
+    }
+
+    /// Writes the VM to `writer` as an image that `load_image` can start from.
+    /// The interpreter must be idle, with no job running or saved VM state.
+    pub fn dump_image<W: Write>(&mut self, writer: W) -> crate::Result<()> {
+        if !self.execution_stack.is_empty()
+            || !self.save_stack.is_empty()
+            || self.job_save.is_some()
+            || self.streamed_job.is_some()
+            || self.contexts.len() > 1
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidContext,
+                "cannot dump an image while a job is running",
+            ));
+        }
 
-        let dict_stack = vec![Rc::new(RefCell::new(system_dict))];
+        self.collect_garbage();
 
-        Self {
+        let vm = Vm {
+            device_params: self.device_params.clone(),
+            dict_stack: self.dict_stack.clone(),
+            display_postscript: self.display_postscript,
+            error_dict: self.error_dict,
+            error_info: self.error_info,
+            font_directory: self.font_directory,
+            global_dict: self.global_dict,
+            heap: mem::take(&mut self.heap),
+            identity: self.identity.clone(),
+            language_level: self.language_level,
+            page_device: self.page_device,
+            resources: self.resources,
+            status_dict: self.status_dict,
+            system_params: self.system_params.clone(),
+            user_dict: self.user_dict,
+            user_params: self.user_params.clone(),
+        };
+        let result = image::write(writer, &vm);
+        self.heap = vm.heap;
+
+        result
+    }
+
+    fn run_prelude(&mut self, prelude: &[u8]) -> crate::Result<()> {
+        let system_dict = self.dict_stack[0];
+        let dict_stack_len = self.dict_stack.len();
+        self.dict_stack.push(system_dict);
+        self.forget_names();
+
+        // What the prelude defines lands in systemdict, so it belongs in
+        // global VM too
+        self.heap.set_global(true);
+        self.push_file(FileObject::from(prelude.to_vec()));
+        let base = self.begin_exec();
+        self.run(base);
+        let error = match self.operand_stack.pop() {
+            Some(Object::Boolean(true)) => self.stopped_error(),
+            _ => None,
+        };
+        self.heap.set_global(false);
+
+        self.dict_stack.truncate(dict_stack_len);
+        self.forget_names();
+        self.operand_stack.clear();
+        self.heap.get_mut(system_dict).set_access(Access::ReadOnly);
+
+        match error {
+            Some(e) => {
+                self.heap
+                    .get_mut(self.error_info)
+                    .insert(literal_name("newerror"), Object::Boolean(false));
+
+                Err(e)
+            },
+            None => Ok(()),
+        }
+    }
+
+    fn from_vm(stdout: Box<dyn Write>, vm: Vm) -> Self {
+        let mut interpreter = Self {
+            awaiting_input: false,
+            contexts: vec![Context::new(1)],
+            current_context: 1,
+            deadline: None,
+            deterministic: false,
+            device_params: vm.device_params,
+            dict_stack: vm.dict_stack,
+            display_postscript: vm.display_postscript,
+            error_dict: vm.error_dict,
+            error_info: vm.error_info,
+            execution_stack: Vec::new(),
+            font_directory: vm.font_directory,
+            global_dict: vm.global_dict,
+            heap: vm.heap,
+            host_limits: Limits::default(),
+            identity: vm.identity,
+            interrupt: Arc::new(AtomicBool::new(false)),
+            job_save: None,
+            job_server: false,
+            job_timeout: None,
+            language_level: vm.language_level,
+            last_error: None,
+            limits: Limits::default(),
+            name_cache: RefCell::default(),
+            next_context_id: 2,
             operand_stack: Vec::new(),
-            transaction_stack: Vec::new(),
-            dict_stack,
+            page_count: 0,
+            page_device: vm.page_device,
+            page_handler: None,
+            quit: false,
+            random_seed: DETERMINISTIC_SEED,
+            resources: vm.resources,
+            save_stack: Vec::new(),
+            start_job_password: Vec::new(),
+            start_time: Instant::now(),
+            status_dict: vm.status_dict,
             stdout,
+            streamed_job: None,
+            system_params: vm.system_params,
+            system_params_password: Vec::new(),
+            transaction_stack: Vec::new(),
+            user_dict: vm.user_dict,
+            user_params: vm.user_params,
+        };
+        interpreter.set_random_seed(realtime() as i32);
+        interpreter.set_vm_threshold();
+
+        interpreter
+    }
+
+    pub fn with_debug_utils<W: Write + 'static>(stdout: W) -> Self {
+        let mut interpreter = Self::new(stdout);
+
+        let system_dict = interpreter.heap.get_mut(interpreter.dict_stack[0]);
+        for (key, obj) in debug_dict() {
+            system_dict.insert(key, obj);
+        }
+
+        // Errors are recorded in $error without stopping, so tests can
+        // inspect them with `asserterror` and carry on
+        let error_dict = interpreter.heap.get_mut(interpreter.error_dict);
+        for kind in ErrorKind::ALL {
+            error_dict.insert(
+                literal_name(kind.into()),
+                Object::Operator((OperatorObject::RecoverFromError(kind), Mode::Executable)),
+            );
         }
+
+        interpreter
+    }
+
+    /// Returns a flag that raises an `interrupt` error in the running job
+    /// once it is set.
+    pub fn interrupt_flag(&self) -> Arc<AtomicBool> {
+        self.interrupt.clone()
     }
 
     pub fn push_file(&mut self, file: FileObject) {
-        self.operand_stack
-            .push(Object::File(Rc::new(RefCell::new(file))));
+        let file = self.heap.alloc(file);
+        self.operand_stack.push(Object::File(file));
     }
 
     pub fn push_string(&mut self, string: StringObject) {
-        self.operand_stack
-            .push(Object::String(Rc::new(RefCell::new(string))))
+        let string = self.heap.alloc(string);
+        self.operand_stack.push(Object::String(string))
     }
 
-    fn execute_object(&mut self, obj: Object) -> crate::Result<()> {
-        match obj {
-            Object::Name(name) => {
-                let def = self.find(&Object::Name(name))?;
-                self.operand_stack.push(def.clone());
-                self.exec()
-            },
-            Object::Operator((operator, _)) => match operator {
-                OperatorObject::Exec => self.exec(),
-                OperatorObject::Flush => self.flush(),
-                OperatorObject::Print => self.print(),
-                OperatorObject::Quit => process::exit(0),
-            },
-            Object::Array(array) => {
-                let array = array.borrow();
+    pub fn push_value<T: IntoObject>(&mut self, value: T) -> crate::Result<()> {
+        let obj = value.into_object(&mut self.heap);
+
+        self.push(obj)
+    }
+
+    /// Pops the top operand, leaving it in place if it cannot be converted.
+    pub fn pop_value<T: FromObject>(&mut self) -> crate::Result<T> {
+        let value = self.peek_value()?;
+        self.operand_stack.pop();
+
+        Ok(value)
+    }
+
+    pub fn peek_value<T: FromObject>(&self) -> crate::Result<T> {
+        let obj = self
+            .operand_stack
+            .last()
+            .copied()
+            .ok_or(Error::from(ErrorKind::StackUnderflow))?;
+
+        T::from_object(obj, &self.heap)
+    }
+
+    /// Parses JSON into an object in the interpreter's VM, for pushing or
+    /// defining with `push_value` and `def_value`.
+    pub fn parse_json(&mut self, json: &str, options: &JsonOptions) -> crate::Result<Rooted> {
+        let obj = json::parse(json, options, &mut self.heap)?;
+
+        Ok(self.heap.root(obj))
+    }
+
+    pub fn to_json(&self, obj: Object, options: &JsonOptions) -> crate::Result<String> {
+        json::write(&obj, options, &self.heap)
+    }
+
+    /// Looks `key` up on the dictionary stack, the way `load` does.
+    pub fn load_value<T: FromObject>(&self, key: &str) -> crate::Result<T> {
+        let obj = self.find(&literal_name(key))?;
+
+        T::from_object(obj, &self.heap)
+    }
+
+    /// Defines `key` in the current dictionary, the way `def` does.
+    pub fn def_value<T: IntoObject>(&mut self, key: &str, value: T) -> crate::Result<()> {
+        let dict = self
+            .dict_stack
+            .last()
+            .copied()
+            .ok_or(Error::from(ErrorKind::DictStackUnderflow))?;
+
+        self.put_value(Object::Dictionary(dict), key, value)
+    }
+
+    pub fn get_value<T: FromObject>(&self, dict: Object, key: &str) -> crate::Result<T> {
+        let Object::Dictionary(dict) = dict else {
+            return Err(Error::new(ErrorKind::TypeCheck, "expected dictionary"));
+        };
+
+        let dict = self.heap.get(dict);
+        if !dict.access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        let obj = dict
+            .get(&literal_name(key))
+            .copied()
+            .ok_or(Error::new(ErrorKind::Undefined, key.to_string()))?;
+
+        T::from_object(obj, &self.heap)
+    }
+
+    pub fn put_value<T: IntoObject>(
+        &mut self,
+        dict: Object,
+        key: &str,
+        value: T,
+    ) -> crate::Result<()> {
+        let Object::Dictionary(dict) = dict else {
+            return Err(Error::new(ErrorKind::TypeCheck, "expected dictionary"));
+        };
+
+        let value = value.into_object(&mut self.heap);
+
+        self.insert(dict, literal_name(key), value)
+    }
+
+    /// Makes jobs reproducible: `realtime` and `usertime` always report 0
+    /// and the random number generator is reseeded with a fixed value.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+
+        if deterministic {
+            self.set_random_seed(DETERMINISTIC_SEED);
+        }
+    }
+
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    pub fn set_identity(&mut self, identity: Identity) {
+        // Level 1 drivers read the identity from statusdict rather than
+        // through the operators
+        let mut product = StringObject::from(identity.product.clone().into_bytes());
+        product.set_access(Access::ReadOnly);
+        let product = self.heap.alloc(product);
+        let status_dict = self.heap.get_mut(self.status_dict);
+        status_dict.insert(literal_name("product"), Object::String(product));
+        status_dict.insert(literal_name("revision"), Object::Integer(identity.revision));
+        self.forget_name(&literal_name("product"));
+        self.forget_name(&literal_name("revision"));
 
-                for obj in array.iter() {
-                    self.operand_stack.push(obj.clone());
-                    self.exec()?;
+        self.identity = identity;
+    }
+
+    pub fn language_level(&self) -> LanguageLevel {
+        self.language_level
+    }
+
+    /// Switches the emulated LanguageLevel, hiding the operators and resource
+    /// categories that it does not define. Takes effect for input scanned
+    /// afterwards.
+    pub fn set_language_level(&mut self, language_level: LanguageLevel) {
+        self.language_level = language_level;
+        self.define_system_operators();
+
+        let system_dict = self.heap.get_mut(self.dict_stack[0]);
+        // globaldict sits between systemdict and userdict from LanguageLevel 2
+        let has_global_dict = self.dict_stack[1] == self.global_dict;
+        if language_level < LanguageLevel::Level2 {
+            system_dict.remove(&literal_name("globaldict"));
+            if has_global_dict {
+                self.dict_stack.remove(1);
+            }
+        } else {
+            system_dict.insert(
+                literal_name("globaldict"),
+                Object::Dictionary(self.global_dict),
+            );
+            if !has_global_dict {
+                self.dict_stack.insert(1, self.global_dict);
+            }
+        }
+
+        for (category, level) in RESOURCE_CATEGORIES {
+            let key = literal_name(category);
+            if *level > language_level {
+                self.heap.get_mut(self.resources).remove(&key);
+            } else if !self.heap.get(self.resources).contains_key(&key) {
+                let global = self.heap.global();
+                self.heap.set_global(true);
+                let instances = self.heap.alloc(DictionaryObject::default());
+                self.heap.set_global(global);
+                self.heap
+                    .get_mut(self.resources)
+                    .insert(key, Object::Dictionary(instances));
+            }
+        }
+    }
+
+    /// Makes the Display PostScript context operators, such as `fork` and
+    /// `monitor`, available. They are an extension rather than part of any
+    /// LanguageLevel, so they are left out unless a host asks for them.
+    pub fn set_display_postscript(&mut self, enabled: bool) {
+        self.display_postscript = enabled;
+        self.define_system_operators();
+    }
+
+    /// Puts the operators the LanguageLevel and extensions allow in
+    /// systemdict, and takes the rest out.
+    fn define_system_operators(&mut self) {
+        self.forget_names();
+
+        let system_dict = self.heap.get_mut(self.dict_stack[0]);
+        for op in SYSTEM_OPERATORS {
+            let (key, obj) = operator(*op);
+            let is_enabled = op.language_level() <= self.language_level
+                && (self.display_postscript || !op.is_display_postscript());
+            if is_enabled {
+                // The prelude may have replaced the operator with a procedure
+                if !system_dict.contains_key(&key) {
+                    system_dict.insert(key, obj);
                 }
+            } else {
+                system_dict.remove(&key);
+            }
+        }
+    }
 
-                Ok(())
-            },
-            Object::File(file) => {
-                let lexer = Lexer::new(file.borrow().clone());
+    /// Sets the ceilings jobs can raise their stack limits to. Current limits
+    /// above the new ceilings are lowered to them.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.host_limits = limits;
+        self.limits = Limits {
+            max_dict_stack: self.limits.max_dict_stack.min(limits.max_dict_stack),
+            max_exec_stack: self.limits.max_exec_stack.min(limits.max_exec_stack),
+            max_op_stack: self.limits.max_op_stack.min(limits.max_op_stack),
+        };
+    }
 
-                for obj in lexer {
-                    self.operand_stack.push(obj?);
-                    self.exec()?;
+    /// Sets the password `startjob` and `exitserver` require to leave job
+    /// encapsulation. An empty password accepts any.
+    pub fn set_start_job_password(&mut self, password: &[u8]) {
+        self.start_job_password = password.to_vec();
+    }
+
+    /// Sets the password `setsystemparams` and `setdevparams` require. An
+    /// empty password accepts any.
+    pub fn set_system_params_password(&mut self, password: &[u8]) {
+        self.system_params_password = password.to_vec();
+    }
+
+    /// Limits how long each job may run before a `timeout` error is raised.
+    pub fn set_job_timeout(&mut self, timeout: Option<Duration>) {
+        self.job_timeout = timeout;
+    }
+
+    /// Redirects everything jobs print.
+    pub fn set_output<W: Write + 'static>(&mut self, output: W) {
+        self.stdout = Box::new(output);
+    }
+
+    /// Calls `handler` with every page `showpage` transmits.
+    pub fn set_page_handler<F: FnMut(Page) + 'static>(&mut self, handler: F) {
+        self.page_handler = Some(Box::new(handler));
+    }
+
+    /// The error that stopped the most recent job, which `exec` reports
+    /// through `handleerror` rather than returning.
+    pub fn last_error(&self) -> Option<&Error> {
+        self.last_error.as_ref()
+    }
+
+    /// Whether the most recent job ended by executing `quit`. The job is
+    /// over, but the interpreter can go on running others.
+    pub fn has_quit(&self) -> bool {
+        self.quit
+    }
+
+    fn run(&mut self, base: usize) {
+        let host = self.current_context;
+
+        loop {
+            let context_base = if self.current_context == host {
+                base
+            } else {
+                0
+            };
+            if self.quit {
+                // A forked context that quits takes the whole job with it
+                if self.current_context != host {
+                    self.unwind_to(0);
+                    self.resume_context(host);
+                }
+                self.unwind_to(base);
+                return;
+            }
+
+            if self.execution_stack.len() <= context_base {
+                if self.current_context != host {
+                    self.terminate_context(host);
+                    continue;
                 }
 
-                Ok(())
+                // Give forked contexts the chance to run until they finish or
+                // block before returning to the host
+                let Some(id) = self.next_ready_context() else {
+                    return;
+                };
+                self.resume_context(id);
+                continue;
+            }
+
+            // Operands the last command consumed are only given back when
+            // that command fails, not when an error arrives between commands
+            self.transaction_stack.clear();
+
+            if self.interrupt.swap(false, atomic::Ordering::Relaxed) {
+                self.initiate_error(
+                    Object::Null(Mode::Literal),
+                    Error::from(ErrorKind::Interrupt),
+                );
+                continue;
+            }
+
+            if self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                self.deadline = None;
+                self.initiate_error(Object::Null(Mode::Literal), Error::from(ErrorKind::Timeout));
+                continue;
+            }
+
+            if self.heap.needs_collection() {
+                self.collect_garbage();
+            }
+
+            self.step();
+
+            if self.awaiting_input {
+                return;
+            }
+        }
+    }
+
+    fn step(&mut self) {
+        let Some(frame) = self.execution_stack.last_mut() else {
+            return;
+        };
+
+        match frame {
+            Frame::Object(_) => {
+                if let Some(Frame::Object(obj)) = self.execution_stack.pop() {
+                    self.execute(obj);
+                }
             },
-            Object::String(string) => {
-                let value: Vec<u8> = string.borrow().value().into();
-                let lexer = Lexer::new(FileObject::from(value));
+            Frame::Procedure(proc, index) => {
+                let (instruction, len) = {
+                    let proc = self.heap.get(*proc);
+                    let instruction = match proc.compiled() {
+                        Some(compiled) => compiled.get(*index).cloned(),
+                        None => proc.value().get(*index).copied().map(Instruction::Token),
+                    };
+
+                    (instruction, proc.len())
+                };
 
-                for obj in lexer {
-                    self.operand_stack.push(obj?);
-                    self.exec()?;
+                let Some(instruction) = instruction else {
+                    self.execution_stack.pop();
+                    return;
+                };
+
+                *index += 1;
+                if *index >= len {
+                    // Drop the finished procedure before its last element
+                    // runs, so tail calls do not grow the execution stack
+                    self.execution_stack.pop();
                 }
 
-                Ok(())
+                match instruction {
+                    Instruction::Operator(operator) => {
+                        self.transaction_stack.clear();
+                        if let Err(e) = self.execute_operator(operator) {
+                            self.initiate_error(Object::Operator((operator, Mode::Executable)), e);
+                        }
+                    },
+                    Instruction::Push(obj) => {
+                        if let Err(e) = self.push(obj) {
+                            self.initiate_error(obj, e);
+                        }
+                    },
+                    Instruction::Call(name) => self.call(name),
+                    Instruction::Token(obj) => self.execute_token(obj),
+                }
+            },
+            Frame::Lexer(source, lexer) => match lexer.next_object(&mut self.heap) {
+                None if lexer.is_waiting() => self.awaiting_input = true,
+                None => {
+                    self.execution_stack.pop();
+                },
+                Some(Err(e)) => {
+                    let source = *source;
+                    self.initiate_error(source, e);
+                },
+                Some(Ok(obj)) => self.execute_token(obj),
+            },
+            Frame::Stopped => {
+                self.execution_stack.pop();
+                self.operand_stack.push(Object::Boolean(false));
+            },
+            Frame::Monitor(lock) => {
+                self.heap.get_mut(*lock).owner = None;
+                self.execution_stack.pop();
+            },
+            Frame::Loop(loop_frame) => match loop_frame.next_iteration(&self.heap) {
+                None => {
+                    self.execution_stack.pop();
+                },
+                Some((operands, proc)) => {
+                    for obj in operands {
+                        if let Err(e) = self.push(obj) {
+                            self.initiate_error(proc, e);
+                            return;
+                        }
+                    }
+
+                    self.execute(proc);
+                },
             },
-            _ => Err(Error::new(ErrorKind::Unregistered, "not implemented")),
         }
     }
 
-    fn clear_transaction(&mut self) {
+    /// Executes an object encountered while scanning a procedure or file.
+    /// Procedures found this way are pushed rather than executed.
+    fn execute_token(&mut self, obj: Object) {
+        if let Object::Array(_) = obj {
+            if let Err(e) = self.push(obj) {
+                self.initiate_error(obj, e);
+            }
+            return;
+        }
+
+        self.execute(obj);
+    }
+
+    /// Runs what a compiled name resolves to. Operators and procedures skip
+    /// the dispatch `execute` would go through to reach them.
+    fn call(&mut self, name: NameObject) {
         self.transaction_stack.clear();
+
+        let def = match self.find(&Object::Name(name)) {
+            Ok(def) => def,
+            Err(e) => {
+                self.initiate_error(Object::Name(name), e);
+                return;
+            },
+        };
+
+        let result = match def {
+            Object::Operator((operator, Mode::Executable)) => self.execute_operator(operator),
+            Object::Array(proc) if self.heap.get(proc).mode().is_executable() => {
+                self.push_frame(Frame::Procedure(proc, 0))
+            },
+            _ => {
+                self.execute(def);
+                return;
+            },
+        };
+
+        if let Err(e) = result {
+            self.initiate_error(def, e);
+        }
     }
 
-    fn initiate_error(&mut self, source: Object, error: Error) {
-        // TODO: actually initiate the error
-        println!("{error}");
+    fn execute(&mut self, obj: Object) {
+        self.transaction_stack.clear();
+
+        if let Err(e) = self.execute_object(obj) {
+            self.initiate_error(obj, e);
+        }
+    }
+
+    fn execute_object(&mut self, obj: Object) -> crate::Result<()> {
+        if obj.mode(&self.heap).is_literal() {
+            return self.push(obj);
+        }
+
+        match obj {
+            Object::Name(name) => {
+                let def = self.find(&Object::Name(name))?;
+                self.execute(def);
+
+                Ok(())
+            },
+            Object::Operator((operator, _)) => self.execute_operator(operator),
+            Object::Array(array) => self.push_frame(Frame::Procedure(array, 0)),
+            Object::File(file) => {
+                let lexer = Lexer::shared(file, self.language_level);
+                self.push_frame(Frame::Lexer(obj, lexer))
+            },
+            Object::String(string) => {
+                let value: Vec<u8> = self.heap.get(string).value().into();
+                let lexer = Lexer::new(FileObject::from(value), self.language_level);
+                self.push_frame(Frame::Lexer(obj, lexer))
+            },
+            Object::Null(_) => Ok(()),
+            _ => self.push(obj),
+        }
+    }
+
+    fn execute_operator(&mut self, operator: OperatorObject) -> crate::Result<()> {
+        match operator {
+            OperatorObject::Clear => self.clear(),
+            OperatorObject::ClearToMark => self.cleartomark(),
+            OperatorObject::Copy => self.copy(),
+            OperatorObject::Count => self.count(),
+            OperatorObject::CountToMark => self.counttomark(),
+            OperatorObject::Dup => self.dup(),
+            OperatorObject::Exch => self.exch(),
+            OperatorObject::Index => self.index(),
+            OperatorObject::Mark | OperatorObject::ArrayStart | OperatorObject::DictStart => {
+                self.mark()
+            },
+            OperatorObject::Pop => self.pop().map(|_| ()),
+            OperatorObject::Roll => self.roll(),
+            OperatorObject::Abs => self.num_unary(i32::checked_abs, f64::abs),
+            OperatorObject::Add => self.arithmetic(i32::checked_add, |a, b| a + b),
+            OperatorObject::Atan => self.atan(),
+            OperatorObject::Ceiling => self.num_unary(Some, f64::ceil),
+            OperatorObject::Cos => self.cos(),
+            OperatorObject::Div => self.arithmetic(|_, _| None, |a, b| a / b),
+            OperatorObject::Exp => self.arithmetic(|_, _| None, f64::powf),
+            OperatorObject::Floor => self.num_unary(Some, f64::floor),
+            OperatorObject::Idiv => self.idiv(),
+            OperatorObject::Ln => self.real_unary(f64::ln),
+            OperatorObject::Log => self.real_unary(f64::log10),
+            OperatorObject::Mod => self.imod(),
+            OperatorObject::Mul => self.arithmetic(i32::checked_mul, |a, b| a * b),
+            OperatorObject::Neg => self.num_unary(i32::checked_neg, |a| -a),
+            OperatorObject::Rand => self.rand(),
+            OperatorObject::Round => self.num_unary(Some, f64::round),
+            OperatorObject::RRand => self.rrand(),
+            OperatorObject::Sin => self.sin(),
+            OperatorObject::Sqrt => self.real_unary(f64::sqrt),
+            OperatorObject::SRand => self.srand(),
+            OperatorObject::Sub => self.arithmetic(i32::checked_sub, |a, b| a - b),
+            OperatorObject::Truncate => self.num_unary(Some, f64::trunc),
+            OperatorObject::Aload => self.aload(),
+            OperatorObject::Array => self.array(),
+            OperatorObject::ArrayEnd => self.endarray(),
+            OperatorObject::Astore => self.astore(),
+            OperatorObject::ForAll => self.forall(),
+            OperatorObject::Get => self.get(),
+            OperatorObject::GetInterval => self.getinterval(),
+            OperatorObject::Length => self.length(),
+            OperatorObject::Put => self.put(),
+            OperatorObject::PutInterval => self.putinterval(),
+            OperatorObject::Begin => self.begin(),
+            OperatorObject::ClearDictStack => self.cleardictstack(),
+            OperatorObject::CountDictStack => self.countdictstack(),
+            OperatorObject::CurrentDict => self.currentdict(),
+            OperatorObject::Def => self.def(),
+            OperatorObject::Dict => self.dict(),
+            OperatorObject::DictEnd => self.enddict(),
+            OperatorObject::DictStack => self.dictstack(),
+            OperatorObject::End => self.end(),
+            OperatorObject::Known => self.known(),
+            OperatorObject::Load => self.load(),
+            OperatorObject::MaxLength => self.maxlength(),
+            OperatorObject::Store => self.store(),
+            OperatorObject::Undef => self.undef(),
+            OperatorObject::Where => self.wheredef(),
+            OperatorObject::Eq => self.eq(),
+            OperatorObject::Ne => self.ne(),
+            OperatorObject::Null => self.push(Object::Null(Mode::Literal)),
+            OperatorObject::Type => self.gettype(),
+            OperatorObject::CountExecStack => self.countexecstack(),
+            OperatorObject::Exec => {
+                let obj = self.pop()?;
+                self.push_frame(Frame::Object(obj))
+            },
+            OperatorObject::ExecStack => self.execstack(),
+            OperatorObject::Exit => self.exit(),
+            OperatorObject::For => self.for_loop(),
+            OperatorObject::If => self.if_proc(),
+            OperatorObject::IfElse => self.ifelse(),
+            OperatorObject::Loop => self.loop_proc(),
+            OperatorObject::Quit => self.quit(),
+            OperatorObject::Repeat => self.repeat(),
+            OperatorObject::Stop => self.stop(),
+            OperatorObject::Stopped => self.stopped(),
+            OperatorObject::CurrentFile => self.currentfile(),
+            OperatorObject::File => self.file(),
+            OperatorObject::Flush => self.flush(),
+            OperatorObject::PStack => self.pstack(),
+            OperatorObject::Print => self.print(),
+            OperatorObject::PrintSyntax => self.print_syntax(),
+            OperatorObject::PrintValue => self.print_value(),
+            OperatorObject::Read => self.read(),
+            OperatorObject::ReadLine => self.readline(),
+            OperatorObject::ReadString => self.readstring(),
+            OperatorObject::Run => self.run_file(),
+            OperatorObject::Stack => self.stack(),
+            OperatorObject::DefineFont => self.definefont(),
+            OperatorObject::DefineResource => self.defineresource(),
+            OperatorObject::FindFont => self.findfont(),
+            OperatorObject::FindResource => self.findresource(),
+            OperatorObject::ResourceStatus => self.resourcestatus(),
+            OperatorObject::UndefineResource => self.undefineresource(),
+            OperatorObject::CurrentGlobal => self.currentglobal(),
+            OperatorObject::DefineUserObject => self.defineuserobject(),
+            OperatorObject::ExecUserObject => self.execuserobject(),
+            OperatorObject::GCheck => self.gcheck(),
+            OperatorObject::Restore => self.restore(),
+            OperatorObject::Save => self.save(),
+            OperatorObject::SetGlobal => self.setglobal(),
+            OperatorObject::UndefineUserObject => self.undefineuserobject(),
+            OperatorObject::CurrentDevParams => self.currentdevparams(),
+            OperatorObject::CurrentSystemParams => self.currentsystemparams(),
+            OperatorObject::CurrentUserParams => self.currentuserparams(),
+            OperatorObject::SetDevParams => self.setdevparams(),
+            OperatorObject::SetSystemParams => self.setsystemparams(),
+            OperatorObject::SetUserParams => self.setuserparams(),
+            OperatorObject::CurrentPageDevice => self.currentpagedevice(),
+            OperatorObject::SetPageDevice => self.setpagedevice(),
+            OperatorObject::ShowPage => self.showpage(),
+            OperatorObject::CheckPassword => self.checkpassword(),
+            OperatorObject::DefaultTimeouts => self.defaulttimeouts(),
+            OperatorObject::DuplexMode => self.duplexmode(),
+            OperatorObject::JobTimeout => self.jobtimeout(),
+            OperatorObject::PageCount => self.pagecount(),
+            OperatorObject::PageParams => self.pageparams(),
+            OperatorObject::PaperTray => self.papertray(),
+            OperatorObject::PrinterName => self.printername(),
+            OperatorObject::SetDuplexMode => self.setduplexmode(),
+            OperatorObject::SetJobTimeout => self.setjobtimeout(),
+            OperatorObject::SetPageParams => self.setpageparams(),
+            OperatorObject::SetPaperTray => self.setpapertray(),
+            OperatorObject::SetTumble => self.settumble(),
+            OperatorObject::Tumble => self.tumble(),
+            OperatorObject::Condition => self.condition(),
+            OperatorObject::CurrentContext => self.currentcontext(),
+            OperatorObject::Detach => self.detach(),
+            OperatorObject::DeviceInfo => self.deviceinfo(),
+            OperatorObject::Fork => self.fork(),
+            OperatorObject::Join => self.join(),
+            OperatorObject::Lock => self.lock(),
+            OperatorObject::Monitor => self.monitor(),
+            OperatorObject::Notify => self.notify(),
+            OperatorObject::Wait => self.wait(),
+            OperatorObject::Yield => self.yield_context(),
+            OperatorObject::ExitServer => self.exitserver(),
+            OperatorObject::StartJob => self.startjob(),
+            OperatorObject::Bind => self.bind(),
+            OperatorObject::LanguageLevel => self.languagelevel(),
+            OperatorObject::Product => self.product(),
+            OperatorObject::RealTime => self.realtime(),
+            OperatorObject::Revision => self.revision(),
+            OperatorObject::SerialNumber => self.serialnumber(),
+            OperatorObject::UserTime => self.usertime(),
+            OperatorObject::Version => self.version(),
+            OperatorObject::Error(kind) => self.error(kind),
+            OperatorObject::HandleError => self.handleerror(),
+            OperatorObject::Assert => self.assert(),
+            OperatorObject::AssertDeepEq => self.assertdeepeq(),
+            OperatorObject::AssertEq => self.asserteq(),
+            OperatorObject::AssertError => self.asserterror(),
+            OperatorObject::AssertNe => self.assertne(),
+            OperatorObject::RecoverFromError(kind) => self.recover_from_error(kind),
+        }
+    }
+
+    fn initiate_error(&mut self, command: Object, error: Error) {
+        // Give back the operands consumed by the failed command
+        while let Some(obj) = self.transaction_stack.pop() {
+            self.operand_stack.push(obj);
+        }
+
+        // The stack that overflowed is reset, and its old contents are
+        // handed to the error handler as an array
+        let overflowed = match error.kind() {
+            ErrorKind::StackOverflow => Some(mem::take(&mut self.operand_stack)),
+            ErrorKind::DictStackOverflow => {
+                let dicts = self.dict_stack.split_off(self.permanent_dicts());
+                self.forget_names();
+
+                Some(
+                    self.dict_stack
+                        .iter()
+                        .chain(&dicts)
+                        .map(|dict| Object::Dictionary(*dict))
+                        .collect(),
+                )
+            },
+            ErrorKind::ExecStackOverflow => {
+                let objs = self.snapshot_execution_stack();
+                // Keep the job's own `stopped` frame, so the job still ends
+                // through `end_exec`
+                let base = self
+                    .execution_stack
+                    .iter()
+                    .position(|frame| matches!(frame, Frame::Stopped))
+                    .map_or(0, |index| index + 1);
+                self.unwind_to(base);
+
+                Some(objs)
+            },
+            _ => None,
+        };
+        if let Some(objs) = overflowed {
+            let arr = self
+                .heap
+                .alloc(ArrayObject::new(objs, Access::Unlimited, Mode::Literal));
+            self.operand_stack.push(Object::Array(arr));
+        }
+
+        let handler = self
+            .heap
+            .get(self.error_dict)
+            .get(&literal_name(error.kind().into()))
+            .copied()
+            .unwrap_or(Object::Operator((
+                OperatorObject::Error(error.kind()),
+                Mode::Executable,
+            )));
+
+        self.operand_stack.push(command);
+        self.execution_stack.push(Frame::Object(handler));
     }
 
     fn find(&self, key: &Object) -> crate::Result<Object> {
+        let key = key.into_key(&self.heap);
+
+        if let Object::Name(name) = key {
+            if let Some(obj) = self.name_cache.borrow().get(&name) {
+                return Ok(*obj);
+            }
+        }
+
         for dict in self.dict_stack.iter().rev() {
-            if let Some(obj) = dict.borrow().get(key).cloned() {
+            if let Some(obj) = self.heap.get(*dict).get(&key).copied() {
+                if let Object::Name(name) = key {
+                    self.name_cache.borrow_mut().insert(name, obj);
+                }
+
                 return Ok(obj);
             }
         }
 
         Err(Error::new(
             ErrorKind::Undefined,
-            format!("`{key}` not found"),
+            format!("`{}` not found", self.heap.format(&key)),
         ))
     }
 
+    /// Drops the cached lookup of `key`. Must be called whenever a dictionary
+    /// that could be on the dictionary stack gains or loses a key.
+    fn forget_name(&self, key: &Object) {
+        if let Object::Name(name) = key.into_key(&self.heap) {
+            self.name_cache.borrow_mut().remove(&name);
+        }
+    }
+
+    /// Drops every cached lookup. Must be called whenever the dictionary stack
+    /// itself changes.
+    fn forget_names(&self) {
+        self.name_cache.borrow_mut().clear();
+    }
+
+    fn find_dict(&self, key: &Object) -> Option<Handle<DictionaryObject>> {
+        let key = key.into_key(&self.heap);
+
+        self.dict_stack
+            .iter()
+            .rev()
+            .find(|dict| self.heap.get(**dict).contains_key(&key))
+            .copied()
+    }
+
     fn pop(&mut self) -> crate::Result<Object> {
         let obj = self
             .operand_stack
             .pop()
             .ok_or(Error::from(ErrorKind::StackUnderflow))?;
-        self.transaction_stack.push(obj.clone());
+        self.transaction_stack.push(obj);
 
         Ok(obj)
     }
 
-    fn pop_string(&mut self) -> crate::Result<Rc<RefCell<StringObject>>> {
+    fn pop_array(&mut self) -> crate::Result<Handle<ArrayObject>> {
+        match self.pop()? {
+            Object::Array(a) => Ok(a),
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected array")),
+        }
+    }
+
+    fn pop_bool(&mut self) -> crate::Result<bool> {
+        self.pop()?.into_bool()
+    }
+
+    fn pop_dict(&mut self) -> crate::Result<Handle<DictionaryObject>> {
+        match self.pop()? {
+            Object::Dictionary(d) => Ok(d),
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected dictionary")),
+        }
+    }
+
+    fn pop_file(&mut self) -> crate::Result<Handle<FileObject>> {
+        match self.pop()? {
+            Object::File(file) => Ok(file),
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected file")),
+        }
+    }
+
+    fn pop_int(&mut self) -> crate::Result<i32> {
+        self.pop()?.into_int()
+    }
+
+    fn pop_real(&mut self) -> crate::Result<f32> {
+        self.pop()?.into_real()
+    }
+
+    fn pop_string(&mut self) -> crate::Result<Handle<StringObject>> {
         match self.pop()? {
             Object::String(s) => Ok(s),
             _ => Err(Error::new(ErrorKind::TypeCheck, "expected string")),
         }
     }
+
+    fn pop_usize(&mut self) -> crate::Result<usize> {
+        self.pop()?.into_usize()
+    }
+
+    fn push(&mut self, obj: Object) -> crate::Result<()> {
+        if self.operand_stack.len() >= self.limits.max_op_stack {
+            return Err(Error::from(ErrorKind::StackOverflow));
+        }
+
+        self.operand_stack.push(obj);
+
+        Ok(())
+    }
+
+    fn push_frame(&mut self, frame: Frame) -> crate::Result<()> {
+        if self.execution_stack.len() >= self.limits.max_exec_stack {
+            return Err(Error::from(ErrorKind::ExecStackOverflow));
+        }
+
+        self.execution_stack.push(frame);
+
+        Ok(())
+    }
 }
 
 impl Interpreter {
+    /// Executes the object on top of the operand stack as a job. Errors that
+    /// stop the job are reported through `handleerror`, and `quit` ends it
+    /// early, which `has_quit` reports.
     pub fn exec(&mut self) -> crate::Result<()> {
-        match self.pop() {
-            Ok(obj) => match obj.mode() {
-                Mode::Literal => {
-                    self.operand_stack.push(obj);
-                    self.clear_transaction();
-                },
-                Mode::Executable => match self.execute_object(obj.clone()) {
-                    Ok(_) => self.clear_transaction(),
-                    Err(e) => self.initiate_error(obj, e),
-                },
-            },
-            Err(e) => {
-                self.initiate_error(
-                    Object::Operator((OperatorObject::Exec, Mode::Executable)),
-                    e,
-                );
-            },
-        };
+        let base = self.begin_exec();
+        self.run(base);
+
+        self.end_exec(base)
+    }
+
+    /// Runs each file as a separate job, the way a printer's job server
+    /// would. A job's changes to VM are undone when it ends, unless it
+    /// leaves encapsulation with `startjob` or `exitserver`.
+    pub fn exec_jobs<I>(&mut self, jobs: I) -> crate::Result<()>
+    where
+        I: IntoIterator<Item = FileObject>,
+    {
+        self.job_server = true;
+
+        for job in jobs {
+            self.begin_job(true);
+            self.push_file(job);
+            let result = self.exec();
+            self.end_job();
+
+            if let Err(e) = result {
+                self.job_server = false;
+                return Err(e);
+            }
+        }
+
+        self.job_server = false;
 
         Ok(())
     }
 
+    /// Appends `data` to the job being streamed in, starting a new job if
+    /// none is in progress, and executes every token that is now complete.
+    /// A token cut off at the end of `data` waits for the next call.
+    pub fn feed(&mut self, data: &[u8]) -> crate::Result<()> {
+        let job = self.streamed_job();
+        let _ = self.heap.get_mut(job.file).write(data);
+
+        self.resume_streamed_job()
+    }
+
+    /// Marks the end of the streamed job's input, executing whatever is
+    /// left of it and finishing the job.
+    pub fn end_input(&mut self) -> crate::Result<()> {
+        let job = self.streamed_job();
+        self.heap.get_mut(job.file).set_streaming(false);
+
+        let result = self.resume_streamed_job();
+        self.streamed_job = None;
+
+        result
+    }
+
     pub fn flush(&mut self) -> crate::Result<()> {
         self.stdout.flush().or(Err(Error::from(ErrorKind::IoError)))
     }
@@ -195,9 +1418,424 @@ impl Interpreter {
 
         let _ = self
             .stdout
-            .write(s.borrow().value())
+            .write(self.heap.get(s).value())
             .or(Err(Error::from(ErrorKind::IoError)))?;
 
         Ok(())
     }
+
+    /// Starts executing the object on top of the operand stack, returning
+    /// the execution stack depth the job runs above.
+    fn begin_exec(&mut self) -> usize {
+        let base = self.execution_stack.len();
+        if base == 0 {
+            self.deadline = self.job_timeout.map(|timeout| Instant::now() + timeout);
+            self.last_error = None;
+            self.quit = false;
+        }
+
+        self.execution_stack.push(Frame::Stopped);
+        self.execution_stack.push(Frame::Object(Object::Operator((
+            OperatorObject::Exec,
+            Mode::Executable,
+        ))));
+
+        base
+    }
+
+    /// Reports the error that stopped the job, if any, once it has run.
+    fn end_exec(&mut self, base: usize) -> crate::Result<()> {
+        // A job that quit never reaches its `stopped` frame
+        if !self.quit && matches!(self.operand_stack.pop(), Some(Object::Boolean(true))) {
+            self.last_error = self.stopped_error();
+
+            let handler = self
+                .heap
+                .get(self.error_dict)
+                .get(&literal_name("handleerror"))
+                .copied()
+                .unwrap_or(Object::Operator((
+                    OperatorObject::HandleError,
+                    Mode::Executable,
+                )));
+
+            self.execution_stack.push(Frame::Object(handler));
+            self.run(base);
+        }
+
+        if base == 0 {
+            self.deadline = None;
+        }
+
+        Ok(())
+    }
+
+    /// The error recorded in `$error`, if the job stopped because of one.
+    fn stopped_error(&self) -> Option<Error> {
+        let error_info = self.heap.get(self.error_info);
+        if !matches!(
+            error_info.get(&literal_name("newerror")),
+            Some(Object::Boolean(true))
+        ) {
+            return None;
+        }
+
+        let kind = match error_info.get(&literal_name("errorname")) {
+            Some(Object::Name(name)) => ErrorKind::ALL
+                .into_iter()
+                .find(|kind| *name == *<&str>::from(*kind)),
+            _ => None,
+        };
+        let command = error_info
+            .get(&literal_name("command"))
+            .copied()
+            .unwrap_or(Object::Null(Mode::Literal));
+
+        Some(Error::new(
+            kind.unwrap_or(ErrorKind::Unregistered),
+            format!("offending command {:?}", self.heap.format(&command)),
+        ))
+    }
+
+    fn streamed_job(&mut self) -> StreamedJob {
+        if let Some(job) = self.streamed_job {
+            return job;
+        }
+
+        let mut file = FileObject::new(Vec::new(), Mode::Executable);
+        file.set_streaming(true);
+        let file = self.heap.alloc(file);
+
+        self.operand_stack.push(Object::File(file));
+        let job = StreamedJob {
+            base: self.begin_exec(),
+            file,
+            is_running: true,
+        };
+        self.streamed_job = Some(job);
+
+        job
+    }
+
+    fn resume_streamed_job(&mut self) -> crate::Result<()> {
+        let Some(job) = self.streamed_job.as_mut() else {
+            return Ok(());
+        };
+
+        // Once a job has stopped, the rest of its input is discarded
+        if !job.is_running {
+            return Ok(());
+        }
+
+        let base = job.base;
+        self.awaiting_input = false;
+        self.run(base);
+        if self.awaiting_input {
+            self.awaiting_input = false;
+            return Ok(());
+        }
+
+        if let Some(job) = self.streamed_job.as_mut() {
+            job.is_running = false;
+        }
+
+        self.end_exec(base)
+    }
+}
+
+/// A job whose input is delivered in pieces through `Interpreter::feed`.
+#[derive(Clone, Copy)]
+struct StreamedJob {
+    base: usize,
+    file: Handle<FileObject>,
+    is_running: bool,
+}
+
+/// Milliseconds since the Unix epoch.
+fn realtime() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default()
+}
+
+fn literal_name(name: &str) -> Object {
+    Object::Name(NameObject::new(name, Mode::Literal))
+}
+
+fn operator(operator: OperatorObject) -> (Object, Object) {
+    (
+        literal_name(&operator.to_string()),
+        Object::Operator((operator, Mode::Executable)),
+    )
+}
+
+fn system_dict(
+    heap: &mut Heap,
+    error_dict: Handle<DictionaryObject>,
+    error_info: Handle<DictionaryObject>,
+    font_directory: Handle<DictionaryObject>,
+    status_dict: Handle<DictionaryObject>,
+    user_dict: Handle<DictionaryObject>,
+) -> DictionaryObject {
+    let definitions = [
+        ("true", Object::Boolean(true)),
+        ("false", Object::Boolean(false)),
+        ("errordict", Object::Dictionary(error_dict)),
+        ("$error", Object::Dictionary(error_info)),
+        ("FontDirectory", Object::Dictionary(font_directory)),
+        ("serverdict", Object::Dictionary(heap.alloc(server_dict()))),
+        ("statusdict", Object::Dictionary(status_dict)),
+        ("userdict", Object::Dictionary(user_dict)),
+    ];
+
+    let capacity = definitions.len() + SYSTEM_OPERATORS.len() + PRELUDE_CAPACITY;
+
+    definitions
+        .into_iter()
+        .map(|(key, obj)| (literal_name(key), obj))
+        .chain(SYSTEM_OPERATORS.iter().copied().map(operator))
+        .fold(
+            DictionaryObject::new(capacity, Access::Unlimited),
+            |mut dict, (key, obj)| {
+                dict.insert(key, obj);
+                dict
+            },
+        )
+}
+
+fn error_dict() -> DictionaryObject {
+    ErrorKind::ALL
+        .into_iter()
+        .map(|kind| operator(OperatorObject::Error(kind)))
+        .chain([operator(OperatorObject::HandleError)])
+        .fold(DictionaryObject::default(), |mut dict, (key, obj)| {
+            dict.insert(key, obj);
+            dict
+        })
+}
+
+fn error_info_dict() -> DictionaryObject {
+    let definitions = [
+        ("newerror", Object::Boolean(false)),
+        ("errorname", Object::Null(Mode::Literal)),
+        ("command", Object::Null(Mode::Literal)),
+        ("errorinfo", Object::Null(Mode::Literal)),
+        ("ostack", Object::Null(Mode::Literal)),
+        ("estack", Object::Null(Mode::Literal)),
+        ("dstack", Object::Null(Mode::Literal)),
+        ("recordstacks", Object::Boolean(true)),
+        ("binary", Object::Boolean(false)),
+    ];
+
+    definitions
+        .into_iter()
+        .fold(DictionaryObject::default(), |mut dict, (key, obj)| {
+            dict.insert(literal_name(key), obj);
+            dict
+        })
+}
+
+fn resource_dict(heap: &mut Heap, font_directory: Handle<DictionaryObject>) -> DictionaryObject {
+    RESOURCE_CATEGORIES
+        .iter()
+        .map(|(category, _)| {
+            let instances = heap.alloc(DictionaryObject::default());
+            (*category, Object::Dictionary(instances))
+        })
+        .chain([("Font", Object::Dictionary(font_directory))])
+        .fold(DictionaryObject::default(), |mut dict, (key, obj)| {
+            dict.insert(literal_name(key), obj);
+            dict
+        })
+}
+
+fn debug_dict() -> Vec<(Object, Object)> {
+    [
+        OperatorObject::Assert,
+        OperatorObject::AssertDeepEq,
+        OperatorObject::AssertEq,
+        OperatorObject::AssertError,
+        OperatorObject::AssertNe,
+    ]
+    .into_iter()
+    .map(operator)
+    .collect()
+}
+
+fn user_params(heap: &mut Heap) -> DictionaryObject {
+    let definitions = [
+        ("AccurateScreens", Object::Boolean(false)),
+        (
+            "JobName",
+            Object::String(heap.alloc(StringObject::from(""))),
+        ),
+        ("MaxFontItem", Object::Integer(12500)),
+        ("MaxFormItem", Object::Integer(100000)),
+        ("MaxPatternItem", Object::Integer(20000)),
+        ("MaxScreenItem", Object::Integer(48000)),
+        ("MaxUPathItem", Object::Integer(0)),
+        ("MinFontCompress", Object::Integer(100)),
+        ("VMReclaim", Object::Integer(0)),
+        ("VMThreshold", Object::Integer(40000)),
+    ];
+
+    definitions
+        .into_iter()
+        .fold(DictionaryObject::default(), |mut dict, (key, obj)| {
+            dict.insert(literal_name(key), obj);
+            dict
+        })
+}
+
+fn system_params(heap: &mut Heap) -> DictionaryObject {
+    let definitions = [
+        ("BuildTime", Object::Integer(0)),
+        ("ByteOrder", Object::Boolean(cfg!(target_endian = "little"))),
+        ("CurFontCache", Object::Integer(0)),
+        ("CurOutlineCache", Object::Integer(0)),
+        ("MaxFontCache", Object::Integer(400000)),
+        ("MaxOutlineCache", Object::Integer(65000)),
+        (
+            "RealFormat",
+            Object::String(heap.alloc(StringObject::from("IEEE"))),
+        ),
+    ];
+
+    definitions
+        .into_iter()
+        .fold(DictionaryObject::default(), |mut dict, (key, obj)| {
+            dict.insert(literal_name(key), obj);
+            dict
+        })
+}
+
+fn device_params(heap: &mut Heap) -> DictionaryObject {
+    let os = [
+        ("HasNames", Object::Boolean(true)),
+        ("Mounted", Object::Boolean(true)),
+        ("Removable", Object::Boolean(false)),
+        ("Searchable", Object::Boolean(true)),
+        ("SearchOrder", Object::Integer(0)),
+        ("Type", literal_name("FileSystem")),
+        ("Writeable", Object::Boolean(true)),
+    ];
+    let stdout = [
+        ("HasNames", Object::Boolean(false)),
+        ("Type", literal_name("Communications")),
+    ];
+
+    let device = |params: &[(&str, Object)]| {
+        params
+            .iter()
+            .fold(DictionaryObject::default(), |mut dict, (key, obj)| {
+                dict.insert(literal_name(key), *obj);
+                dict
+            })
+    };
+
+    let mut devices = DictionaryObject::default();
+    devices.insert(
+        literal_name("%os%"),
+        Object::Dictionary(heap.alloc(device(&os))),
+    );
+    devices.insert(
+        literal_name("%stdout%"),
+        Object::Dictionary(heap.alloc(device(&stdout))),
+    );
+
+    devices
+}
+
+fn server_dict() -> DictionaryObject {
+    [operator(OperatorObject::ExitServer)].into_iter().fold(
+        DictionaryObject::default(),
+        |mut dict, (key, obj)| {
+            dict.insert(key, obj);
+            dict
+        },
+    )
+}
+
+fn status_dict() -> DictionaryObject {
+    let definitions = [
+        ("checkpassword", OperatorObject::CheckPassword),
+        ("defaulttimeouts", OperatorObject::DefaultTimeouts),
+        ("duplexmode", OperatorObject::DuplexMode),
+        ("jobtimeout", OperatorObject::JobTimeout),
+        ("pagecount", OperatorObject::PageCount),
+        ("pageparams", OperatorObject::PageParams),
+        ("papertray", OperatorObject::PaperTray),
+        ("printername", OperatorObject::PrinterName),
+        ("setduplexmode", OperatorObject::SetDuplexMode),
+        ("setjobtimeout", OperatorObject::SetJobTimeout),
+        ("setpageparams", OperatorObject::SetPageParams),
+        ("setpapertray", OperatorObject::SetPaperTray),
+        ("settumble", OperatorObject::SetTumble),
+        ("tumble", OperatorObject::Tumble),
+    ];
+
+    let mut dict = DictionaryObject::new(200, Access::Unlimited);
+    for (key, op) in definitions {
+        dict.insert(literal_name(key), Object::Operator((op, Mode::Executable)));
+    }
+
+    dict.insert(literal_name("manualfeed"), Object::Boolean(false));
+    dict.insert(literal_name("manualfeedtimeout"), Object::Integer(60));
+    dict.insert(literal_name("waittimeout"), Object::Integer(40));
+
+    dict
+}
+
+fn page_device(heap: &mut Heap) -> Handle<DictionaryObject> {
+    let page_size = ArrayObject::new(
+        vec![Object::Integer(612), Object::Integer(792)],
+        Access::Unlimited,
+        Mode::Literal,
+    );
+
+    let definitions = [
+        ("Duplex", Object::Boolean(false)),
+        ("ManualFeed", Object::Boolean(false)),
+        ("MediaPosition", Object::Null(Mode::Literal)),
+        ("PageSize", Object::Array(heap.alloc(page_size))),
+        ("Tumble", Object::Boolean(false)),
+    ];
+
+    let page_device =
+        definitions
+            .into_iter()
+            .fold(DictionaryObject::default(), |mut dict, (key, obj)| {
+                dict.insert(literal_name(key), obj);
+                dict
+            });
+
+    heap.alloc(page_device)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streamed_job_drops_consumed_input() -> crate::Result<()> {
+        let mut interpreter = Interpreter::new(std::io::sink());
+        interpreter.feed(b"/total 0 def ")?;
+        let file = interpreter.streamed_job().file;
+
+        // Tokens are split across chunks, so some input is always pending
+        for _ in 0..10_000 {
+            interpreter.feed(b"/total total 1 ad")?;
+            interpreter.feed(b"d def { 1 } po")?;
+            interpreter.feed(b"p ")?;
+
+            let file = interpreter.heap.get(file);
+            assert!(file.cursor() + file.remaining().len() < 64);
+        }
+        interpreter.end_input()?;
+
+        assert_eq!(10_000, interpreter.load_value::<i32>("total")?);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,20 @@
+use crate::{MAX_DICT_STACK, MAX_EXECUTION_STACK, MAX_OPERAND_STACK};
+
+/// Stack depths an interpreter allows. Hosts set the ceilings, and jobs may
+/// lower or raise their own limits up to them with `setuserparams`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Limits {
+    pub max_dict_stack: usize,
+    pub max_exec_stack: usize,
+    pub max_op_stack: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_dict_stack: MAX_DICT_STACK,
+            max_exec_stack: MAX_EXECUTION_STACK,
+            max_op_stack: MAX_OPERAND_STACK,
+        }
+    }
+}
@@ -10,9 +10,11 @@ fn main() -> io::Result<()> {
     let mut interpreter = Interpreter::new(output);
 
     loop {
-        interpreter.push_string(">>> ".into());
-        let _ = interpreter.print(); // TODO: handle this
-        let _ = interpreter.flush();
+        // The prompt comes from the prelude's `prompt` procedure
+        interpreter.push_string("prompt".into());
+        if let Err(e) = interpreter.exec() {
+            panic!("{}", e.to_string());
+        }
 
         let mut buf = String::new();
         input.read_line(&mut buf)?;
@@ -22,5 +24,9 @@ fn main() -> io::Result<()> {
         if let Err(e) = interpreter.exec() {
             panic!("{}", e.to_string());
         }
+
+        if interpreter.has_quit() {
+            return Ok(());
+        }
     }
 }
@@ -1,33 +1,78 @@
-use std::{fmt, hash, str};
+use std::{
+    collections::HashMap,
+    fmt, hash, str,
+    sync::{OnceLock, PoisonError, RwLock},
+};
 
 use crate::object::Mode;
 
-#[derive(Clone, Debug)]
+/// Names are interned, so a name is only an index into the table of every
+/// name seen so far. Names are never freed, the same as in the VM.
+#[derive(Clone, Copy, Debug)]
 pub struct NameObject {
-    inner: Vec<u8>,
+    id: u32,
     mode: Mode,
 }
 
+#[derive(Default)]
+struct NameTable {
+    ids: HashMap<&'static [u8], u32>,
+    names: Vec<&'static [u8]>,
+}
+
+fn names() -> &'static RwLock<NameTable> {
+    static NAMES: OnceLock<RwLock<NameTable>> = OnceLock::new();
+
+    NAMES.get_or_init(RwLock::default)
+}
+
 impl NameObject {
     pub fn new(value: &str, mode: Mode) -> Self {
-        Self {
-            inner: value.bytes().collect(),
-            mode,
+        Self::from_bytes(value.as_bytes(), mode)
+    }
+
+    pub fn from_bytes(value: &[u8], mode: Mode) -> Self {
+        if let Some(id) = names()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .ids
+            .get(value)
+        {
+            return Self { id: *id, mode };
         }
+
+        let mut table = names().write().unwrap_or_else(PoisonError::into_inner);
+        let id = match table.ids.get(value) {
+            Some(id) => *id,
+            None => {
+                let value: &'static [u8] = Box::leak(value.into());
+                let id = u32::try_from(table.names.len()).expect("too many names");
+                table.names.push(value);
+                table.ids.insert(value, id);
+
+                id
+            },
+        };
+
+        Self { id, mode }
     }
 
     pub fn mode(&self) -> Mode {
         self.mode
     }
 
-    pub fn value(&self) -> &[u8] {
-        &self.inner
+    pub(crate) fn with_mode(self, mode: Mode) -> Self {
+        Self { id: self.id, mode }
+    }
+
+    pub fn value(&self) -> &'static [u8] {
+        names().read().unwrap_or_else(PoisonError::into_inner).names[self.id as usize]
     }
 }
 
 impl fmt::Display for NameObject {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = unsafe { str::from_utf8_unchecked(&self.inner) };
+        let s = unsafe { str::from_utf8_unchecked(self.value()) };
 
         s.fmt(f)
     }
@@ -41,18 +86,20 @@ impl<'a> From<&'a str> for NameObject {
 
 impl hash::Hash for NameObject {
     fn hash<H: hash::Hasher>(&self, state: &mut H) {
-        self.inner.hash(state);
+        self.id.hash(state);
     }
 }
 
+impl Eq for NameObject {}
+
 impl PartialEq for NameObject {
     fn eq(&self, other: &Self) -> bool {
-        self.inner == other.inner
+        self.id == other.id
     }
 }
 
 impl PartialEq<str> for NameObject {
     fn eq(&self, other: &str) -> bool {
-        self.inner == other.as_bytes()
+        self.value() == other.as_bytes()
     }
 }
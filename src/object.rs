@@ -1,11 +1,26 @@
-use std::{cell::RefCell, fmt, hash, rc::Rc};
+use std::{
+    fmt::{self, Write},
+    hash, str,
+};
 
 use crate::{
-    array::ArrayObject, dictionary::DictionaryObject, file::FileObject, name::NameObject,
-    operator::OperatorObject, string::StringObject, Error, ErrorKind,
+    array::ArrayObject,
+    context::{Condition, Lock},
+    dictionary::DictionaryObject,
+    file::FileObject,
+    heap::{Handle, Heap},
+    name::NameObject,
+    operator::OperatorObject,
+    save::Save,
+    string::StringObject,
+    Error, ErrorKind,
 };
 
-#[derive(Clone)]
+const ELISION: &str = "...";
+
+/// Composite objects are handles into the interpreter's heap, so every object
+/// is cheap to copy.
+#[derive(Clone, Copy, Debug)]
 pub enum Object {
     // Simple
     Boolean(bool),
@@ -18,16 +33,44 @@ pub enum Object {
     Real(f32),
 
     // Composite
-    Array(Rc<RefCell<ArrayObject>>),
-    Dictionary(Rc<RefCell<DictionaryObject>>),
-    File(Rc<RefCell<FileObject>>),
-    GState(Rc<RefCell<GState>>),
-    PackedArray(Rc<RefCell<PackedArray>>),
-    Save(Rc<RefCell<Save>>),
-    String(Rc<RefCell<StringObject>>),
+    Array(Handle<ArrayObject>),
+    Condition(Handle<Condition>),
+    Dictionary(Handle<DictionaryObject>),
+    File(Handle<FileObject>),
+    GState(Handle<GState>),
+    Lock(Handle<Lock>),
+    PackedArray(Handle<PackedArray>),
+    Save(Handle<Save>),
+    String(Handle<StringObject>),
 }
 
 impl Object {
+    /// Compares composite objects by value rather than identity. Objects that loop back into
+    /// themselves compare equal when both sides loop back at the same point.
+    pub(crate) fn deep_eq(&self, other: &Object, heap: &Heap) -> bool {
+        deep_eq(self, other, heap, &mut Vec::new())
+    }
+
+    /// Compares objects the way `eq` does, which looks inside strings.
+    pub(crate) fn equals(&self, other: &Object, heap: &Heap) -> bool {
+        match (self, other) {
+            (Object::String(lhs), Object::String(rhs)) => {
+                heap.get(*lhs).value() == heap.get(*rhs).value()
+            },
+            (Object::Name(lhs), Object::String(rhs)) | (Object::String(rhs), Object::Name(lhs)) => {
+                lhs.value() == heap.get(*rhs).value()
+            },
+            (lhs, rhs) => lhs == rhs,
+        }
+    }
+
+    pub fn into_bool(self) -> crate::Result<bool> {
+        match self {
+            Object::Boolean(b) => Ok(b),
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected boolean")),
+        }
+    }
+
     pub fn into_int(self) -> crate::Result<i32> {
         match self {
             Object::Integer(i) => Ok(i),
@@ -35,6 +78,18 @@ impl Object {
         }
     }
 
+    /// Strings used as dictionary keys are converted to names, so that
+    /// `(key)` and `/key` look up the same entry.
+    pub(crate) fn into_key(self, heap: &Heap) -> Object {
+        match self {
+            Object::String(string) => Object::Name(NameObject::from_bytes(
+                heap.get(string).value(),
+                Mode::Literal,
+            )),
+            obj => obj,
+        }
+    }
+
     pub fn into_name(self) -> crate::Result<NameObject> {
         match self {
             Object::Name(n) => Ok(n),
@@ -44,45 +99,219 @@ impl Object {
 
     pub fn into_real(self) -> crate::Result<f32> {
         match self {
+            Object::Integer(i) => Ok(i as f32),
             Object::Real(r) => Ok(r),
             _ => Err(Error::new(ErrorKind::TypeCheck, "expected real")),
         }
     }
 
-    pub fn mode(&self) -> Mode {
+    pub fn into_usize(self) -> crate::Result<usize> {
+        let i = self.into_int()?;
+
+        usize::try_from(i).or(Err(Error::new(
+            ErrorKind::RangeCheck,
+            "expected non-negative int",
+        )))
+    }
+
+    pub fn is_int(&self) -> bool {
+        matches!(self, Object::Integer(_))
+    }
+
+    pub fn is_mark(&self) -> bool {
+        matches!(self, Object::Mark)
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, Object::Integer(_) | Object::Real(_))
+    }
+
+    pub(crate) fn mode(&self, heap: &Heap) -> Mode {
         match self {
             Object::Name(name) => name.mode(),
             Object::Null(mode) => *mode,
             Object::Operator((_, mode)) => *mode,
-            Object::Array(array) => array.borrow().mode(),
-            Object::File(file) => file.borrow().mode(),
-            Object::String(string) => string.borrow().mode(),
+            Object::Array(array) => heap.get(*array).mode(),
+            Object::File(file) => heap.get(*file).mode(),
+            Object::String(string) => heap.get(*string).mode(),
             _ => Mode::Literal,
         }
     }
 }
 
-impl fmt::Debug for Object {
+fn deep_eq(lhs: &Object, rhs: &Object, heap: &Heap, seen: &mut Vec<(u32, u32)>) -> bool {
+    let pair = match (lhs, rhs) {
+        (Object::Array(lhs), Object::Array(rhs)) => (lhs.index(), rhs.index()),
+        (Object::Dictionary(lhs), Object::Dictionary(rhs)) => (lhs.index(), rhs.index()),
+        (lhs, rhs) => return lhs.equals(rhs, heap),
+    };
+
+    if pair.0 == pair.1 || seen.contains(&pair) {
+        return true;
+    }
+
+    seen.push(pair);
+    let is_eq = match (lhs, rhs) {
+        (Object::Array(lhs), Object::Array(rhs)) => {
+            let lhs = heap.get(*lhs);
+            let rhs = heap.get(*rhs);
+
+            lhs.len() == rhs.len()
+                && lhs
+                    .iter()
+                    .zip(rhs.iter())
+                    .all(|(lhs, rhs)| deep_eq(lhs, rhs, heap, seen))
+        },
+        (Object::Dictionary(lhs), Object::Dictionary(rhs)) => {
+            let lhs = heap.get(*lhs);
+            let rhs = heap.get(*rhs);
+
+            lhs.len() == rhs.len()
+                && lhs.iter().all(|(key, lhs)| match rhs.get(key) {
+                    Some(rhs) => deep_eq(lhs, rhs, heap, seen),
+                    None => false,
+                })
+        },
+        _ => unreachable!(),
+    };
+    seen.pop();
+
+    is_eq
+}
+
+/// An object alongside the heap it lives in, for printing. `Debug` gives the
+/// syntax `==` prints and `Display` the text `=` prints.
+pub(crate) struct Formatted<'a> {
+    heap: &'a Heap,
+    obj: &'a Object,
+    /// The composite this object is printed as part of, if any. Following these back tells
+    /// whether printing has looped back into an object it is already inside of.
+    parent: Option<&'a Formatted<'a>>,
+}
+
+impl Heap {
+    pub(crate) fn format<'a>(&'a self, obj: &'a Object) -> Formatted<'a> {
+        Formatted {
+            heap: self,
+            obj,
+            parent: None,
+        }
+    }
+}
+
+impl Heap {
+    /// The mode and elements of an array or packed array, for code that
+    /// reads both alike.
+    pub(crate) fn elements(&self, obj: &Object) -> Option<(Mode, &[Object])> {
+        match obj {
+            Object::Array(arr) => {
+                let arr = self.get(*arr);
+                Some((arr.mode(), arr.value()))
+            },
+            Object::PackedArray(arr) => {
+                let arr = self.get(*arr);
+                Some((arr.mode(), arr.value()))
+            },
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Formatted<'a> {
+    fn child(&'a self, obj: &'a Object) -> Formatted<'a> {
+        Formatted {
+            heap: self.heap,
+            obj,
+            parent: Some(self),
+        }
+    }
+
+    fn is_nested(&self) -> bool {
+        let mut parent = self.parent;
+        while let Some(formatted) = parent {
+            if formatted.obj == self.obj {
+                return true;
+            }
+            parent = formatted.parent;
+        }
+
+        false
+    }
+}
+
+impl fmt::Debug for Formatted<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
+        let heap = self.heap;
+
+        match self.obj {
             Object::Boolean(b) => b.fmt(f),
+            Object::FontID => f.write_str("-fontID-"),
             Object::Integer(i) => i.fmt(f),
-            Object::Mark => "mark".fmt(f),
-            Object::Name(n) => n.fmt(f),
-            Object::Null(_) => "null".fmt(f),
-            Object::Operator((o, _)) => o.to_string().fmt(f),
+            Object::Mark => f.write_str("-mark-"),
+            Object::Name(n) if n.mode().is_literal() => write!(f, "/{n}"),
+            Object::Name(n) => write!(f, "{n}"),
+            Object::Null(_) => f.write_str("null"),
+            Object::Operator((o, _)) => write!(f, "--{o}--"),
             Object::Real(r) => r.fmt(f),
-            Object::Array(a) => a.fmt(f),
-            Object::Dictionary(d) => d.fmt(f),
-            Object::String(s) => s.fmt(f),
-            _ => "TODO".fmt(f),
+            Object::Array(_) | Object::PackedArray(_) => {
+                if self.is_nested() {
+                    return f.write_str(ELISION);
+                }
+
+                let (mode, objs) = heap.elements(self.obj).unwrap_or_default();
+                let (l_delim, r_delim) = match mode {
+                    Mode::Executable => ("{", "}"),
+                    Mode::Literal => ("[", "]"),
+                };
+
+                f.write_str(l_delim)?;
+                for (index, obj) in objs.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{:?}", self.child(obj))?;
+                }
+                f.write_str(r_delim)
+            },
+            Object::Dictionary(d) => {
+                if self.is_nested() {
+                    return f.write_str(ELISION);
+                }
+
+                f.write_str("<<")?;
+                for (key, value) in heap.get(*d).iter() {
+                    write!(f, " {:?} {:?}", self.child(key), self.child(value))?;
+                }
+                f.write_str(" >>")
+            },
+            Object::Condition(_) => f.write_str("-condition-"),
+            Object::File(_) => f.write_str("-file-"),
+            Object::GState(_) => f.write_str("-gstate-"),
+            Object::Lock(_) => f.write_str("-lock-"),
+            Object::Save(_) => f.write_str("-save-"),
+            Object::String(s) => {
+                f.write_str("(")?;
+                for ch in heap.get(*s).value() {
+                    match ch {
+                        b'(' | b')' | b'\\' => write!(f, "\\{}", *ch as char)?,
+                        b'\n' => f.write_str("\\n")?,
+                        b'\r' => f.write_str("\\r")?,
+                        b'\t' => f.write_str("\\t")?,
+                        0x20..=0x7e => f.write_char(*ch as char)?,
+                        _ => write!(f, "\\{ch:03o}")?,
+                    }
+                }
+                f.write_str(")")
+            },
         }
     }
 }
 
-impl fmt::Display for Object {
+impl fmt::Display for Formatted<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
+        let heap = self.heap;
+
+        match self.obj {
             Object::Boolean(b) => b.fmt(f),
             Object::Integer(i) => i.fmt(f),
             Object::Mark => "mark".fmt(f),
@@ -90,16 +319,47 @@ impl fmt::Display for Object {
             Object::Null(_) => "null".fmt(f),
             Object::Operator((o, _)) => o.to_string().fmt(f),
             Object::Real(r) => r.fmt(f),
-            Object::Array(a) => a.borrow().fmt(f),
-            Object::Dictionary(d) => d.borrow().fmt(f),
-            Object::String(s) => s.borrow().fmt(f),
-            _ => "TODO".fmt(f),
+            Object::Array(_) | Object::PackedArray(_) => {
+                if self.is_nested() {
+                    return ELISION.fmt(f);
+                }
+
+                let (mode, objs) = heap.elements(self.obj).unwrap_or_default();
+                let (l_delim, r_delim) = match mode {
+                    Mode::Executable => ("{ ", "}"),
+                    Mode::Literal => ("[ ", "]"),
+                };
+
+                l_delim.fmt(f)?;
+                for obj in objs {
+                    write!(f, "{} ", self.child(obj))?;
+                }
+                r_delim.fmt(f)
+            },
+            Object::Dictionary(d) => {
+                if self.is_nested() {
+                    return ELISION.fmt(f);
+                }
+
+                "<< ".fmt(f)?;
+                for (k, v) in heap.get(*d).iter() {
+                    write!(f, "{} {} ", self.child(k), self.child(v))?;
+                }
+                ">>".fmt(f)
+            },
+            Object::String(s) => match str::from_utf8(heap.get(*s).value()) {
+                Ok(s) => s.fmt(f),
+                Err(_) => "(� )".fmt(f),
+            },
+            _ => "--nostringval--".fmt(f),
         }
     }
 }
 
 impl Eq for Object {}
 
+// Composites, strings included, compare and hash by identity. Only `equals` looks inside
+// them.
 impl hash::Hash for Object {
     fn hash<H: hash::Hasher>(&self, state: &mut H) {
         match self {
@@ -107,21 +367,22 @@ impl hash::Hash for Object {
             Object::Integer(i) => i.hash(state),
             Object::Name(n) => n.hash(state),
             Object::Real(r) => {
-                let u = r.to_bits();
-                u.hash(state);
-            },
-            Object::Array(a) => {
-                for obj in a.borrow().iter() {
-                    obj.hash(state);
-                }
-            },
-            Object::Dictionary(d) => {
-                for (key, value) in d.borrow().iter() {
-                    key.hash(state);
-                    value.hash(state);
+                // Integral reals compare equal to integers, so they must hash like them too
+                if r.fract() == 0.0 && *r >= i32::MIN as f32 && *r <= i32::MAX as f32 {
+                    (*r as i32).hash(state);
+                } else {
+                    r.to_bits().hash(state);
                 }
             },
-            Object::String(s) => s.borrow().hash(state),
+            Object::Array(a) => a.hash(state),
+            Object::Condition(c) => c.hash(state),
+            Object::Dictionary(d) => d.hash(state),
+            Object::File(f) => f.hash(state),
+            Object::GState(g) => g.hash(state),
+            Object::Lock(l) => l.hash(state),
+            Object::PackedArray(p) => p.hash(state),
+            Object::Save(s) => s.hash(state),
+            Object::String(s) => s.hash(state),
             _ => {},
         };
     }
@@ -131,18 +392,24 @@ impl PartialEq for Object {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Object::Boolean(lhs), Object::Boolean(rhs)) => lhs == rhs,
+            (Object::FontID, Object::FontID) => true,
             (Object::Integer(lhs), Object::Integer(rhs)) => lhs == rhs,
             (Object::Integer(lhs), Object::Real(rhs)) => *lhs as f32 == *rhs,
             (Object::Name(lhs), Object::Name(rhs)) => lhs == rhs,
-            (Object::Name(lhs), Object::String(rhs)) => lhs.value() == rhs.borrow().value(),
             (Object::Operator(lhs), Object::Operator(rhs)) => lhs == rhs,
             (Object::Real(lhs), Object::Real(rhs)) => lhs == rhs,
             (Object::Real(lhs), Object::Integer(rhs)) => *lhs == *rhs as f32,
-            (Object::Array(lhs), Object::Array(rhs)) => Rc::ptr_eq(lhs, rhs),
-            (Object::Dictionary(lhs), Object::Dictionary(rhs)) => Rc::ptr_eq(lhs, rhs),
-            (Object::File(lhs), Object::File(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Object::Mark, Object::Mark) => true,
+            (Object::Null(_), Object::Null(_)) => true,
+            (Object::Array(lhs), Object::Array(rhs)) => lhs == rhs,
+            (Object::Condition(lhs), Object::Condition(rhs)) => lhs == rhs,
+            (Object::Dictionary(lhs), Object::Dictionary(rhs)) => lhs == rhs,
+            (Object::File(lhs), Object::File(rhs)) => lhs == rhs,
+            (Object::GState(lhs), Object::GState(rhs)) => lhs == rhs,
+            (Object::Lock(lhs), Object::Lock(rhs)) => lhs == rhs,
+            (Object::PackedArray(lhs), Object::PackedArray(rhs)) => lhs == rhs,
+            (Object::Save(lhs), Object::Save(rhs)) => lhs == rhs,
             (Object::String(lhs), Object::String(rhs)) => lhs == rhs,
-            (Object::String(lhs), Object::Name(rhs)) => lhs.borrow().value() == rhs.value(),
             _ => false,
         }
     }
@@ -192,14 +459,140 @@ impl Mode {
     }
 }
 
+impl From<&Object> for &'static str {
+    fn from(value: &Object) -> Self {
+        match value {
+            Object::Boolean(_) => "booleantype",
+            Object::FontID => "fonttype",
+            Object::Integer(_) => "integertype",
+            Object::Mark => "marktype",
+            Object::Name(_) => "nametype",
+            Object::Null(_) => "nulltype",
+            Object::Operator(_) => "operatortype",
+            Object::Real(_) => "realtype",
+            Object::Array(_) => "arraytype",
+            Object::Condition(_) => "conditiontype",
+            Object::Dictionary(_) => "dicttype",
+            Object::File(_) => "filetype",
+            Object::GState(_) => "gstatetype",
+            Object::Lock(_) => "locktype",
+            Object::PackedArray(_) => "packedarraytype",
+            Object::Save(_) => "savetype",
+            Object::String(_) => "stringtype",
+        }
+    }
+}
+
 #[derive(Debug)]
-struct GState {}
+pub struct GState {}
 
+/// An array whose elements cannot be changed, as made by `packedarray`.
 #[derive(Debug)]
-struct PackedArray {
-    inner: Vec<u8>,
+pub struct PackedArray {
+    inner: Vec<Object>,
     mode: Mode,
 }
 
-#[derive(Debug)]
-struct Save {}
+impl PackedArray {
+    #[allow(dead_code)]
+    pub(crate) fn new(inner: Vec<Object>, mode: Mode) -> Self {
+        Self { inner, mode }
+    }
+
+    pub(crate) fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub(crate) fn value(&self) -> &[Object] {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cyclic_array(heap: &mut Heap) -> Object {
+        let arr = heap.alloc(ArrayObject::new(
+            vec![Object::Integer(1), Object::Null(Mode::Literal)],
+            Access::Unlimited,
+            Mode::Literal,
+        ));
+        *heap.get_mut(arr).get_mut(1).unwrap() = Object::Array(arr);
+
+        Object::Array(arr)
+    }
+
+    #[test]
+    fn test_fmt_cyclic() {
+        let mut heap = Heap::default();
+
+        let arr = cyclic_array(&mut heap);
+        assert_eq!("[1 ...]", format!("{:?}", heap.format(&arr)));
+        assert_eq!("[ 1 ... ]", format!("{}", heap.format(&arr)));
+
+        let mut dict = DictionaryObject::new(1, Access::Unlimited);
+        dict.insert(Object::Name(NameObject::from("a")), arr);
+        let dict = Object::Dictionary(heap.alloc(dict));
+        assert_eq!("<< a [1 ...] >>", format!("{:?}", heap.format(&dict)));
+    }
+
+    #[test]
+    fn test_fmt_shared() {
+        let mut heap = Heap::default();
+
+        let inner = Object::Array(heap.alloc(ArrayObject::new(
+            vec![Object::Integer(1)],
+            Access::Unlimited,
+            Mode::Literal,
+        )));
+        let outer = Object::Array(heap.alloc(ArrayObject::new(
+            vec![inner, inner],
+            Access::Unlimited,
+            Mode::Executable,
+        )));
+
+        assert_eq!("{[1] [1]}", format!("{:?}", heap.format(&outer)));
+    }
+
+    #[test]
+    fn test_fmt_string() {
+        let mut heap = Heap::default();
+
+        let s = Object::String(heap.alloc(StringObject::from("a(b)\\\n\x01")));
+
+        assert_eq!("(a\\(b\\)\\\\\\n\\001)", format!("{:?}", heap.format(&s)));
+    }
+
+    #[test]
+    fn test_deep_eq_cyclic() {
+        let mut heap = Heap::default();
+
+        let lhs = cyclic_array(&mut heap);
+        let rhs = cyclic_array(&mut heap);
+
+        assert!(lhs.deep_eq(&rhs, &heap));
+        assert_ne!(lhs, rhs);
+
+        let other = Object::Array(heap.alloc(ArrayObject::new(
+            vec![Object::Integer(2), rhs],
+            Access::Unlimited,
+            Mode::Literal,
+        )));
+        assert!(!lhs.deep_eq(&other, &heap));
+    }
+
+    #[test]
+    fn test_equals_strings() {
+        let mut heap = Heap::default();
+
+        let lhs = Object::String(heap.alloc(StringObject::from("abc")));
+        let rhs = Object::String(heap.alloc(StringObject::from("abc")));
+        let name = Object::Name(NameObject::from("abc"));
+
+        assert_ne!(lhs, rhs);
+        assert!(lhs.equals(&rhs, &heap));
+        assert!(name.equals(&lhs, &heap));
+        assert_eq!(name, rhs.into_key(&heap));
+    }
+}
@@ -1,20 +1,414 @@
 use std::fmt;
 
-#[derive(Clone, Debug, PartialEq)]
+use crate::{ErrorKind, LanguageLevel};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum OperatorObject {
+    // Stack
+    Clear,
+    ClearToMark,
+    Copy,
+    Count,
+    CountToMark,
+    Dup,
+    Exch,
+    Index,
+    Mark,
+    Pop,
+    Roll,
+
+    // Math
+    Abs,
+    Add,
+    Atan,
+    Ceiling,
+    Cos,
+    Div,
+    Exp,
+    Floor,
+    Idiv,
+    Ln,
+    Log,
+    Mod,
+    Mul,
+    Neg,
+    Rand,
+    Round,
+    RRand,
+    Sin,
+    Sqrt,
+    SRand,
+    Sub,
+    Truncate,
+
+    // Array
+    Aload,
+    Array,
+    ArrayEnd,
+    ArrayStart,
+    Astore,
+    ForAll,
+    Get,
+    GetInterval,
+    Length,
+    Put,
+    PutInterval,
+
+    // Dictionary
+    Begin,
+    ClearDictStack,
+    CountDictStack,
+    CurrentDict,
+    Def,
+    Dict,
+    DictEnd,
+    DictStack,
+    DictStart,
+    End,
+    Known,
+    Load,
+    MaxLength,
+    Store,
+    Undef,
+    Where,
+
+    // Relational
+    Eq,
+    Ne,
+
+    // Type
+    Null,
+    Type,
+
+    // Control
+    CountExecStack,
     Exec,
+    ExecStack,
+    Exit,
+    For,
+    If,
+    IfElse,
+    Loop,
+    Quit,
+    Repeat,
+    Stop,
+    Stopped,
+
+    // File
+    CurrentFile,
+    File,
     Flush,
+    PStack,
     Print,
-    Quit,
+    PrintSyntax,
+    PrintValue,
+    Read,
+    ReadLine,
+    ReadString,
+    Run,
+    Stack,
+
+    // Resource
+    DefineFont,
+    DefineResource,
+    FindFont,
+    FindResource,
+    ResourceStatus,
+    UndefineResource,
+
+    // VM
+    CurrentGlobal,
+    DefineUserObject,
+    ExecUserObject,
+    GCheck,
+    Restore,
+    Save,
+    SetGlobal,
+    UndefineUserObject,
+
+    // Parameters
+    CurrentDevParams,
+    CurrentSystemParams,
+    CurrentUserParams,
+    SetDevParams,
+    SetSystemParams,
+    SetUserParams,
+
+    // Device
+    CurrentPageDevice,
+    SetPageDevice,
+    ShowPage,
+
+    // Status
+    CheckPassword,
+    DefaultTimeouts,
+    DuplexMode,
+    JobTimeout,
+    PageCount,
+    PageParams,
+    PaperTray,
+    PrinterName,
+    SetDuplexMode,
+    SetJobTimeout,
+    SetPageParams,
+    SetPaperTray,
+    SetTumble,
+    Tumble,
+
+    // Context
+    Condition,
+    CurrentContext,
+    Detach,
+    DeviceInfo,
+    Fork,
+    Join,
+    Lock,
+    Monitor,
+    Notify,
+    Wait,
+    Yield,
+
+    // Job
+    ExitServer,
+    StartJob,
+
+    // Misc
+    Bind,
+    LanguageLevel,
+    Product,
+    RealTime,
+    Revision,
+    SerialNumber,
+    UserTime,
+    Version,
+
+    // Error
+    Error(ErrorKind),
+    HandleError,
+
+    // Debug
+    Assert,
+    AssertDeepEq,
+    AssertEq,
+    AssertError,
+    AssertNe,
+    RecoverFromError(ErrorKind),
 }
 
-impl fmt::Display for OperatorObject {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl OperatorObject {
+    /// The first LanguageLevel that defines the operator.
+    /// Whether the operator belongs to the Display PostScript extension,
+    /// which no LanguageLevel includes.
+    pub fn is_display_postscript(&self) -> bool {
+        matches!(
+            self,
+            OperatorObject::Condition
+                | OperatorObject::CurrentContext
+                | OperatorObject::Detach
+                | OperatorObject::DeviceInfo
+                | OperatorObject::Fork
+                | OperatorObject::Join
+                | OperatorObject::Lock
+                | OperatorObject::Monitor
+                | OperatorObject::Notify
+                | OperatorObject::Wait
+                | OperatorObject::Yield
+        )
+    }
+
+    pub fn language_level(&self) -> LanguageLevel {
         match self {
-            OperatorObject::Exec => "exec".fmt(f),
-            OperatorObject::Flush => "flush".fmt(f),
-            OperatorObject::Print => "print".fmt(f),
-            OperatorObject::Quit => "quit".fmt(f),
+            OperatorObject::ClearDictStack
+            | OperatorObject::DictEnd
+            | OperatorObject::DictStart
+            | OperatorObject::Undef
+            | OperatorObject::DefineResource
+            | OperatorObject::FindResource
+            | OperatorObject::ResourceStatus
+            | OperatorObject::UndefineResource
+            | OperatorObject::CurrentGlobal
+            | OperatorObject::DefineUserObject
+            | OperatorObject::ExecUserObject
+            | OperatorObject::GCheck
+            | OperatorObject::SetGlobal
+            | OperatorObject::UndefineUserObject
+            | OperatorObject::CurrentDevParams
+            | OperatorObject::CurrentSystemParams
+            | OperatorObject::CurrentUserParams
+            | OperatorObject::SetDevParams
+            | OperatorObject::SetSystemParams
+            | OperatorObject::SetUserParams
+            | OperatorObject::CurrentPageDevice
+            | OperatorObject::SetPageDevice
+            | OperatorObject::StartJob
+            | OperatorObject::LanguageLevel
+            | OperatorObject::Product
+            | OperatorObject::RealTime
+            | OperatorObject::Revision
+            | OperatorObject::SerialNumber => LanguageLevel::Level2,
+            _ => LanguageLevel::Level1,
         }
     }
 }
+
+impl fmt::Display for OperatorObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OperatorObject::Clear => "clear",
+            OperatorObject::ClearToMark => "cleartomark",
+            OperatorObject::Copy => "copy",
+            OperatorObject::Count => "count",
+            OperatorObject::CountToMark => "counttomark",
+            OperatorObject::Dup => "dup",
+            OperatorObject::Exch => "exch",
+            OperatorObject::Index => "index",
+            OperatorObject::Mark => "mark",
+            OperatorObject::Pop => "pop",
+            OperatorObject::Roll => "roll",
+            OperatorObject::Abs => "abs",
+            OperatorObject::Add => "add",
+            OperatorObject::Atan => "atan",
+            OperatorObject::Ceiling => "ceiling",
+            OperatorObject::Cos => "cos",
+            OperatorObject::Div => "div",
+            OperatorObject::Exp => "exp",
+            OperatorObject::Floor => "floor",
+            OperatorObject::Idiv => "idiv",
+            OperatorObject::Ln => "ln",
+            OperatorObject::Log => "log",
+            OperatorObject::Mod => "mod",
+            OperatorObject::Mul => "mul",
+            OperatorObject::Neg => "neg",
+            OperatorObject::Rand => "rand",
+            OperatorObject::Round => "round",
+            OperatorObject::RRand => "rrand",
+            OperatorObject::Sin => "sin",
+            OperatorObject::Sqrt => "sqrt",
+            OperatorObject::SRand => "srand",
+            OperatorObject::Sub => "sub",
+            OperatorObject::Truncate => "truncate",
+            OperatorObject::Aload => "aload",
+            OperatorObject::Array => "array",
+            OperatorObject::ArrayEnd => "]",
+            OperatorObject::ArrayStart => "[",
+            OperatorObject::Astore => "astore",
+            OperatorObject::ForAll => "forall",
+            OperatorObject::Get => "get",
+            OperatorObject::GetInterval => "getinterval",
+            OperatorObject::Length => "length",
+            OperatorObject::Put => "put",
+            OperatorObject::PutInterval => "putinterval",
+            OperatorObject::Begin => "begin",
+            OperatorObject::ClearDictStack => "cleardictstack",
+            OperatorObject::CountDictStack => "countdictstack",
+            OperatorObject::CurrentDict => "currentdict",
+            OperatorObject::Def => "def",
+            OperatorObject::Dict => "dict",
+            OperatorObject::DictEnd => ">>",
+            OperatorObject::DictStack => "dictstack",
+            OperatorObject::DictStart => "<<",
+            OperatorObject::End => "end",
+            OperatorObject::Known => "known",
+            OperatorObject::Load => "load",
+            OperatorObject::MaxLength => "maxlength",
+            OperatorObject::Store => "store",
+            OperatorObject::Undef => "undef",
+            OperatorObject::Where => "where",
+            OperatorObject::Eq => "eq",
+            OperatorObject::Ne => "ne",
+            OperatorObject::Null => "null",
+            OperatorObject::Type => "type",
+            OperatorObject::CountExecStack => "countexecstack",
+            OperatorObject::Exec => "exec",
+            OperatorObject::ExecStack => "execstack",
+            OperatorObject::Exit => "exit",
+            OperatorObject::For => "for",
+            OperatorObject::If => "if",
+            OperatorObject::IfElse => "ifelse",
+            OperatorObject::Loop => "loop",
+            OperatorObject::Quit => "quit",
+            OperatorObject::Repeat => "repeat",
+            OperatorObject::Stop => "stop",
+            OperatorObject::Stopped => "stopped",
+            OperatorObject::CurrentFile => "currentfile",
+            OperatorObject::File => "file",
+            OperatorObject::Flush => "flush",
+            OperatorObject::PStack => "pstack",
+            OperatorObject::Print => "print",
+            OperatorObject::PrintSyntax => "==",
+            OperatorObject::PrintValue => "=",
+            OperatorObject::Read => "read",
+            OperatorObject::ReadLine => "readline",
+            OperatorObject::ReadString => "readstring",
+            OperatorObject::Run => "run",
+            OperatorObject::Stack => "stack",
+            OperatorObject::DefineFont => "definefont",
+            OperatorObject::DefineResource => "defineresource",
+            OperatorObject::FindFont => "findfont",
+            OperatorObject::FindResource => "findresource",
+            OperatorObject::ResourceStatus => "resourcestatus",
+            OperatorObject::UndefineResource => "undefineresource",
+            OperatorObject::CurrentGlobal => "currentglobal",
+            OperatorObject::DefineUserObject => "defineuserobject",
+            OperatorObject::ExecUserObject => "execuserobject",
+            OperatorObject::GCheck => "gcheck",
+            OperatorObject::Restore => "restore",
+            OperatorObject::Save => "save",
+            OperatorObject::SetGlobal => "setglobal",
+            OperatorObject::UndefineUserObject => "undefineuserobject",
+            OperatorObject::CurrentDevParams => "currentdevparams",
+            OperatorObject::CurrentSystemParams => "currentsystemparams",
+            OperatorObject::CurrentUserParams => "currentuserparams",
+            OperatorObject::SetDevParams => "setdevparams",
+            OperatorObject::SetSystemParams => "setsystemparams",
+            OperatorObject::SetUserParams => "setuserparams",
+            OperatorObject::CurrentPageDevice => "currentpagedevice",
+            OperatorObject::SetPageDevice => "setpagedevice",
+            OperatorObject::ShowPage => "showpage",
+            OperatorObject::CheckPassword => "checkpassword",
+            OperatorObject::DefaultTimeouts => "defaulttimeouts",
+            OperatorObject::DuplexMode => "duplexmode",
+            OperatorObject::JobTimeout => "jobtimeout",
+            OperatorObject::PageCount => "pagecount",
+            OperatorObject::PageParams => "pageparams",
+            OperatorObject::PaperTray => "papertray",
+            OperatorObject::PrinterName => "printername",
+            OperatorObject::SetDuplexMode => "setduplexmode",
+            OperatorObject::SetJobTimeout => "setjobtimeout",
+            OperatorObject::SetPageParams => "setpageparams",
+            OperatorObject::SetPaperTray => "setpapertray",
+            OperatorObject::SetTumble => "settumble",
+            OperatorObject::Tumble => "tumble",
+            OperatorObject::Condition => "condition",
+            OperatorObject::CurrentContext => "currentcontext",
+            OperatorObject::Detach => "detach",
+            OperatorObject::DeviceInfo => "deviceinfo",
+            OperatorObject::Fork => "fork",
+            OperatorObject::Join => "join",
+            OperatorObject::Lock => "lock",
+            OperatorObject::Monitor => "monitor",
+            OperatorObject::Notify => "notify",
+            OperatorObject::Wait => "wait",
+            OperatorObject::Yield => "yield",
+            OperatorObject::ExitServer => "exitserver",
+            OperatorObject::StartJob => "startjob",
+            OperatorObject::Bind => "bind",
+            OperatorObject::LanguageLevel => "languagelevel",
+            OperatorObject::Product => "product",
+            OperatorObject::RealTime => "realtime",
+            OperatorObject::Revision => "revision",
+            OperatorObject::SerialNumber => "serialnumber",
+            OperatorObject::UserTime => "usertime",
+            OperatorObject::Version => "version",
+            OperatorObject::Error(kind) | OperatorObject::RecoverFromError(kind) => (*kind).into(),
+            OperatorObject::HandleError => "handleerror",
+            OperatorObject::Assert => "assert",
+            OperatorObject::AssertDeepEq => "assertdeepeq",
+            OperatorObject::AssertEq => "asserteq",
+            OperatorObject::AssertError => "asserterror",
+            OperatorObject::AssertNe => "assertne",
+        };
+
+        name.fmt(f)
+    }
+}
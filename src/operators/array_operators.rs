@@ -1,310 +1,296 @@
 use crate::{
-    execute_object,
-    object::{Access, Mode, NameObject},
-    ArrayObject, Context, Error, ErrorKind, Object, StringObject,
+    dictionary::Cursor,
+    frame::{ForAllItems, Frame, LoopFrame},
+    object::{Access, Mode},
+    ArrayObject, Error, ErrorKind, Interpreter, Object, StringObject, MAX_ARRAY_LENGTH,
 };
 
 use super::usize_to_i32;
 
-pub fn array(ctx: &mut Context) -> crate::Result<()> {
-    let len = ctx.pop_usize()?;
-
-    let arr = ArrayObject::new(vec![Object::Null; len], Access::Unlimited, Mode::Literal);
-    let idx = ctx.mem_mut().insert(arr);
-
-    ctx.push(Object::Array(idx));
-
-    Ok(())
-}
-
-pub fn endarray(ctx: &mut Context) -> crate::Result<()> {
-    let mut arr = Vec::new();
-
-    loop {
-        match ctx.pop() {
-            Err(_) => return Err(Error::from(ErrorKind::UnmatchedMark)),
-            Ok(obj) => {
-                if matches!(obj, Object::Mark) {
-                    break;
-                }
-
-                arr.push(obj);
-            },
+impl Interpreter {
+    pub(crate) fn array(&mut self) -> crate::Result<()> {
+        let len = self.pop_usize()?;
+        if len > MAX_ARRAY_LENGTH {
+            return Err(Error::from(ErrorKind::LimitCheck));
         }
-    }
-
-    arr.reverse();
-    let arr = ArrayObject::new(arr, Access::Unlimited, Mode::Literal);
-    let idx = ctx.mem_mut().insert(arr);
-
-    ctx.push(Object::Array(idx));
-
-    Ok(())
-}
-
-pub fn length(ctx: &mut Context) -> crate::Result<()> {
-    let obj = ctx.pop()?;
-
-    let len = match obj {
-        Object::Array(idx) | Object::Dictionary(idx) => {
-            let arr = ctx.get_array(idx)?;
-
-            if !arr.access().is_readable() {
-                return Err(Error::from(ErrorKind::InvalidAccess));
-            }
-
-            Ok(arr.len())
-        },
-        _ => Err(Error::new(ErrorKind::TypeCheck, "expected array")),
-    }?;
-
-    let i = usize_to_i32(len)?;
-    ctx.push(Object::Integer(i));
-
-    Ok(())
-}
 
-pub fn get(ctx: &mut Context) -> crate::Result<()> {
-    let key = ctx.pop()?;
-    let obj = ctx.pop()?;
+        let arr = self.heap.alloc(ArrayObject::new(
+            vec![Object::Null(Mode::Literal); len],
+            Access::Unlimited,
+            Mode::Literal,
+        ));
 
-    let obj = match obj {
-        Object::Array(idx) => {
-            let arr = ctx.get_array(idx)?;
-
-            if arr.access().is_exec_only() {
-                return Err(Error::from(ErrorKind::InvalidAccess));
-            }
+        self.push(Object::Array(arr))
+    }
 
-            let index = key.into_usize()?;
-            let obj = arr.get(index)?;
+    pub(crate) fn endarray(&mut self) -> crate::Result<()> {
+        let Some(u) = self.operand_stack.iter().rposition(Object::is_mark) else {
+            return Err(Error::from(ErrorKind::UnmatchedMark));
+        };
 
-            Ok(obj.clone())
-        },
-        Object::Dictionary(idx) => {
-            let dict = ctx.get_dict(idx)?;
+        let objs = self.operand_stack.split_off(u + 1);
+        self.operand_stack.pop();
 
-            if !dict.access().is_readable() {
-                return Err(Error::from(ErrorKind::InvalidAccess));
-            }
+        let arr = self
+            .heap
+            .alloc(ArrayObject::new(objs, Access::Unlimited, Mode::Literal));
 
-            let key = ctx.stringify(&key)?;
-            let obj = dict.get(&key)?;
+        self.push(Object::Array(arr))
+    }
 
-            Ok(obj.clone())
-        },
-        _ => Err(Error::new(ErrorKind::TypeCheck, "expected array")),
-    }?;
+    pub(crate) fn length(&mut self) -> crate::Result<()> {
+        let len = match self.pop()? {
+            Object::Array(arr) => {
+                let arr = self.heap.get(arr);
+                if !arr.access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-    ctx.push(obj);
+                arr.len()
+            },
+            Object::Dictionary(dict) => {
+                let dict = self.heap.get(dict);
+                if !dict.access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-    Ok(())
-}
+                dict.len()
+            },
+            Object::String(string) => {
+                let string = self.heap.get(string);
+                if !string.access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-pub fn put(ctx: &mut Context) -> crate::Result<()> {
-    let value = ctx.pop()?;
-    let key = ctx.pop()?;
-    let obj = ctx.pop()?;
+                string.len()
+            },
+            Object::Name(name) => name.value().len(),
+            _ => return Err(Error::new(ErrorKind::TypeCheck, "expected array")),
+        };
 
-    match obj {
-        Object::Array(idx) => {
-            let arr = ctx.get_array_mut(idx)?;
+        let i = usize_to_i32(len)?;
 
-            if !arr.access().is_writeable() {
-                return Err(Error::from(ErrorKind::InvalidAccess));
-            }
+        self.push(Object::Integer(i))
+    }
 
-            let index = key.into_usize()?;
+    pub(crate) fn get(&mut self) -> crate::Result<()> {
+        let key = self.pop()?;
 
-            let obj = arr.get_mut(index)?;
-            *obj = value;
+        let obj = match self.pop()? {
+            Object::Array(arr) => {
+                let arr = self.heap.get(arr);
+                if !arr.access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-            Ok(())
-        },
-        Object::Dictionary(idx) => {
-            let key = ctx.stringify(&key)?;
-            let dict = ctx.get_dict_mut(idx)?;
+                *arr.get(key.into_usize()?)?
+            },
+            Object::Dictionary(dict) => {
+                let dict = self.heap.get(dict);
+                if !dict.access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-            if !dict.access().is_writeable() {
-                return Err(Error::from(ErrorKind::InvalidAccess));
-            }
+                let key = key.into_key(&self.heap);
+                match dict.get(&key) {
+                    Some(obj) => *obj,
+                    None => {
+                        return Err(Error::new(
+                            ErrorKind::Undefined,
+                            self.heap.format(&key).to_string(),
+                        ))
+                    },
+                }
+            },
+            Object::String(string) => {
+                let string = self.heap.get(string);
+                if !string.access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-            dict.insert(key, value);
+                Object::Integer(i32::from(string.get(key.into_usize()?)?))
+            },
+            _ => return Err(Error::new(ErrorKind::TypeCheck, "expected array")),
+        };
 
-            Ok(())
-        },
-        _ => Err(Error::new(ErrorKind::TypeCheck, "expected array")),
+        self.push(obj)
     }
-}
 
-pub fn getinterval(ctx: &mut Context) -> crate::Result<()> {
-    let count = ctx.pop_usize()?;
-    let index = ctx.pop_usize()?;
-    let obj = ctx.pop()?;
-
-    match obj {
-        Object::Array(idx) => {
-            let arr = ctx.get_array(idx)?;
-
-            if !arr.access().is_readable() {
-                return Err(Error::from(ErrorKind::InvalidAccess));
-            }
-
-            if index > arr.len() {
-                return Err(Error::from(ErrorKind::RangeCheck));
-            }
-
-            let mut subarr = Vec::with_capacity(count);
-            for i in index..(index + count) {
-                let obj = arr.get(i)?;
-                subarr.push(obj.clone());
-            }
-
-            let idx =
-                ctx.mem_mut()
-                    .insert(ArrayObject::new(subarr, Access::default(), Mode::default()));
-            ctx.push(Object::Array(idx));
-
-            Ok(())
-        },
-        Object::String(idx) => {
-            let string: &str = ctx.get_string(idx)?.into();
-
-            if string.len() <= index || string.len() <= (index + count) {
-                return Err(Error::from(ErrorKind::RangeCheck));
-            }
-
-            let mut substr = String::with_capacity(count);
-            for ch in string.chars().skip(index).take(count) {
-                substr.push(ch);
-            }
-
-            let idx = ctx.mem_mut().insert(StringObject::from(substr));
-            ctx.push(Object::String(idx));
-
-            Ok(())
-        },
-        _ => Err(Error::new(ErrorKind::TypeCheck, "expected array")),
-    }
-}
+    pub(crate) fn put(&mut self) -> crate::Result<()> {
+        let value = self.pop()?;
+        let key = self.pop()?;
 
-pub fn putinterval(ctx: &mut Context) -> crate::Result<()> {
-    let source = ctx.pop_array()?;
+        match self.pop()? {
+            Object::Array(arr) => {
+                if !self.heap.get(arr).access().is_writeable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-    if !source.access().is_readable() {
-        return Err(Error::from(ErrorKind::InvalidAccess));
-    }
+                self.journal_array(arr);
+                *self.heap.get_mut(arr).get_mut(key.into_usize()?)? = value;
 
-    let source = source.clone();
+                Ok(())
+            },
+            Object::Dictionary(dict) => self.insert(dict, key, value),
+            Object::String(string) => {
+                if !self.heap.get(string).access().is_writeable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-    let index = ctx.pop_usize()?;
-    let destination = ctx.pop_array_mut()?;
+                let ch =
+                    u8::try_from(value.into_int()?).or(Err(Error::from(ErrorKind::RangeCheck)))?;
 
-    if !destination.access().is_writeable() {
-        return Err(Error::from(ErrorKind::InvalidAccess));
-    }
+                self.journal_string(string);
+                *self.heap.get_mut(string).get_mut(key.into_usize()?)? = ch;
 
-    for (offset, obj) in source.into_iter().enumerate() {
-        let dest_obj = destination.get_mut(index + offset)?;
-        *dest_obj = obj;
+                Ok(())
+            },
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected array")),
+        }
     }
 
-    Ok(())
-}
-
-pub fn astore(ctx: &mut Context) -> crate::Result<()> {
-    let idx = ctx.pop()?.into_index()?;
-    let mut arr = ctx.get_array(idx)?.clone();
-
-    for i in (0..arr.len()).rev() {
-        let obj = arr.get_mut(i)?;
-        *obj = ctx.pop()?;
-    }
+    pub(crate) fn getinterval(&mut self) -> crate::Result<()> {
+        let count = self.pop_usize()?;
+        let index = self.pop_usize()?;
 
-    let target = ctx.get_array_mut(idx)?;
-    *target = arr;
+        match self.pop()? {
+            Object::Array(arr) => {
+                let arr = self.heap.get(arr);
+                if !arr.access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-    Ok(())
-}
+                let Some(subarr) = arr.value().get(index..index + count) else {
+                    return Err(Error::from(ErrorKind::RangeCheck));
+                };
 
-pub fn aload(ctx: &mut Context) -> crate::Result<()> {
-    let obj = ctx.pop()?;
+                let subarr = ArrayObject::new(subarr.to_vec(), Access::default(), arr.mode());
+                let subarr = self.heap.alloc(subarr);
 
-    let (idx, arr) = match obj {
-        Object::Array(idx) => {
-            let arr = ctx.get_array(idx)?;
+                self.push(Object::Array(subarr))
+            },
+            Object::String(string) => {
+                let string = self.heap.get(string);
+                if !string.access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-            if !arr.access().is_readable() {
-                return Err(Error::from(ErrorKind::InvalidAccess));
-            }
+                let Some(substr) = string.value().get(index..index + count) else {
+                    return Err(Error::from(ErrorKind::RangeCheck));
+                };
 
-            Ok((idx, arr.clone()))
-        },
-        _ => Err(Error::new(ErrorKind::TypeCheck, "expected array")),
-    }?;
+                let substr = self.heap.alloc(StringObject::from(substr.to_vec()));
 
-    for obj in arr {
-        ctx.push(obj);
+                self.push(Object::String(substr))
+            },
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected array")),
+        }
     }
 
-    ctx.push(Object::Array(idx));
-
-    Ok(())
-}
+    pub(crate) fn putinterval(&mut self) -> crate::Result<()> {
+        let source = self.pop()?;
+        let index = self.pop_usize()?;
 
-pub fn forall(ctx: &mut Context) -> crate::Result<()> {
-    let proc = ctx.pop()?;
-    let obj = ctx.pop()?;
+        match (self.pop()?, source) {
+            (Object::Array(destination), Object::Array(source)) => {
+                if !self.heap.get(source).access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-    match obj {
-        Object::Array(idx) => {
-            let arr = ctx.get_array(idx)?.clone();
+                let source = self.heap.get(source).value().to_vec();
+                if !self.heap.get(destination).access().is_writeable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
+                if index + source.len() > self.heap.get(destination).len() {
+                    return Err(Error::from(ErrorKind::RangeCheck));
+                }
 
-            for obj in arr.into_iter() {
-                ctx.push(obj);
-                execute_object(ctx, proc.clone());
-            }
+                self.journal_array(destination);
+                let destination = self.heap.get_mut(destination);
+                for (offset, obj) in source.into_iter().enumerate() {
+                    *destination.get_mut(index + offset)? = obj;
+                }
 
-            Ok(())
-        },
-        Object::Dictionary(idx) => {
-            let dict = ctx.get_dict(idx)?;
+                Ok(())
+            },
+            (Object::String(destination), Object::String(source)) => {
+                if !self.heap.get(source).access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-            for (key, value) in dict.clone() {
-                let name = NameObject::new(key, Mode::Literal);
-                ctx.push(Object::Name(name));
-                ctx.push(value);
+                let source = self.heap.get(source).value().to_vec();
+                if !self.heap.get(destination).access().is_writeable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
+                if index + source.len() > self.heap.get(destination).len() {
+                    return Err(Error::from(ErrorKind::RangeCheck));
+                }
 
-                execute_object(ctx, proc.clone());
-            }
+                self.journal_string(destination);
+                let destination = self.heap.get_mut(destination);
+                for (offset, ch) in source.into_iter().enumerate() {
+                    *destination.get_mut(index + offset)? = ch;
+                }
 
-            Ok(())
-        },
-        _ => Err(Error::new(ErrorKind::TypeCheck, "expected array")),
+                Ok(())
+            },
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected array")),
+        }
     }
-}
 
-pub fn packedarray(ctx: &mut Context) -> crate::Result<()> {
-    let len = ctx.pop_usize()?;
+    pub(crate) fn astore(&mut self) -> crate::Result<()> {
+        let arr = self.pop_array()?;
+        if !self.heap.get(arr).access().is_writeable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
 
-    let arr = ArrayObject::new(vec![Object::Null; len], Access::ReadOnly, Mode::Literal);
-    let idx = ctx.mem_mut().insert(arr);
+        let len = self.heap.get(arr).len();
+        if len > self.operand_stack.len() {
+            return Err(Error::from(ErrorKind::StackUnderflow));
+        }
 
-    ctx.push(Object::Array(idx));
+        self.journal_array(arr);
+        let objs = self.operand_stack.split_off(self.operand_stack.len() - len);
+        for (index, obj) in objs.into_iter().enumerate() {
+            *self.heap.get_mut(arr).get_mut(index)? = obj;
+        }
 
-    Ok(())
-}
+        self.push(Object::Array(arr))
+    }
 
-pub fn setpacking(ctx: &mut Context) -> crate::Result<()> {
-    let b = ctx.pop_bool()?;
+    pub(crate) fn aload(&mut self) -> crate::Result<()> {
+        let arr = self.pop_array()?;
+        if !self.heap.get(arr).access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
 
-    ctx.is_packing = b;
+        let objs = self.heap.get(arr).value().to_vec();
+        for obj in objs {
+            self.push(obj)?;
+        }
 
-    Ok(())
-}
+        self.push(Object::Array(arr))
+    }
 
-pub fn currentpacking(ctx: &mut Context) -> crate::Result<()> {
-    ctx.push(Object::Boolean(ctx.is_packing));
+    pub(crate) fn forall(&mut self) -> crate::Result<()> {
+        let proc = self.pop()?;
+
+        let (items, access) = match self.pop()? {
+            Object::Array(arr) => (ForAllItems::Array(arr, 0), self.heap.get(arr).access()),
+            Object::Dictionary(dict) => (
+                ForAllItems::Dictionary(dict, Cursor::default()),
+                self.heap.get(dict).access(),
+            ),
+            Object::String(string) => (
+                ForAllItems::String(string, 0),
+                self.heap.get(string).access(),
+            ),
+            _ => return Err(Error::new(ErrorKind::TypeCheck, "expected array")),
+        };
+        if !access.is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
 
-    Ok(())
+        self.push_frame(Frame::Loop(LoopFrame::ForAll { items, proc }))
+    }
 }
@@ -0,0 +1,325 @@
+use std::mem;
+
+use crate::{
+    context::{Condition, Context, ContextState, Lock},
+    dictionary::DictionaryObject,
+    frame::Frame,
+    heap::Handle,
+    literal_name,
+    object::Mode,
+    operator::OperatorObject,
+    Error, ErrorKind, Interpreter, Object,
+};
+
+impl Interpreter {
+    pub(crate) fn fork(&mut self) -> crate::Result<()> {
+        let proc = self.pop()?;
+
+        let Some(mark) = self.operand_stack.iter().rposition(Object::is_mark) else {
+            return Err(Error::from(ErrorKind::UnmatchedMark));
+        };
+
+        let id = self.next_context_id;
+        self.next_context_id = self
+            .next_context_id
+            .checked_add(1)
+            .ok_or(Error::from(ErrorKind::LimitCheck))?;
+
+        let mut context = Context::new(id);
+        context.operand_stack = self.operand_stack.split_off(mark + 1);
+        context.execution_stack = vec![Frame::Object(proc)];
+        context.dict_stack = self.dict_stack[..self.permanent_dicts()].to_vec();
+        self.operand_stack.pop();
+        self.contexts.push(context);
+
+        self.push(Object::Integer(id))
+    }
+
+    pub(crate) fn join(&mut self) -> crate::Result<()> {
+        let id = self.pop_int()?;
+
+        let context = self.joinable_context(id)?;
+        if !matches!(context.state, ContextState::Terminated) {
+            // Run join again once the context has terminated
+            self.block(ContextState::Joining(id), |interpreter| {
+                interpreter.operand_stack.push(Object::Integer(id));
+                interpreter
+                    .execution_stack
+                    .push(Frame::Object(operator(OperatorObject::Join)));
+            })?;
+
+            return Ok(());
+        }
+
+        let index = self.context_index(id)?;
+        let context = self.contexts.remove(index);
+
+        self.push(Object::Mark)?;
+        for obj in context.operand_stack {
+            self.push(obj)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn detach(&mut self) -> crate::Result<()> {
+        let id = self.pop_int()?;
+
+        let context = self.joinable_context(id)?;
+        if matches!(context.state, ContextState::Terminated) {
+            let index = self.context_index(id)?;
+            self.contexts.remove(index);
+        } else {
+            let index = self.context_index(id)?;
+            self.contexts[index].detached = true;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn lock(&mut self) -> crate::Result<()> {
+        let lock = self.heap.alloc(Lock::default());
+
+        self.push(Object::Lock(lock))
+    }
+
+    pub(crate) fn monitor(&mut self) -> crate::Result<()> {
+        let proc = self.pop()?;
+        let lock = self.pop_lock()?;
+
+        let owner = self.heap.get(lock).owner;
+        if owner == Some(self.current_context) {
+            return Err(Error::new(ErrorKind::InvalidContext, "lock already held"));
+        }
+
+        if self.execution_stack.len() + 2 > self.limits.max_exec_stack {
+            return Err(Error::from(ErrorKind::ExecStackOverflow));
+        }
+
+        let push_frames = |interpreter: &mut Interpreter| {
+            interpreter.execution_stack.push(Frame::Monitor(lock));
+            interpreter.execution_stack.push(Frame::Object(proc));
+        };
+
+        match owner {
+            None => {
+                self.heap.get_mut(lock).owner = Some(self.current_context);
+                push_frames(self);
+
+                Ok(())
+            },
+            Some(_) => self.block(ContextState::Locking(lock), push_frames),
+        }
+    }
+
+    pub(crate) fn condition(&mut self) -> crate::Result<()> {
+        let condition = self.heap.alloc(Condition);
+
+        self.push(Object::Condition(condition))
+    }
+
+    pub(crate) fn wait(&mut self) -> crate::Result<()> {
+        let condition = self.pop_condition()?;
+        let lock = self.pop_lock()?;
+
+        if self.heap.get(lock).owner != Some(self.current_context) {
+            return Err(Error::new(ErrorKind::InvalidContext, "lock not held"));
+        }
+
+        self.heap.get_mut(lock).owner = None;
+        let result = self.block(ContextState::Waiting(condition, lock), |_| {});
+        if result.is_err() {
+            self.heap.get_mut(lock).owner = Some(self.current_context);
+        }
+
+        result
+    }
+
+    pub(crate) fn notify(&mut self) -> crate::Result<()> {
+        let condition = self.pop_condition()?;
+
+        for context in &mut self.contexts {
+            if let ContextState::Waiting(waiting_on, lock) = context.state {
+                if waiting_on == condition {
+                    context.state = ContextState::Locking(lock);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn yield_context(&mut self) -> crate::Result<()> {
+        if let Some(id) = self.next_ready_context() {
+            self.resume_context(id);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn currentcontext(&mut self) -> crate::Result<()> {
+        self.push(Object::Integer(self.current_context))
+    }
+
+    pub(crate) fn deviceinfo(&mut self) -> crate::Result<()> {
+        let definitions = [
+            ("Colors", 1),
+            ("GrayValues", 256),
+            ("RedValues", 0),
+            ("GreenValues", 0),
+            ("BlueValues", 0),
+            ("ColorValues", 0),
+        ];
+
+        let info =
+            definitions
+                .into_iter()
+                .fold(DictionaryObject::default(), |mut dict, (key, value)| {
+                    dict.insert(literal_name(key), Object::Integer(value));
+                    dict
+                });
+
+        let info = self.heap.alloc(info);
+
+        self.push(Object::Dictionary(info))
+    }
+
+    /// Finds the next context that can run, round robin after the current
+    /// one.
+    pub(crate) fn next_ready_context(&self) -> Option<i32> {
+        let current = self
+            .contexts
+            .iter()
+            .position(|context| context.id == self.current_context)?;
+
+        self.contexts
+            .iter()
+            .cycle()
+            .skip(current + 1)
+            .take(self.contexts.len() - 1)
+            .find(|context| self.is_ready(context))
+            .map(|context| context.id)
+    }
+
+    /// Switches to another context, giving it the lock it was waiting for.
+    pub(crate) fn resume_context(&mut self, id: i32) {
+        let Ok(index) = self.context_index(id) else {
+            return;
+        };
+
+        let context = &mut self.contexts[index];
+        if let ContextState::Locking(lock) | ContextState::Waiting(_, lock) = context.state {
+            let lock = self.heap.get_mut(lock);
+            if lock.owner.is_none() {
+                lock.owner = Some(id);
+            }
+        }
+        context.state = ContextState::Runnable;
+
+        if let Ok(current) = self.context_index(self.current_context) {
+            self.swap_stacks(current);
+        }
+        self.swap_stacks(index);
+        self.current_context = id;
+    }
+
+    /// Called once the current context runs out of things to execute. If
+    /// nothing else can run, the host resumes with an `invalidcontext` error
+    /// rather than waiting forever.
+    pub(crate) fn terminate_context(&mut self, host: i32) {
+        let id = self.current_context;
+        if let Ok(index) = self.context_index(id) {
+            self.contexts[index].state = ContextState::Terminated;
+        }
+
+        let next = self.next_ready_context();
+        self.resume_context(next.unwrap_or(host));
+
+        if let Ok(index) = self.context_index(id) {
+            if self.contexts[index].detached {
+                self.contexts.remove(index);
+            }
+        }
+
+        if next.is_none() {
+            self.initiate_error(
+                Object::Null(Mode::Literal),
+                Error::new(ErrorKind::InvalidContext, "deadlock"),
+            );
+        }
+    }
+
+    // Suspends the current context until it is ready again. `suspend`
+    // prepares the context's stacks for when it resumes. A context that would
+    // never be woken raises an error instead.
+    fn block<F>(&mut self, state: ContextState, suspend: F) -> crate::Result<()>
+    where
+        F: FnOnce(&mut Interpreter),
+    {
+        let index = self.context_index(self.current_context)?;
+        let previous = mem::replace(&mut self.contexts[index].state, state);
+
+        let Some(next) = self.next_ready_context() else {
+            self.contexts[index].state = previous;
+            return Err(Error::new(ErrorKind::InvalidContext, "deadlock"));
+        };
+
+        suspend(self);
+        self.resume_context(next);
+
+        Ok(())
+    }
+
+    fn is_ready(&self, context: &Context) -> bool {
+        match &context.state {
+            ContextState::Runnable => true,
+            ContextState::Joining(id) => self
+                .contexts
+                .iter()
+                .find(|other| other.id == *id)
+                .is_none_or(|other| matches!(other.state, ContextState::Terminated)),
+            ContextState::Locking(lock) => self.heap.get(*lock).owner.is_none(),
+            ContextState::Waiting(..) | ContextState::Terminated => false,
+        }
+    }
+
+    fn joinable_context(&self, id: i32) -> crate::Result<&Context> {
+        match self.contexts.iter().find(|context| context.id == id) {
+            Some(context) if id != self.current_context && !context.detached => Ok(context),
+            _ => Err(Error::new(ErrorKind::InvalidContext, "invalid context")),
+        }
+    }
+
+    fn context_index(&self, id: i32) -> crate::Result<usize> {
+        self.contexts
+            .iter()
+            .position(|context| context.id == id)
+            .ok_or(Error::new(ErrorKind::InvalidContext, "invalid context"))
+    }
+
+    fn swap_stacks(&mut self, index: usize) {
+        let context = &mut self.contexts[index];
+        mem::swap(&mut self.dict_stack, &mut context.dict_stack);
+        mem::swap(&mut self.execution_stack, &mut context.execution_stack);
+        mem::swap(&mut self.operand_stack, &mut context.operand_stack);
+        self.forget_names();
+    }
+
+    fn pop_condition(&mut self) -> crate::Result<Handle<Condition>> {
+        match self.pop()? {
+            Object::Condition(condition) => Ok(condition),
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected condition")),
+        }
+    }
+
+    fn pop_lock(&mut self) -> crate::Result<Handle<Lock>> {
+        match self.pop()? {
+            Object::Lock(lock) => Ok(lock),
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected lock")),
+        }
+    }
+}
+
+fn operator(operator: OperatorObject) -> Object {
+    Object::Operator((operator, Mode::Executable))
+}
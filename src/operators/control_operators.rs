@@ -0,0 +1,164 @@
+use crate::{
+    frame::{Frame, LoopFrame},
+    object::{Access, Mode},
+    ArrayObject, Error, ErrorKind, Interpreter, Object,
+};
+
+use super::usize_to_i32;
+
+impl Interpreter {
+    pub(crate) fn if_proc(&mut self) -> crate::Result<()> {
+        let proc = self.pop()?;
+        let condition = self.pop_bool()?;
+
+        if condition {
+            self.push_frame(Frame::Object(proc))?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn ifelse(&mut self) -> crate::Result<()> {
+        let else_proc = self.pop()?;
+        let if_proc = self.pop()?;
+        let condition = self.pop_bool()?;
+
+        if condition {
+            self.push_frame(Frame::Object(if_proc))
+        } else {
+            self.push_frame(Frame::Object(else_proc))
+        }
+    }
+
+    pub(crate) fn for_loop(&mut self) -> crate::Result<()> {
+        let proc = self.pop()?;
+        let limit = self.pop()?;
+        let increment = self.pop()?;
+        let initial = self.pop()?;
+
+        if !initial.is_number() || !increment.is_number() || !limit.is_number() {
+            return Err(Error::new(ErrorKind::TypeCheck, "expected number"));
+        }
+
+        let is_int = initial.is_int() && increment.is_int();
+
+        self.push_frame(Frame::Loop(LoopFrame::For {
+            control: f64::from(initial.into_real()?),
+            increment: f64::from(increment.into_real()?),
+            limit: f64::from(limit.into_real()?),
+            is_int,
+            proc,
+        }))
+    }
+
+    pub(crate) fn repeat(&mut self) -> crate::Result<()> {
+        let proc = self.pop()?;
+        let count = self.pop_usize()?;
+
+        self.push_frame(Frame::Loop(LoopFrame::Repeat(count, proc)))
+    }
+
+    pub(crate) fn loop_proc(&mut self) -> crate::Result<()> {
+        let proc = self.pop()?;
+
+        self.push_frame(Frame::Loop(LoopFrame::Loop(proc)))
+    }
+
+    pub(crate) fn exit(&mut self) -> crate::Result<()> {
+        let boundary = self
+            .execution_stack
+            .iter()
+            .rposition(|frame| frame.is_loop() || matches!(frame, Frame::Stopped));
+
+        match boundary {
+            Some(index) if self.execution_stack[index].is_loop() => {
+                self.unwind_to(index);
+                Ok(())
+            },
+            _ => Err(Error::new(ErrorKind::InvalidExit, "no enclosing loop")),
+        }
+    }
+
+    pub(crate) fn stop(&mut self) -> crate::Result<()> {
+        let boundary = self
+            .execution_stack
+            .iter()
+            .rposition(|frame| matches!(frame, Frame::Stopped));
+
+        self.unwind_to(boundary.unwrap_or(0));
+        if boundary.is_some() {
+            self.operand_stack.push(Object::Boolean(true));
+        }
+
+        Ok(())
+    }
+
+    /// Ends the job rather than the process the interpreter is embedded in.
+    /// The run loop unwinds the rest of the job once the flag is set.
+    pub(crate) fn quit(&mut self) -> crate::Result<()> {
+        self.quit = true;
+
+        Ok(())
+    }
+
+    /// Pops every frame from `index` upwards, releasing the locks held by any
+    /// `monitor` among them.
+    pub(crate) fn unwind_to(&mut self, index: usize) {
+        for frame in self.execution_stack.split_off(index) {
+            if let Frame::Monitor(lock) = frame {
+                self.heap.get_mut(lock).owner = None;
+            }
+        }
+    }
+
+    pub(crate) fn stopped(&mut self) -> crate::Result<()> {
+        let obj = self.pop()?;
+
+        if self.execution_stack.len() + 2 > self.limits.max_exec_stack {
+            return Err(Error::from(ErrorKind::ExecStackOverflow));
+        }
+
+        self.push_frame(Frame::Stopped)?;
+        self.push_frame(Frame::Object(obj))
+    }
+
+    pub(crate) fn countexecstack(&mut self) -> crate::Result<()> {
+        let i = usize_to_i32(self.execution_stack.len())?;
+
+        self.push(Object::Integer(i))
+    }
+
+    pub(crate) fn execstack(&mut self) -> crate::Result<()> {
+        let arr = self.pop_array()?;
+        if !self.heap.get(arr).access().is_writeable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        let objs = self.snapshot_execution_stack();
+        if objs.len() > self.heap.get(arr).len() {
+            return Err(Error::from(ErrorKind::RangeCheck));
+        }
+
+        self.journal_array(arr);
+        for (index, obj) in objs.iter().enumerate() {
+            *self.heap.get_mut(arr).get_mut(index)? = *obj;
+        }
+
+        if objs.len() == self.heap.get(arr).len() {
+            return self.push(Object::Array(arr));
+        }
+
+        let subarr = self
+            .heap
+            .alloc(ArrayObject::new(objs, Access::Unlimited, Mode::Literal));
+
+        self.push(Object::Array(subarr))
+    }
+
+    pub(crate) fn snapshot_execution_stack(&mut self) -> Vec<Object> {
+        self.execution_stack
+            .iter()
+            .map(|frame| frame.to_object(&mut self.heap))
+            .collect()
+    }
+}
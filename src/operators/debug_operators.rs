@@ -1,81 +1,80 @@
-use crate::{Context, Object};
+use crate::{literal_name, Interpreter, Object};
 
-pub fn assert(ctx: &mut Context) -> crate::Result<()> {
-    assert!(ctx.pop_bool()?);
+impl Interpreter {
+    pub(crate) fn assert(&mut self) -> crate::Result<()> {
+        let obj = match self.pop()? {
+            // `type` results are executable names, so tests can write
+            // `/arraytype true def ... type assert`
+            Object::Name(name) if name.mode().is_executable() => self.find(&Object::Name(name))?,
+            obj => obj,
+        };
 
-    Ok(())
-}
+        assert!(obj.into_bool()?);
 
-pub fn asserteq(ctx: &mut Context) -> crate::Result<()> {
-    let rhs = ctx.pop()?;
-    let lhs = ctx.pop()?;
+        Ok(())
+    }
 
-    assert_eq!(lhs, rhs);
+    pub(crate) fn asserteq(&mut self) -> crate::Result<()> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
 
-    Ok(())
-}
+        assert!(
+            lhs.equals(&rhs, &self.heap),
+            "{:?} != {:?}",
+            self.heap.format(&lhs),
+            self.heap.format(&rhs)
+        );
 
-pub fn assertne(ctx: &mut Context) -> crate::Result<()> {
-    let rhs = ctx.pop()?;
-    let lhs = ctx.pop()?;
+        Ok(())
+    }
 
-    assert_ne!(lhs, rhs);
+    pub(crate) fn assertne(&mut self) -> crate::Result<()> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
 
-    Ok(())
-}
+        assert!(
+            !lhs.equals(&rhs, &self.heap),
+            "{:?} == {:?}",
+            self.heap.format(&lhs),
+            self.heap.format(&rhs)
+        );
 
-pub fn assertdeepeq(ctx: &mut Context) -> crate::Result<()> {
-    let rhs = ctx.pop()?;
-    let lhs = ctx.pop()?;
-
-    match (lhs, rhs) {
-        (Object::Array(lhs), Object::Array(rhs)) => {
-            let lhs = ctx.get_array(lhs)?.clone();
-            let rhs = ctx.get_array(rhs)?.clone();
-
-            for (lhs, rhs) in lhs.into_iter().zip(rhs.into_iter()) {
-                ctx.push(lhs);
-                ctx.push(rhs);
-                assertdeepeq(ctx)?;
-            }
-        },
-        (Object::Dictionary(lhs), Object::Dictionary(rhs)) => {
-            let lhs = ctx.get_dict(lhs)?.clone();
-            let rhs = ctx.get_dict(rhs)?.clone();
-
-            for (key, lhs_obj) in lhs.into_iter() {
-                let rhs_obj = rhs.get(&key)?.clone();
-
-                ctx.push(lhs_obj);
-                ctx.push(rhs_obj);
-                assertdeepeq(ctx)?;
-            }
-        },
-        (Object::String(lhs), Object::String(rhs)) => {
-            let lhs: &str = ctx.get_string(lhs)?.into();
-            let rhs: &str = ctx.get_string(rhs)?.into();
-
-            assert_eq!(lhs, rhs);
-        },
-        (lhs, rhs) => assert_eq!(lhs, rhs),
+        Ok(())
     }
 
-    Ok(())
-}
+    pub(crate) fn assertdeepeq(&mut self) -> crate::Result<()> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
 
-pub fn asserterror(ctx: &mut Context) -> crate::Result<()> {
-    let expected = ctx.pop()?;
+        assert!(
+            lhs.deep_eq(&rhs, &self.heap),
+            "{:?} != {:?}",
+            self.heap.format(&lhs),
+            self.heap.format(&rhs)
+        );
 
-    let idx = ctx.find_def("$error").cloned()?.into_index()?;
-    let error_info = ctx.get_dict(idx)?;
+        Ok(())
+    }
+
+    pub(crate) fn asserterror(&mut self) -> crate::Result<()> {
+        let expected = self.pop()?;
+
+        let error_info = self.heap.get(self.error_info);
 
-    let is_new = error_info
-        .get("newerror")
-        .is_ok_and(|b| matches!(b, Object::Boolean(true)));
-    assert!(is_new, "expected error");
+        let is_new = error_info
+            .get(&literal_name("newerror"))
+            .is_some_and(|b| matches!(b, Object::Boolean(true)));
+        assert!(is_new, "expected error");
 
-    let received = error_info.get("errorname").cloned()?;
-    assert_eq!(expected, received);
+        let received = error_info.get(&literal_name("errorname")).copied();
+        assert_eq!(Some(expected), received);
 
-    Ok(())
+        // The error was expected, so it no longer counts as new
+        self.heap
+            .get_mut(self.error_info)
+            .insert(literal_name("newerror"), Object::Boolean(false));
+        self.forget_name(&literal_name("newerror"));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,123 @@
+use crate::{
+    heap::Heap,
+    literal_name,
+    object::{Access, Mode},
+    ArrayObject, Error, ErrorKind, Interpreter, Object, Page,
+};
+
+impl Interpreter {
+    pub(crate) fn setpagedevice(&mut self) -> crate::Result<()> {
+        let request = self.pop_dict()?;
+        let request = self.heap.get(request).clone();
+        if !request.access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        let mut updates = Vec::new();
+        for (key, value) in request.iter() {
+            let value = match key {
+                Object::Name(name) if *name == *"PageSize" => page_size(value, &mut self.heap)?,
+                Object::Name(name)
+                    if *name == *"Duplex" || *name == *"ManualFeed" || *name == *"Tumble" =>
+                {
+                    Object::Boolean(value.into_bool()?)
+                },
+                Object::Name(name) if *name == *"MediaPosition" => match value {
+                    Object::Integer(_) | Object::Null(_) => *value,
+                    _ => return Err(Error::new(ErrorKind::TypeCheck, "expected integer")),
+                },
+                _ => *value,
+            };
+
+            updates.push((*key, value));
+        }
+
+        self.journal_dict(self.page_device);
+        let page_device = self.heap.get_mut(self.page_device);
+        for (key, value) in updates {
+            page_device.insert(key, value);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn currentpagedevice(&mut self) -> crate::Result<()> {
+        let mut page_device = self.heap.get(self.page_device).clone();
+
+        // Copy the page size so it cannot be changed in place
+        if let Some(size) = page_device.get(&literal_name("PageSize")) {
+            let size = page_size(size, &mut self.heap)?;
+            page_device.insert(literal_name("PageSize"), size);
+        }
+
+        let page_device = self.heap.alloc(page_device);
+
+        self.push(Object::Dictionary(page_device))
+    }
+
+    pub(crate) fn showpage(&mut self) -> crate::Result<()> {
+        let size = match self.page_param("PageSize") {
+            Some(Object::Array(arr)) => self.heap.get(arr).value().to_vec(),
+            _ => Vec::new(),
+        };
+        let [width, height] = match size.as_slice() {
+            [width, height] => [width.into_real()?, height.into_real()?],
+            _ => return Err(Error::from(ErrorKind::ConfigurationError)),
+        };
+
+        self.page_count += 1;
+        let page = Page {
+            height,
+            number: self.page_count,
+            width,
+        };
+        if let Some(handler) = self.page_handler.as_mut() {
+            handler(page);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn page_param(&self, key: &str) -> Option<Object> {
+        self.heap
+            .get(self.page_device)
+            .get(&literal_name(key))
+            .copied()
+    }
+
+    /// Changes a page device parameter, journaled so that `restore` undoes
+    /// it.
+    pub(crate) fn set_page_param(&mut self, key: &str, value: Object) {
+        self.journal_dict(self.page_device);
+        self.heap
+            .get_mut(self.page_device)
+            .insert(literal_name(key), value);
+    }
+}
+
+fn page_size(value: &Object, heap: &mut Heap) -> crate::Result<Object> {
+    let Object::Array(arr) = value else {
+        return Err(Error::new(ErrorKind::TypeCheck, "expected array"));
+    };
+
+    let arr = heap.get(*arr);
+    if arr.len() != 2 {
+        return Err(Error::from(ErrorKind::RangeCheck));
+    }
+
+    let mut size = Vec::with_capacity(2);
+    for dimension in arr.iter() {
+        if !dimension.is_number() {
+            return Err(Error::new(ErrorKind::TypeCheck, "expected number"));
+        }
+        if dimension.into_real()? <= 0.0 {
+            return Err(Error::from(ErrorKind::RangeCheck));
+        }
+
+        size.push(*dimension);
+    }
+
+    let size = heap.alloc(ArrayObject::new(size, Access::Unlimited, Mode::Literal));
+
+    Ok(Object::Array(size))
+}
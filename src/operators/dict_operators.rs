@@ -1,219 +1,269 @@
-use std::collections::HashMap;
-
-use crate::{object::DictionaryObject, Context, Error, ErrorKind, Object};
+use crate::{
+    dictionary::DictionaryObject,
+    heap::Handle,
+    object::{Access, Mode},
+    ArrayObject, Error, ErrorKind, Interpreter, LanguageLevel, Object, MAX_DICT_LENGTH,
+};
 
 use super::usize_to_i32;
 
-pub fn dict(ctx: &mut Context) -> crate::Result<()> {
-    let capacity = ctx.pop_usize()?;
-
-    let idx = ctx
-        .mem_mut()
-        .insert(DictionaryObject::with_capacity(capacity));
+impl Interpreter {
+    pub(crate) fn insert(
+        &mut self,
+        dict: Handle<DictionaryObject>,
+        key: Object,
+        value: Object,
+    ) -> crate::Result<()> {
+        if matches!(key, Object::Null(_)) {
+            return Err(Error::new(ErrorKind::TypeCheck, "null key"));
+        }
 
-    ctx.push(Object::Dictionary(idx));
+        let key = key.into_key(&self.heap);
+        {
+            let dict = self.heap.get(dict);
+            if !dict.access().is_writeable() {
+                return Err(Error::from(ErrorKind::InvalidAccess));
+            }
+
+            // Dictionaries only grow past their declared capacity from
+            // LanguageLevel 2 onwards
+            let capacity = if self.language_level < LanguageLevel::Level2 {
+                dict.capacity()
+            } else {
+                MAX_DICT_LENGTH
+            };
+            if !dict.contains_key(&key) && dict.len() >= capacity {
+                return Err(Error::from(ErrorKind::DictFull));
+            }
+        }
 
-    Ok(())
-}
+        self.journal_dict(dict);
+        self.forget_name(&key);
+        self.heap.get_mut(dict).insert(key, value);
 
-pub fn enddict(ctx: &mut Context) -> crate::Result<()> {
-    let mut dict = HashMap::new();
+        Ok(())
+    }
 
-    loop {
-        let value = ctx.pop()?;
-        if value.is_mark() {
-            break;
+    pub(crate) fn dict(&mut self) -> crate::Result<()> {
+        let capacity = self.pop_usize()?;
+        if capacity > MAX_DICT_LENGTH {
+            return Err(Error::from(ErrorKind::LimitCheck));
         }
 
-        let key = ctx.pop()?;
-        if key.is_mark() {
-            return Err(Error::from(ErrorKind::RangeCheck));
-        }
+        let dict = self
+            .heap
+            .alloc(DictionaryObject::new(capacity, Access::Unlimited));
 
-        let key = ctx.stringify(&key)?;
-
-        dict.insert(key, value);
+        self.push(Object::Dictionary(dict))
     }
 
-    let idx = ctx.mem_mut().insert(DictionaryObject::from(dict));
-    ctx.push(Object::Dictionary(idx));
+    pub(crate) fn enddict(&mut self) -> crate::Result<()> {
+        let Some(u) = self.operand_stack.iter().rposition(Object::is_mark) else {
+            return Err(Error::from(ErrorKind::UnmatchedMark));
+        };
 
-    Ok(())
-}
+        if !(self.operand_stack.len() - u - 1).is_multiple_of(2) {
+            return Err(Error::from(ErrorKind::RangeCheck));
+        }
 
-pub fn maxlength(ctx: &mut Context) -> crate::Result<()> {
-    let dict = ctx.pop_dict()?;
+        let objs = self.operand_stack.split_off(u + 1);
+        self.operand_stack.pop();
 
-    if !dict.access().is_readable() {
-        return Err(Error::from(ErrorKind::InvalidAccess));
-    }
+        let dict = self
+            .heap
+            .alloc(DictionaryObject::new(objs.len() / 2, Access::Unlimited));
 
-    let capacity = dict.len() + 1;
+        let mut objs = objs.into_iter();
+        while let (Some(key), Some(value)) = (objs.next(), objs.next()) {
+            self.insert(dict, key, value)?;
+        }
 
-    let capacity = usize_to_i32(capacity)?;
+        self.push(Object::Dictionary(dict))
+    }
 
-    ctx.push(Object::Integer(capacity));
+    pub(crate) fn maxlength(&mut self) -> crate::Result<()> {
+        let dict = self.pop_dict()?;
 
-    Ok(())
-}
+        if !self.heap.get(dict).access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
 
-pub fn begin(ctx: &mut Context) -> crate::Result<()> {
-    let obj = ctx.pop()?;
+        let capacity = usize_to_i32(self.heap.get(dict).capacity())?;
 
-    match obj {
-        Object::Dictionary(idx) => {
-            ctx.dict_stack.push(idx);
-            Ok(())
-        },
-        _ => Err(Error::new(ErrorKind::TypeCheck, "expected dictionary")),
+        self.push(Object::Integer(capacity))
     }
-}
 
-pub fn end(ctx: &mut Context) -> crate::Result<()> {
-    if ctx.dict_stack.len() <= 3 {
-        return Err(Error::from(ErrorKind::DictStackUnderflow));
-    }
+    pub(crate) fn begin(&mut self) -> crate::Result<()> {
+        let dict = self.pop_dict()?;
 
-    let _ = ctx.dict_stack.pop();
+        if self.dict_stack.len() >= self.limits.max_dict_stack {
+            return Err(Error::from(ErrorKind::DictStackOverflow));
+        }
 
-    Ok(())
-}
+        self.dict_stack.push(dict);
+        self.forget_names();
 
-pub fn def(ctx: &mut Context) -> crate::Result<()> {
-    let value = ctx.pop()?;
-    let key_obj = ctx.pop()?;
-    let key = ctx.stringify(&key_obj)?;
+        Ok(())
+    }
 
-    let dict_idx = ctx
-        .dict_stack
-        .last()
-        .ok_or(Error::from(ErrorKind::LimitCheck))?;
+    pub(crate) fn end(&mut self) -> crate::Result<()> {
+        if self.dict_stack.len() <= self.permanent_dicts() {
+            return Err(Error::from(ErrorKind::DictStackUnderflow));
+        }
 
-    let dict = ctx.get_dict_mut(*dict_idx)?;
+        let _ = self.dict_stack.pop();
+        self.forget_names();
 
-    if !dict.access().is_writeable() {
-        return Err(Error::from(ErrorKind::InvalidAccess));
+        Ok(())
     }
 
-    dict.insert(key, value);
+    pub(crate) fn def(&mut self) -> crate::Result<()> {
+        let value = self.pop()?;
+        let key = self.pop()?;
 
-    Ok(())
-}
+        let dict = self
+            .dict_stack
+            .last()
+            .copied()
+            .ok_or(Error::from(ErrorKind::DictStackUnderflow))?;
 
-pub fn load(ctx: &mut Context) -> crate::Result<()> {
-    let key_obj = ctx.pop()?;
-    let key = ctx.stringify(&key_obj)?;
+        self.insert(dict, key, value)
+    }
 
-    let obj = ctx.find_def(key)?;
+    pub(crate) fn load(&mut self) -> crate::Result<()> {
+        let key = self.pop()?;
 
-    ctx.push(obj.clone());
+        let obj = self.find(&key)?;
 
-    Ok(())
-}
+        self.push(obj)
+    }
 
-pub fn store(ctx: &mut Context) -> crate::Result<()> {
-    let value = ctx.pop()?;
-    let key_obj = ctx.pop()?;
-    let key = ctx.stringify(&key_obj)?;
+    pub(crate) fn store(&mut self) -> crate::Result<()> {
+        let value = self.pop()?;
+        let key = self.pop()?;
 
-    let dict = ctx.find_dict_mut(&key)?;
+        let dict = match self.find_dict(&key) {
+            Some(dict) => dict,
+            None => self
+                .dict_stack
+                .last()
+                .copied()
+                .ok_or(Error::from(ErrorKind::DictStackUnderflow))?,
+        };
 
-    dict.insert(key, value);
+        self.insert(dict, key, value)
+    }
 
-    Ok(())
-}
+    pub(crate) fn undef(&mut self) -> crate::Result<()> {
+        let key = self.pop()?;
+        let dict = self.pop_dict()?;
 
-pub fn undef(ctx: &mut Context) -> crate::Result<()> {
-    let key_obj = ctx.pop()?;
-    let key = ctx.stringify(&key_obj)?;
-    let dict = ctx.pop_dict_mut()?;
+        if !self.heap.get(dict).access().is_writeable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
 
-    if !dict.access().is_writeable() {
-        return Err(Error::from(ErrorKind::InvalidAccess));
-    }
+        let key = key.into_key(&self.heap);
+        self.journal_dict(dict);
+        self.forget_name(&key);
+        self.heap.get_mut(dict).remove(&key);
 
-    match dict.remove(&key) {
-        Some(..) => Ok(()),
-        None => Err(Error::new(ErrorKind::Undefined, key)),
+        Ok(())
     }
-}
 
-pub fn known(ctx: &mut Context) -> crate::Result<()> {
-    let key_obj = ctx.pop()?;
-    let key = ctx.stringify(&key_obj)?;
-    let dict = ctx.pop_dict()?;
+    pub(crate) fn known(&mut self) -> crate::Result<()> {
+        let key = self.pop()?;
+        let dict = self.pop_dict()?;
 
-    if !dict.access().is_readable() {
-        return Err(Error::from(ErrorKind::InvalidAccess));
+        if !self.heap.get(dict).access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        let key = key.into_key(&self.heap);
+        let exists = self.heap.get(dict).contains_key(&key);
+
+        self.push(Object::Boolean(exists))
     }
 
-    let exists = dict.contains_key(&key);
+    pub(crate) fn wheredef(&mut self) -> crate::Result<()> {
+        let key = self.pop()?;
 
-    ctx.push(Object::Boolean(exists));
+        match self.find_dict(&key) {
+            Some(dict) => {
+                self.push(Object::Dictionary(dict))?;
+                self.push(Object::Boolean(true))
+            },
+            None => self.push(Object::Boolean(false)),
+        }
+    }
 
-    Ok(())
-}
+    pub(crate) fn currentdict(&mut self) -> crate::Result<()> {
+        let dict = self
+            .dict_stack
+            .last()
+            .copied()
+            .ok_or(Error::from(ErrorKind::DictStackUnderflow))?;
 
-pub fn wheredef(ctx: &mut Context) -> crate::Result<()> {
-    let key_obj = ctx.pop()?;
-    let key = ctx.stringify(&key_obj)?;
+        self.push(Object::Dictionary(dict))
+    }
 
-    match ctx.find_index(key) {
-        Ok(idx) => {
-            ctx.push(Object::Dictionary(idx));
-            ctx.push(Object::Boolean(true));
-        },
-        Err(..) => {
-            ctx.push(Object::Boolean(false));
-        },
+    #[allow(dead_code)]
+    pub(crate) fn errorstatus(&mut self) -> crate::Result<()> {
+        Err(Error::new(ErrorKind::Unregistered, "not implemented"))
     }
 
-    Ok(())
-}
+    pub(crate) fn countdictstack(&mut self) -> crate::Result<()> {
+        let i = usize_to_i32(self.dict_stack.len())?;
 
-#[allow(dead_code)]
-pub fn currentdict(ctx: &mut Context) -> crate::Result<()> {
-    let idx = ctx
-        .dict_stack
-        .last()
-        .ok_or(Error::from(ErrorKind::DictStackUnderflow))?;
+        self.push(Object::Integer(i))
+    }
 
-    ctx.push(Object::Dictionary(*idx));
+    pub(crate) fn dictstack(&mut self) -> crate::Result<()> {
+        let arr = self.pop_array()?;
+        if !self.heap.get(arr).access().is_writeable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
 
-    Ok(())
-}
+        let dicts: Vec<Object> = self
+            .dict_stack
+            .iter()
+            .copied()
+            .map(Object::Dictionary)
+            .collect();
+        if dicts.len() > self.heap.get(arr).len() {
+            return Err(Error::from(ErrorKind::RangeCheck));
+        }
 
-#[allow(dead_code)]
-pub fn errorstatus(_ctx: &mut Context) -> crate::Result<()> {
-    Err(Error::new(ErrorKind::Unregistered, "not implemented"))
-}
+        self.journal_array(arr);
+        for (index, obj) in dicts.iter().enumerate() {
+            *self.heap.get_mut(arr).get_mut(index)? = *obj;
+        }
 
-#[allow(dead_code)]
-pub fn userdict(_ctx: &mut Context) -> crate::Result<()> {
-    Err(Error::new(ErrorKind::Unregistered, "not implemented"))
-}
+        if dicts.len() == self.heap.get(arr).len() {
+            return self.push(Object::Array(arr));
+        }
 
-#[allow(dead_code)]
-pub fn globaldict(_ctx: &mut Context) -> crate::Result<()> {
-    Err(Error::new(ErrorKind::Unregistered, "not implemented"))
-}
+        let subarr = self
+            .heap
+            .alloc(ArrayObject::new(dicts, Access::Unlimited, Mode::Literal));
 
-#[allow(dead_code)]
-pub fn statusdict(_ctx: &mut Context) -> crate::Result<()> {
-    Err(Error::new(ErrorKind::Unregistered, "not implemented"))
-}
+        self.push(Object::Array(subarr))
+    }
 
-#[allow(dead_code)]
-pub fn countdictstack(_ctx: &mut Context) -> crate::Result<()> {
-    Err(Error::new(ErrorKind::Unregistered, "not implemented"))
-}
+    pub(crate) fn cleardictstack(&mut self) -> crate::Result<()> {
+        let permanent = self.permanent_dicts();
+        self.dict_stack.truncate(permanent);
+        self.forget_names();
 
-#[allow(dead_code)]
-pub fn dictstack(_ctx: &mut Context) -> crate::Result<()> {
-    Err(Error::new(ErrorKind::Unregistered, "not implemented"))
-}
+        Ok(())
+    }
 
-#[allow(dead_code)]
-pub fn cleardictstack(_ctx: &mut Context) -> crate::Result<()> {
-    Err(Error::new(ErrorKind::Unregistered, "not implemented"))
+    // systemdict, globaldict (from LanguageLevel 2) and userdict can never be
+    // popped
+    pub(crate) fn permanent_dicts(&self) -> usize {
+        if self.language_level < LanguageLevel::Level2 {
+            2
+        } else {
+            3
+        }
+    }
 }
@@ -1,84 +1,127 @@
+use std::io::Write;
+
 use crate::{
+    heap::Heap,
+    literal_name,
     object::{Access, Mode},
-    ArrayObject, Context, ErrorKind, Object,
+    ArrayObject, Error, ErrorKind, Interpreter, Object,
 };
 
-pub fn handleerror(ctx: &mut Context) -> crate::Result<()> {
-    let idx = ctx.find_def("$error").cloned()?.into_index()?;
-    let error_info = ctx.get_dict_mut(idx)?;
-    error_info.insert("newerror", Object::Boolean(false));
-
-    Ok(())
-}
-
-pub fn recover_from_error(ctx: &mut Context, e: ErrorKind) -> crate::Result<()> {
-    let cause = ctx.pop()?;
-    let error_name: &str = e.into();
-    let ostack = ArrayObject::new(
-        ctx.operand_stack.clone(),
-        Access::default(),
-        Mode::default(),
-    );
-    let ostack_idx = ctx.mem_mut().insert(ostack);
-
-    let idx = ctx.find_def("$error").cloned()?.into_index()?;
-    let error_info = ctx.get_dict_mut(idx)?;
-    error_info.insert("newerror", Object::Boolean(true));
-    error_info.insert("errorname", Object::Name(error_name.into()));
-    error_info.insert("command", cause);
-    error_info.insert("ostack", Object::Array(ostack_idx));
-
-    Ok(())
-}
-
-pub fn dictstackunderflow(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::DictStackUnderflow)
-}
-
-pub fn invalidaccess(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::InvalidAccess)
-}
-
-pub fn ioerror(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::IoError)
-}
-
-pub fn limitcheck(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::LimitCheck)
-}
-
-pub fn rangecheck(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::RangeCheck)
-}
-
-pub fn stackunderflow(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::StackUnderflow)
-}
-
-pub fn syntaxerror(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::SyntaxError)
-}
-
-pub fn typecheck(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::TypeCheck)
-}
-
-pub fn undefined(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::Undefined)
-}
-
-pub fn undefinedresult(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::UndefinedResult)
-}
-
-pub fn unmatchedmark(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::UnmatchedMark)
-}
-
-pub fn unregistered(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::Unregistered)
+impl Interpreter {
+    pub(crate) fn error(&mut self, kind: ErrorKind) -> crate::Result<()> {
+        self.recover_from_error(kind)?;
+
+        self.stop()
+    }
+
+    pub(crate) fn recover_from_error(&mut self, kind: ErrorKind) -> crate::Result<()> {
+        let command = self.pop()?;
+        let error_name: &str = kind.into();
+
+        let record_stacks = matches!(
+            self.heap
+                .get(self.error_info)
+                .get(&literal_name("recordstacks")),
+            Some(Object::Boolean(true))
+        );
+        if record_stacks {
+            let ostack = self.operand_stack.clone();
+            let estack = self.snapshot_execution_stack();
+            let dstack = self
+                .dict_stack
+                .iter()
+                .copied()
+                .map(Object::Dictionary)
+                .collect();
+
+            let stacks = [("ostack", ostack), ("estack", estack), ("dstack", dstack)];
+            for (key, stack) in stacks {
+                let stack =
+                    self.heap
+                        .alloc(ArrayObject::new(stack, Access::Unlimited, Mode::Literal));
+                self.heap
+                    .get_mut(self.error_info)
+                    .insert(literal_name(key), Object::Array(stack));
+            }
+        }
+
+        let error_info = self.heap.get_mut(self.error_info);
+        error_info.insert(literal_name("newerror"), Object::Boolean(true));
+        error_info.insert(literal_name("errorname"), literal_name(error_name));
+        error_info.insert(literal_name("command"), command);
+        error_info.insert(literal_name("errorinfo"), Object::Null(Mode::Literal));
+
+        // $error can be on the dictionary stack like any other dictionary
+        self.forget_names();
+
+        Ok(())
+    }
+
+    pub(crate) fn handleerror(&mut self) -> crate::Result<()> {
+        let (is_new, error_name, command) = {
+            let error_info = self.heap.get(self.error_info);
+            let get = |key| {
+                error_info
+                    .get(&literal_name(key))
+                    .copied()
+                    .unwrap_or(Object::Null(Mode::Literal))
+            };
+
+            (get("newerror"), get("errorname"), get("command"))
+        };
+        let stacks = {
+            let error_info = self.heap.get(self.error_info);
+            let get = |key| match error_info.get(&literal_name(key)) {
+                Some(Object::Array(arr)) => Some(self.heap.get(*arr).value().to_vec()),
+                _ => None,
+            };
+
+            [
+                ("Operand stack", get("ostack")),
+                ("Execution stack", get("estack")),
+                ("Dictionary stack", get("dstack")),
+            ]
+        };
+
+        if !matches!(is_new, Object::Boolean(true)) {
+            return Ok(());
+        }
+
+        self.heap
+            .get_mut(self.error_info)
+            .insert(literal_name("newerror"), Object::Boolean(false));
+        self.forget_name(&literal_name("newerror"));
+
+        writeln!(
+            self.stdout,
+            "%%[ Error: {}; OffendingCommand: {} ]%%",
+            self.heap.format(&error_name),
+            self.heap.format(&command)
+        )
+        .or(Err(Error::from(ErrorKind::IoError)))?;
+
+        for (label, stack) in stacks {
+            let Some(stack) = stack else {
+                continue;
+            };
+
+            let items: Vec<String> = stack.iter().map(|obj| summarize(obj, &self.heap)).collect();
+            writeln!(self.stdout, "{label}:\n    {}", items.join(" "))
+                .or(Err(Error::from(ErrorKind::IoError)))?;
+        }
+
+        Ok(())
+    }
 }
 
-pub fn vmerror(ctx: &mut Context) -> crate::Result<()> {
-    recover_from_error(ctx, ErrorKind::VmError)
+// Dictionaries on the recorded stacks are typically systemdict and friends,
+// so only their size is shown
+fn summarize(obj: &Object, heap: &Heap) -> String {
+    match obj {
+        Object::Dictionary(dict) => {
+            let dict = heap.get(*dict);
+            format!("--dict:{}/{}--", dict.len(), dict.capacity())
+        },
+        obj => format!("{:?}", heap.format(obj)),
+    }
 }
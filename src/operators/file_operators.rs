@@ -0,0 +1,246 @@
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    str,
+};
+
+use crate::{
+    frame::Frame, heap::Handle, lexer::Lexer, object::Mode, operator::OperatorObject,
+    string::StringObject, Error, ErrorKind, FileObject, Interpreter, Object,
+};
+
+impl Interpreter {
+    pub(crate) fn file(&mut self) -> crate::Result<()> {
+        let access = self.pop_string()?;
+        let filename = self.pop_string()?;
+
+        match self.heap.get(access).value() {
+            b"r" => {},
+            b"w" | b"a" | b"r+" | b"w+" | b"a+" => {
+                return Err(Error::new(
+                    ErrorKind::InvalidFileAccess,
+                    "files may only be opened for reading",
+                ))
+            },
+            _ => return Err(Error::from(ErrorKind::InvalidFileAccess)),
+        }
+
+        let file = read_file(self.heap.get(filename).value())?;
+
+        let file = self.heap.alloc(FileObject::new(file, Mode::Literal));
+
+        self.push(Object::File(file))
+    }
+
+    pub(crate) fn run_file(&mut self) -> crate::Result<()> {
+        let filename = self.pop_string()?;
+
+        let file = FileObject::new(
+            read_file(self.heap.get(filename).value())?,
+            Mode::Executable,
+        );
+        let file = self.heap.alloc(file);
+        let lexer = Lexer::shared(file, self.language_level);
+
+        self.push_frame(Frame::Lexer(Object::File(file), lexer))
+    }
+
+    /// Pushes the file that is being executed, so procedures can read the
+    /// data that follows them. Outside of any file, an empty file is pushed.
+    pub(crate) fn currentfile(&mut self) -> crate::Result<()> {
+        let file = self
+            .execution_stack
+            .iter()
+            .rev()
+            .find_map(|frame| match frame {
+                Frame::Lexer(Object::File(file), _) => Some(*file),
+                _ => None,
+            })
+            .unwrap_or_else(|| self.heap.alloc(FileObject::default()));
+
+        self.push(Object::File(file))
+    }
+
+    pub(crate) fn read(&mut self) -> crate::Result<()> {
+        let file = self.pop_file()?;
+
+        let file_obj = self.heap.get(file);
+        if file_obj.is_streaming() && file_obj.remaining().is_empty() {
+            return self.await_input(OperatorObject::Read);
+        }
+
+        let mut buf = [0];
+        match self.heap.get_mut(file).read(&mut buf) {
+            Ok(1) => {
+                self.push(Object::Integer(i32::from(buf[0])))?;
+                self.push(Object::Boolean(true))
+            },
+            _ => self.push(Object::Boolean(false)),
+        }
+    }
+
+    pub(crate) fn readstring(&mut self) -> crate::Result<()> {
+        let string = self.pop_writeable_string()?;
+        let file = self.pop_file()?;
+
+        let len = self.heap.get(string).len();
+        if len == 0 {
+            return Err(Error::new(ErrorKind::RangeCheck, "string is empty"));
+        }
+
+        let file_obj = self.heap.get(file);
+        if file_obj.is_streaming() && file_obj.remaining().len() < len {
+            return self.await_input(OperatorObject::ReadString);
+        }
+
+        let mut buf = Vec::with_capacity(len);
+        self.heap
+            .get_mut(file)
+            .take(len as u64)
+            .read_to_end(&mut buf)
+            .or(Err(Error::from(ErrorKind::IoError)))?;
+
+        let is_filled = buf.len() == len;
+        let substr = self.fill_string(string, buf)?;
+
+        self.push(Object::String(substr))?;
+        self.push(Object::Boolean(is_filled))
+    }
+
+    pub(crate) fn readline(&mut self) -> crate::Result<()> {
+        let string = self.pop_writeable_string()?;
+        let file = self.pop_file()?;
+
+        let len = self.heap.get(string).len();
+
+        let file_obj = self.heap.get(file);
+        if file_obj.is_streaming() && !has_line(file_obj.remaining(), len) {
+            return self.await_input(OperatorObject::ReadLine);
+        }
+
+        let file = self.heap.get_mut(file);
+        let mut line = Vec::new();
+        let mut ch = [0];
+        let mut is_terminated = false;
+        while file.read(&mut ch).is_ok_and(|count| count == 1) {
+            match ch[0] {
+                b'\n' => {},
+                b'\r' => {
+                    // A carriage return and line feed end a single line
+                    if file.read(&mut ch).is_ok_and(|count| count == 1) && ch[0] != b'\n' {
+                        let _ = file.seek(SeekFrom::Current(-1));
+                    }
+                },
+                _ if line.len() == len => {
+                    return Err(Error::new(ErrorKind::RangeCheck, "line is too long"));
+                },
+                _ => {
+                    line.push(ch[0]);
+                    continue;
+                },
+            }
+
+            is_terminated = true;
+            break;
+        }
+
+        let substr = self.fill_string(string, line)?;
+
+        self.push(Object::String(substr))?;
+        self.push(Object::Boolean(is_terminated))
+    }
+
+    /// Gives back the operands and retries `operator` once more of the
+    /// streamed job has arrived.
+    fn await_input(&mut self, operator: OperatorObject) -> crate::Result<()> {
+        while let Some(obj) = self.transaction_stack.pop() {
+            self.operand_stack.push(obj);
+        }
+
+        self.awaiting_input = true;
+        self.push_frame(Frame::Object(Object::Operator((
+            operator,
+            Mode::Executable,
+        ))))
+    }
+
+    fn pop_writeable_string(&mut self) -> crate::Result<Handle<StringObject>> {
+        let string = self.pop_string()?;
+        if !self.heap.get(string).access().is_writeable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        Ok(string)
+    }
+
+    /// Copies `data` to the start of `string`, returning the filled part.
+    fn fill_string(
+        &mut self,
+        string: Handle<StringObject>,
+        data: Vec<u8>,
+    ) -> crate::Result<Handle<StringObject>> {
+        self.journal_string(string);
+        let destination = self.heap.get_mut(string);
+        for (index, ch) in data.iter().enumerate() {
+            *destination.get_mut(index)? = *ch;
+        }
+
+        Ok(self.heap.alloc(StringObject::from(data)))
+    }
+
+    pub(crate) fn print_value(&mut self) -> crate::Result<()> {
+        let obj = self.pop()?;
+
+        writeln!(self.stdout, "{}", self.heap.format(&obj)).or(Err(Error::from(ErrorKind::IoError)))
+    }
+
+    pub(crate) fn print_syntax(&mut self) -> crate::Result<()> {
+        let obj = self.pop()?;
+
+        writeln!(self.stdout, "{:?}", self.heap.format(&obj))
+            .or(Err(Error::from(ErrorKind::IoError)))
+    }
+
+    pub(crate) fn stack(&mut self) -> crate::Result<()> {
+        for obj in self.operand_stack.iter().rev() {
+            writeln!(self.stdout, "{}", self.heap.format(obj))
+                .or(Err(Error::from(ErrorKind::IoError)))?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn pstack(&mut self) -> crate::Result<()> {
+        for obj in self.operand_stack.iter().rev() {
+            writeln!(self.stdout, "{:?}", self.heap.format(obj))
+                .or(Err(Error::from(ErrorKind::IoError)))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `data` holds enough for `readline` to finish with a string of
+/// `len` bytes, without waiting for more.
+fn has_line(data: &[u8], len: usize) -> bool {
+    match data.iter().position(|ch| matches!(ch, b'\r' | b'\n')) {
+        // A carriage return may still be followed by a line feed
+        Some(index) => data[index] == b'\n' || index + 1 < data.len(),
+        None => data.len() > len,
+    }
+}
+
+fn read_file(filename: &[u8]) -> crate::Result<Vec<u8>> {
+    let filename = str::from_utf8(filename).or(Err(Error::new(
+        ErrorKind::UndefinedFilename,
+        "invalid filename",
+    )))?;
+
+    fs::read(filename).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => Error::new(ErrorKind::UndefinedFilename, filename.to_string()),
+        io::ErrorKind::PermissionDenied => {
+            Error::new(ErrorKind::InvalidFileAccess, filename.to_string())
+        },
+        _ => Error::new(ErrorKind::IoError, e),
+    })
+}
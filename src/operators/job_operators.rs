@@ -0,0 +1,81 @@
+use std::{io::Write, time::Instant};
+
+use crate::{Error, ErrorKind, Interpreter, Object, Save};
+
+use super::param_operators::password_matches;
+
+impl Interpreter {
+    pub(crate) fn startjob(&mut self) -> crate::Result<()> {
+        let password = self.pop()?;
+        let persistent = self.pop_bool()?;
+
+        let started = self.start_job(persistent, &password);
+
+        self.push(Object::Boolean(started))
+    }
+
+    pub(crate) fn exitserver(&mut self) -> crate::Result<()> {
+        let password = self.pop()?;
+
+        if !self.start_job(true, &password) {
+            return Err(Error::new(ErrorKind::InvalidAccess, "exitserver refused"));
+        }
+
+        writeln!(
+            self.stdout,
+            "%%[ exitserver: permanent state may be changed ]%%"
+        )
+        .or(Err(Error::from(ErrorKind::IoError)))
+    }
+
+    /// Ends the current job and starts a new one from the rest of its input,
+    /// unencapsulated when `persistent` is true.
+    fn start_job(&mut self, persistent: bool, password: &Object) -> bool {
+        if !self.job_server || !password_matches(password, &self.start_job_password, &self.heap) {
+            return false;
+        }
+
+        // Only allowed from the job's own save level, so programs that are
+        // themselves encapsulated cannot escape
+        let job_level = match self.job_save {
+            Some(save) => self
+                .save_stack
+                .iter()
+                .position(|s| *s == save)
+                .map(|level| level + 1),
+            None => Some(0),
+        };
+        if job_level != Some(self.save_stack.len()) {
+            return false;
+        }
+
+        self.end_job();
+        self.begin_job(!persistent);
+
+        true
+    }
+
+    pub(crate) fn begin_job(&mut self, encapsulated: bool) {
+        self.deadline = self.job_timeout.map(|timeout| Instant::now() + timeout);
+        self.heap.set_global(false);
+
+        if encapsulated {
+            let save = self.heap.alloc(Save::new(&self.heap));
+            self.save_stack.push(save);
+            self.job_save = Some(save);
+        }
+    }
+
+    pub(crate) fn end_job(&mut self) {
+        // An unencapsulated job keeps its changes, but any saves it left
+        // behind are still undone
+        let level = match self.job_save.take() {
+            Some(save) => self.save_stack.iter().position(|s| *s == save).unwrap_or(0),
+            None => 0,
+        };
+        self.restore_to(level);
+
+        self.operand_stack.clear();
+        let _ = self.cleardictstack();
+    }
+}
@@ -1,175 +1,184 @@
-use crate::{Context, Error, ErrorKind, Object};
+use crate::{Error, ErrorKind, Interpreter, Object};
 
 use super::{degrees_to_radians, is_valid_real, positive_degrees, radians_to_degrees};
 
-pub fn arithmetic(
-    ctx: &mut Context,
-    checked: impl Fn(i32, i32) -> Option<i32>,
-    real: impl Fn(f64, f64) -> f64,
-) -> crate::Result<()> {
-    let rhs = ctx.pop()?;
-    let lhs = ctx.pop()?;
-
-    if lhs.is_int() && rhs.is_int() {
-        let lhs = lhs.into_int()?;
-        let rhs = rhs.into_int()?;
-
-        let obj = match checked(lhs, rhs) {
-            Some(total) => Object::Integer(total),
-            None => {
-                let total = real(f64::from(lhs), f64::from(rhs));
-                if !is_valid_real(total) {
-                    return Err(Error::from(ErrorKind::UndefinedResult));
-                }
-
-                Object::Real(total)
-            },
-        };
+const RANDOM_MODULUS: i32 = i32::MAX;
+const RANDOM_MULTIPLIER: i32 = 16807;
 
-        ctx.push(obj);
+impl Interpreter {
+    pub(crate) fn arithmetic(
+        &mut self,
+        checked: impl Fn(i32, i32) -> Option<i32>,
+        real: impl Fn(f64, f64) -> f64,
+    ) -> crate::Result<()> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
 
-        return Ok(());
-    }
+        if !lhs.is_number() || !rhs.is_number() {
+            return Err(Error::new(ErrorKind::TypeCheck, "expected number"));
+        }
 
-    let total = real(lhs.into_real()?, rhs.into_real()?);
+        if lhs.is_int() && rhs.is_int() {
+            let lhs = lhs.into_int()?;
+            let rhs = rhs.into_int()?;
 
-    if !is_valid_real(total) {
-        return Err(Error::from(ErrorKind::UndefinedResult));
-    }
+            let obj = match checked(lhs, rhs) {
+                Some(total) => Object::Integer(total),
+                None => {
+                    let total = real(f64::from(lhs), f64::from(rhs));
+                    if !is_valid_real(total) {
+                        return Err(Error::from(ErrorKind::UndefinedResult));
+                    }
 
-    ctx.push(Object::Real(total));
+                    Object::Real(total as f32)
+                },
+            };
 
-    Ok(())
-}
+            return self.push(obj);
+        }
 
-pub fn num_unary(
-    ctx: &mut Context,
-    int: impl Fn(i32) -> Option<i32>,
-    real: impl Fn(f64) -> f64,
-) -> crate::Result<()> {
-    let n = ctx.pop()?;
-
-    if n.is_int() {
-        let obj = match int(n.clone().into_int()?) {
-            Some(total) => Object::Integer(total),
-            None => Object::Real(real(n.into_real()?)),
-        };
+        let total = real(f64::from(lhs.into_real()?), f64::from(rhs.into_real()?));
 
-        ctx.push(obj);
+        if !is_valid_real(total) {
+            return Err(Error::from(ErrorKind::UndefinedResult));
+        }
 
-        return Ok(());
+        self.push(Object::Real(total as f32))
     }
 
-    ctx.push(Object::Real(real(n.into_real()?)));
+    pub(crate) fn num_unary(
+        &mut self,
+        int: impl Fn(i32) -> Option<i32>,
+        real: impl Fn(f64) -> f64,
+    ) -> crate::Result<()> {
+        let n = self.pop()?;
 
-    Ok(())
-}
+        if n.is_int() {
+            let obj = match int(n.into_int()?) {
+                Some(total) => Object::Integer(total),
+                None => Object::Real(real(f64::from(n.into_real()?)) as f32),
+            };
 
-pub fn real_unary(ctx: &mut Context, unary: impl Fn(f64) -> f64) -> crate::Result<()> {
-    let n = ctx.pop_real()?;
+            return self.push(obj);
+        }
 
-    let total = unary(n);
+        let total = real(f64::from(n.into_real()?));
 
-    if !is_valid_real(total) {
-        return Err(Error::from(ErrorKind::UndefinedResult));
+        self.push(Object::Real(total as f32))
     }
 
-    ctx.push(Object::Real(total));
-
-    Ok(())
-}
-
-pub fn idiv(ctx: &mut Context) -> crate::Result<()> {
-    let rhs = ctx.pop_int()?;
-    let lhs = ctx.pop_int()?;
+    pub(crate) fn real_unary(&mut self, unary: impl Fn(f64) -> f64) -> crate::Result<()> {
+        let n = self.pop_real()?;
 
-    let total = match lhs.checked_div(rhs) {
-        Some(total) => Ok(total),
-        None => Err(Error::from(ErrorKind::UndefinedResult)),
-    }?;
+        let total = unary(f64::from(n));
 
-    ctx.push(Object::Integer(total));
-
-    Ok(())
-}
+        if !is_valid_real(total) {
+            return Err(Error::from(ErrorKind::UndefinedResult));
+        }
 
-pub fn imod(ctx: &mut Context) -> crate::Result<()> {
-    let rhs = ctx.pop_int()?;
-    let lhs = ctx.pop_int()?;
+        self.push(Object::Real(total as f32))
+    }
 
-    let total = match lhs.checked_rem(rhs) {
-        Some(total) => Ok(total),
-        None => Err(Error::from(ErrorKind::UndefinedResult)),
-    }?;
+    pub(crate) fn idiv(&mut self) -> crate::Result<()> {
+        let rhs = self.pop_int()?;
+        let lhs = self.pop_int()?;
 
-    ctx.push(Object::Integer(total));
+        let total = match lhs.checked_div(rhs) {
+            Some(total) => Ok(total),
+            None => Err(Error::from(ErrorKind::UndefinedResult)),
+        }?;
 
-    Ok(())
-}
+        self.push(Object::Integer(total))
+    }
 
-pub fn atan(ctx: &mut Context) -> crate::Result<()> {
-    let den = ctx.pop_real()?;
-    let num = ctx.pop_real()?;
+    pub(crate) fn imod(&mut self) -> crate::Result<()> {
+        let rhs = self.pop_int()?;
+        let lhs = self.pop_int()?;
 
-    let total = positive_degrees(radians_to_degrees((num / den).atan()));
+        let total = match lhs.checked_rem(rhs) {
+            Some(total) => Ok(total),
+            None => Err(Error::from(ErrorKind::UndefinedResult)),
+        }?;
 
-    if !is_valid_real(total) {
-        return Err(Error::from(ErrorKind::UndefinedResult));
+        self.push(Object::Integer(total))
     }
 
-    ctx.push(Object::Real(total));
+    pub(crate) fn atan(&mut self) -> crate::Result<()> {
+        let den = f64::from(self.pop_real()?);
+        let num = f64::from(self.pop_real()?);
 
-    Ok(())
-}
+        if num == 0.0 && den == 0.0 {
+            return Err(Error::from(ErrorKind::UndefinedResult));
+        }
 
-pub fn cos(ctx: &mut Context) -> crate::Result<()> {
-    let n = ctx.pop_real()?;
+        let total = positive_degrees(radians_to_degrees(num.atan2(den)));
 
-    let total = degrees_to_radians(n).cos();
+        if !is_valid_real(total) {
+            return Err(Error::from(ErrorKind::UndefinedResult));
+        }
 
-    if !is_valid_real(total) {
-        return Err(Error::from(ErrorKind::UndefinedResult));
+        self.push(Object::Real(total as f32))
     }
 
-    ctx.push(Object::Real(total));
-
-    Ok(())
-}
+    pub(crate) fn cos(&mut self) -> crate::Result<()> {
+        let n = f64::from(self.pop_real()?);
 
-pub fn sin(ctx: &mut Context) -> crate::Result<()> {
-    let n = ctx.pop_real()?;
+        let total = degrees_to_radians(n).cos();
 
-    let total = degrees_to_radians(n).sin();
+        if !is_valid_real(total) {
+            return Err(Error::from(ErrorKind::UndefinedResult));
+        }
 
-    if !is_valid_real(total) {
-        return Err(Error::from(ErrorKind::UndefinedResult));
+        self.push(Object::Real(total as f32))
     }
 
-    ctx.push(Object::Real(total));
+    pub(crate) fn sin(&mut self) -> crate::Result<()> {
+        let n = f64::from(self.pop_real()?);
 
-    Ok(())
-}
+        let total = degrees_to_radians(n).sin();
 
-pub fn rand(ctx: &mut Context) -> crate::Result<()> {
-    let n = ctx.rng.rand().abs();
-
-    ctx.push(Object::Integer(n));
-
-    Ok(())
-}
+        if !is_valid_real(total) {
+            return Err(Error::from(ErrorKind::UndefinedResult));
+        }
 
-pub fn srand(ctx: &mut Context) -> crate::Result<()> {
-    let seed = ctx.pop_int()?;
+        self.push(Object::Real(total as f32))
+    }
 
-    ctx.rng.set_seed(seed);
+    // The "minimal standard" generator of Park and Miller (CACM 31(10), 1988),
+    // x' = 16807x mod (2^31 - 1), which is what Adobe interpreters use.
+    // Schrage's method keeps the intermediate products within an i32.
+    pub(crate) fn rand(&mut self) -> crate::Result<()> {
+        const Q: i32 = RANDOM_MODULUS / RANDOM_MULTIPLIER;
+        const R: i32 = RANDOM_MODULUS % RANDOM_MULTIPLIER;
+
+        let seed = self.random_seed;
+        let mut next = RANDOM_MULTIPLIER * (seed % Q) - R * (seed / Q);
+        if next <= 0 {
+            next += RANDOM_MODULUS;
+        }
+        self.random_seed = next;
+
+        self.push(Object::Integer(next))
+    }
 
-    Ok(())
-}
+    pub(crate) fn srand(&mut self) -> crate::Result<()> {
+        let seed = self.pop_int()?;
+        self.set_random_seed(seed);
 
-pub fn rrand(ctx: &mut Context) -> crate::Result<()> {
-    let seed = ctx.rng.seed();
+        Ok(())
+    }
 
-    ctx.push(Object::Integer(seed));
+    /// Folds any integer into the generator's valid states, 1..=2^31 - 2, the
+    /// same way Adobe interpreters do.
+    pub(crate) fn set_random_seed(&mut self, seed: i32) {
+        self.random_seed = if seed < 1 {
+            -(seed % (RANDOM_MODULUS - 1)) + 1
+        } else {
+            seed.min(RANDOM_MODULUS - 1)
+        };
+    }
 
-    Ok(())
+    pub(crate) fn rrand(&mut self) -> crate::Result<()> {
+        self.push(Object::Integer(self.random_seed))
+    }
 }
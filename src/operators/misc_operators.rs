@@ -1,7 +1,117 @@
-use crate::{Context, Object};
+use crate::{
+    array::ArrayObject,
+    bytecode,
+    heap::Handle,
+    object::{Access, Mode},
+    realtime,
+    visit::Visits,
+    Interpreter, Object, StringObject,
+};
 
-pub fn null(ctx: &mut Context) -> crate::Result<()> {
-    ctx.push(Object::Null);
+impl Interpreter {
+    pub(crate) fn bind(&mut self) -> crate::Result<()> {
+        let proc = self.pop_array()?;
+        self.bind_procedure(proc, &mut Visits::default())?;
 
-    Ok(())
+        self.push(Object::Array(proc))
+    }
+
+    // Replaces names that currently resolve to operators with the operators
+    // themselves, then compiles the procedure. Nested procedures are bound
+    // too and made read-only, while read-only procedures are left as they
+    // are.
+    fn bind_procedure(
+        &mut self,
+        proc: Handle<ArrayObject>,
+        visits: &mut Visits,
+    ) -> crate::Result<()> {
+        let Some(mut visit) = visits.enter(proc) else {
+            return Ok(());
+        };
+
+        if self.heap.get(proc).access().is_writeable() {
+            self.journal_array(proc);
+
+            let objs = self.heap.get(proc).value().to_vec();
+            for (index, obj) in objs.into_iter().enumerate() {
+                match obj {
+                    Object::Name(name) if name.mode().is_executable() => {
+                        if let Ok(Object::Operator((operator, Mode::Executable))) =
+                            self.find(&Object::Name(name))
+                        {
+                            *self.heap.get_mut(proc).get_mut(index)? =
+                                Object::Operator((operator, Mode::Executable));
+                        }
+                    },
+                    Object::Array(nested) if self.heap.get(nested).mode().is_executable() => {
+                        let is_writeable = self.heap.get(nested).access().is_writeable();
+                        self.bind_procedure(nested, &mut visit)?;
+                        if is_writeable {
+                            self.heap.get_mut(nested).set_access(Access::ReadOnly);
+                        }
+                    },
+                    _ => {},
+                }
+            }
+        }
+
+        bytecode::compile(proc, &mut self.heap);
+
+        Ok(())
+    }
+
+    pub(crate) fn languagelevel(&mut self) -> crate::Result<()> {
+        self.push(Object::Integer(self.language_level.into()))
+    }
+
+    pub(crate) fn realtime(&mut self) -> crate::Result<()> {
+        // Only differences between readings are meaningful, so wrapping
+        // into an int is fine
+        let ms = if self.deterministic {
+            0
+        } else {
+            realtime() as i32
+        };
+
+        self.push(Object::Integer(ms))
+    }
+
+    pub(crate) fn usertime(&mut self) -> crate::Result<()> {
+        let ms = if self.deterministic {
+            0
+        } else {
+            self.start_time.elapsed().as_millis() as i32
+        };
+
+        self.push(Object::Integer(ms))
+    }
+
+    pub(crate) fn product(&mut self) -> crate::Result<()> {
+        let product = read_only_string(self.identity.product.clone());
+        let product = self.heap.alloc(product);
+
+        self.push(Object::String(product))
+    }
+
+    pub(crate) fn revision(&mut self) -> crate::Result<()> {
+        self.push(Object::Integer(self.identity.revision))
+    }
+
+    pub(crate) fn serialnumber(&mut self) -> crate::Result<()> {
+        self.push(Object::Integer(self.identity.serial_number))
+    }
+
+    pub(crate) fn version(&mut self) -> crate::Result<()> {
+        let version = read_only_string(self.identity.version.clone());
+        let version = self.heap.alloc(version);
+
+        self.push(Object::String(version))
+    }
+}
+
+fn read_only_string(value: String) -> StringObject {
+    let mut string = StringObject::from(value.into_bytes());
+    string.set_access(Access::ReadOnly);
+
+    string
 }
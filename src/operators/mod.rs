@@ -1,26 +1,25 @@
 use std::f64::consts;
 
-pub use array_operators::*;
-pub use debug_operators::*;
-pub use dict_operators::*;
-pub use error_operators::*;
-pub use math_operators::*;
-pub use misc_operators::*;
-pub use relational_operators::*;
-pub use stack_operators::*;
-pub use type_operators::*;
-
 use crate::{Error, ErrorKind};
 
 mod array_operators;
+mod context_operators;
+mod control_operators;
 mod debug_operators;
+mod device_operators;
 mod dict_operators;
 mod error_operators;
+mod file_operators;
+mod job_operators;
 mod math_operators;
 mod misc_operators;
+mod param_operators;
 mod relational_operators;
+mod resource_operators;
 mod stack_operators;
+mod status_operators;
 mod type_operators;
+mod vm_operators;
 
 fn radians_to_degrees(radians: f64) -> f64 {
     radians * (180.0 / consts::PI)
@@ -51,5 +50,5 @@ fn usize_to_i32(u: usize) -> crate::Result<i32> {
 }
 
 fn is_valid_real(n: f64) -> bool {
-    n.is_finite() && !n.is_nan()
+    n.is_finite() && !n.is_nan() && (n as f32).is_finite()
 }
@@ -0,0 +1,302 @@
+use std::{mem, ops::RangeInclusive};
+
+use crate::{
+    dictionary::DictionaryObject, heap::Heap, literal_name, name::NameObject, object::Mode, Error,
+    ErrorKind, Interpreter, Limits, Object, StringObject,
+};
+
+// The VMThreshold used when the parameter is set to -1
+const DEFAULT_VM_THRESHOLD: usize = 40000;
+
+const READ_ONLY_DEVICE_PARAMS: &[&str] = &["HasNames", "Removable", "Type"];
+
+const READ_ONLY_SYSTEM_PARAMS: &[&str] = &[
+    "BuildTime",
+    "ByteOrder",
+    "CurFontCache",
+    "CurOutlineCache",
+    "RealFormat",
+];
+
+impl Interpreter {
+    pub(crate) fn setuserparams(&mut self) -> crate::Result<()> {
+        let changes = self.pop_dict()?;
+        let changes = self.heap.get(changes).clone();
+        if !changes.access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        let limits = Limits {
+            max_dict_stack: stack_param(
+                &changes,
+                "MaxDictStack",
+                self.limits.max_dict_stack,
+                self.dict_stack.len()..=self.host_limits.max_dict_stack,
+            )?,
+            max_exec_stack: stack_param(
+                &changes,
+                "MaxExecStack",
+                self.limits.max_exec_stack,
+                self.execution_stack.len()..=self.host_limits.max_exec_stack,
+            )?,
+            max_op_stack: stack_param(
+                &changes,
+                "MaxOpStack",
+                self.limits.max_op_stack,
+                self.operand_stack.len()..=self.host_limits.max_op_stack,
+            )?,
+        };
+
+        if let Some(Object::Integer(vm_reclaim)) = changes.get(&literal_name("VMReclaim")) {
+            if !(-2..=0).contains(vm_reclaim) {
+                return Err(Error::from(ErrorKind::RangeCheck));
+            }
+        }
+
+        let updates = param_updates(&self.user_params, &changes, &[], &mut self.heap)?;
+
+        self.limits = limits;
+        for (key, value) in updates {
+            self.user_params.insert(key, value);
+        }
+        self.set_vm_threshold();
+
+        Ok(())
+    }
+
+    /// Applies the VMReclaim and VMThreshold user parameters to the heap.
+    pub(crate) fn set_vm_threshold(&mut self) {
+        let threshold = match (
+            self.user_params.get(&literal_name("VMReclaim")),
+            self.user_params.get(&literal_name("VMThreshold")),
+        ) {
+            (Some(Object::Integer(0)), Some(Object::Integer(threshold))) => {
+                Some(usize::try_from(*threshold).unwrap_or(DEFAULT_VM_THRESHOLD))
+            },
+            _ => None,
+        };
+
+        self.heap.set_threshold(threshold);
+    }
+
+    pub(crate) fn currentuserparams(&mut self) -> crate::Result<()> {
+        let mut params = copy_params(&self.user_params, &mut self.heap);
+
+        let limits = [
+            ("MaxDictStack", self.limits.max_dict_stack),
+            ("MaxExecStack", self.limits.max_exec_stack),
+            ("MaxOpStack", self.limits.max_op_stack),
+        ];
+        for (key, limit) in limits {
+            let limit = i32::try_from(limit).unwrap_or(i32::MAX);
+            params.insert(literal_name(key), Object::Integer(limit));
+        }
+
+        let params = self.heap.alloc(params);
+
+        self.push(Object::Dictionary(params))
+    }
+
+    pub(crate) fn setsystemparams(&mut self) -> crate::Result<()> {
+        let changes = self.pop_dict()?;
+        let changes = self.heap.get(changes).clone();
+        if !changes.access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        check_password(&changes, &self.system_params_password, &self.heap)?;
+
+        let system_params_password = changes
+            .get(&literal_name("SystemParamsPassword"))
+            .map(|obj| password(obj, &self.heap))
+            .transpose()?;
+        let start_job_password = changes
+            .get(&literal_name("StartJobPassword"))
+            .map(|obj| password(obj, &self.heap))
+            .transpose()?;
+        let updates = param_updates(
+            &self.system_params,
+            &changes,
+            READ_ONLY_SYSTEM_PARAMS,
+            &mut self.heap,
+        )?;
+
+        if let Some(system_params_password) = system_params_password {
+            self.system_params_password = system_params_password;
+        }
+        if let Some(start_job_password) = start_job_password {
+            self.start_job_password = start_job_password;
+        }
+        for (key, value) in updates {
+            self.system_params.insert(key, value);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn currentsystemparams(&mut self) -> crate::Result<()> {
+        // Passwords are write-only, so they are never reported
+        let mut params = copy_params(&self.system_params, &mut self.heap);
+        params.insert(
+            literal_name("Revision"),
+            Object::Integer(self.identity.revision),
+        );
+
+        let params = self.heap.alloc(params);
+
+        self.push(Object::Dictionary(params))
+    }
+
+    pub(crate) fn setdevparams(&mut self) -> crate::Result<()> {
+        let changes = self.pop_dict()?;
+        let device = self.pop_string()?;
+
+        let changes = self.heap.get(changes).clone();
+        if !changes.access().is_readable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+
+        let Some(&Object::Dictionary(params)) =
+            self.device_params.get(&device_name(self.heap.get(device)))
+        else {
+            return Err(Error::from(ErrorKind::Undefined));
+        };
+
+        check_password(&changes, &self.system_params_password, &self.heap)?;
+
+        let current = self.heap.get(params).clone();
+        let updates = param_updates(&current, &changes, READ_ONLY_DEVICE_PARAMS, &mut self.heap)?;
+        for (key, value) in updates {
+            self.heap.get_mut(params).insert(key, value);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn currentdevparams(&mut self) -> crate::Result<()> {
+        let device = self.pop_string()?;
+
+        let params = match self.device_params.get(&device_name(self.heap.get(device))) {
+            Some(&Object::Dictionary(params)) => {
+                let params = self.heap.get(params).clone();
+                copy_params(&params, &mut self.heap)
+            },
+            _ => return Err(Error::from(ErrorKind::Undefined)),
+        };
+
+        let params = self.heap.alloc(params);
+
+        self.push(Object::Dictionary(params))
+    }
+}
+
+fn stack_param(
+    changes: &DictionaryObject,
+    key: &str,
+    current: usize,
+    allowed: RangeInclusive<usize>,
+) -> crate::Result<usize> {
+    match changes.get(&literal_name(key)) {
+        None => Ok(current),
+        Some(Object::Integer(limit)) => {
+            let limit = usize::try_from(*limit).or(Err(Error::from(ErrorKind::RangeCheck)))?;
+            if !allowed.contains(&limit) {
+                return Err(Error::new(
+                    ErrorKind::LimitCheck,
+                    format!(
+                        "{key} must be between {} and {}",
+                        allowed.start(),
+                        allowed.end()
+                    ),
+                ));
+            }
+
+            Ok(limit)
+        },
+        Some(_) => Err(Error::new(ErrorKind::TypeCheck, "expected integer")),
+    }
+}
+
+// Validates every change before any are applied, so a bad entry leaves the
+// parameters untouched. Unknown and read-only parameters are ignored.
+fn param_updates(
+    params: &DictionaryObject,
+    changes: &DictionaryObject,
+    read_only: &[&str],
+    heap: &mut Heap,
+) -> crate::Result<Vec<(Object, Object)>> {
+    let mut updates = Vec::new();
+
+    for (key, value) in changes.iter() {
+        let Some(current) = params.get(key) else {
+            continue;
+        };
+
+        if read_only.iter().any(|name| *key == literal_name(name)) {
+            continue;
+        }
+
+        if mem::discriminant(current) != mem::discriminant(value) {
+            return Err(Error::new(
+                ErrorKind::TypeCheck,
+                format!("invalid value for {}", heap.format(key)),
+            ));
+        }
+
+        updates.push((*key, copy_param(value, heap)));
+    }
+
+    Ok(updates)
+}
+
+fn copy_params(params: &DictionaryObject, heap: &mut Heap) -> DictionaryObject {
+    params
+        .iter()
+        .fold(DictionaryObject::default(), |mut dict, (key, value)| {
+            dict.insert(*key, copy_param(value, heap));
+            dict
+        })
+}
+
+// Strings are copied in and out so jobs cannot change parameters in place
+fn copy_param(value: &Object, heap: &mut Heap) -> Object {
+    match value {
+        Object::String(string) => {
+            let string = StringObject::from(heap.get(*string).value().to_vec());
+            Object::String(heap.alloc(string))
+        },
+        value => *value,
+    }
+}
+
+fn check_password(changes: &DictionaryObject, expected: &[u8], heap: &Heap) -> crate::Result<()> {
+    if expected.is_empty() {
+        return Ok(());
+    }
+
+    match changes.get(&literal_name("Password")) {
+        Some(given) if password_matches(given, expected, heap) => Ok(()),
+        _ => Err(Error::new(ErrorKind::InvalidAccess, "incorrect password")),
+    }
+}
+
+pub(crate) fn password_matches(given: &Object, expected: &[u8], heap: &Heap) -> bool {
+    expected.is_empty() || password(given, heap).is_ok_and(|given| given == expected)
+}
+
+fn password(obj: &Object, heap: &Heap) -> crate::Result<Vec<u8>> {
+    match obj {
+        Object::Integer(i) => Ok(i.to_string().into_bytes()),
+        Object::String(string) => Ok(heap.get(*string).value().to_vec()),
+        _ => Err(Error::new(
+            ErrorKind::TypeCheck,
+            "expected string or integer",
+        )),
+    }
+}
+
+fn device_name(device: &StringObject) -> Object {
+    let name = String::from_utf8_lossy(device.value());
+
+    Object::Name(NameObject::new(&name, Mode::Literal))
+}
@@ -1,30 +1,17 @@
-use crate::{Context, Object};
+use crate::{Interpreter, Object};
 
-pub fn eq(ctx: &mut Context) -> crate::Result<()> {
-    let rhs = ctx.pop()?;
-    let lhs = ctx.pop()?;
+impl Interpreter {
+    pub(crate) fn eq(&mut self) -> crate::Result<()> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
 
-    match (lhs, rhs) {
-        (Object::String(lhs), Object::String(rhs)) => {
-            let lhs = ctx.get_string(lhs)?;
-            let rhs = ctx.get_string(rhs)?;
-
-            ctx.push(Object::Boolean(lhs == rhs));
-        },
-        (Object::String(lhs), Object::Name(rhs)) => {
-            let lhs: &str = ctx.get_string(lhs)?.into();
-            let rhs: &str = (&rhs).into();
+        self.push(Object::Boolean(lhs == rhs))
+    }
 
-            ctx.push(Object::Boolean(lhs == rhs))
-        },
-        (Object::Name(lhs), Object::String(rhs)) => {
-            let lhs: &str = (&lhs).into();
-            let rhs: &str = ctx.get_string(rhs)?.into();
+    pub(crate) fn ne(&mut self) -> crate::Result<()> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
 
-            ctx.push(Object::Boolean(lhs == rhs))
-        },
-        (lhs, rhs) => ctx.push(Object::Boolean(lhs == rhs)),
+        self.push(Object::Boolean(lhs != rhs))
     }
-
-    Ok(())
 }
@@ -0,0 +1,149 @@
+use crate::{
+    dictionary::DictionaryObject,
+    heap::{Handle, Heap},
+    literal_name,
+    object::Access,
+    Error, ErrorKind, Interpreter, Object,
+};
+
+impl Interpreter {
+    pub(crate) fn defineresource(&mut self) -> crate::Result<()> {
+        let category = self.pop()?;
+        let instance = self.pop()?;
+        let key = self.pop()?;
+
+        let instances = self.find_category(&category)?;
+
+        if instances == self.font_directory {
+            return self.define_font(key, instance);
+        }
+
+        self.insert(instances, key, instance)?;
+
+        self.push(instance)
+    }
+
+    pub(crate) fn findresource(&mut self) -> crate::Result<()> {
+        let category = self.pop()?;
+        let key = self.pop()?;
+
+        let instances = self.find_category(&category)?;
+        let key = key.into_key(&self.heap);
+        let instance = self.heap.get(instances).get(&key).copied();
+
+        match instance {
+            Some(instance) => self.push(instance),
+            None => Err(Error::new(
+                ErrorKind::UndefinedResource,
+                format!(
+                    "{} not found in {}",
+                    self.heap.format(&key),
+                    self.heap.format(&category)
+                ),
+            )),
+        }
+    }
+
+    pub(crate) fn undefineresource(&mut self) -> crate::Result<()> {
+        let category = self.pop()?;
+        let key = self.pop()?;
+
+        let instances = self.find_category(&category)?;
+        let key = key.into_key(&self.heap);
+        self.journal_dict(instances);
+        self.forget_name(&key);
+        self.heap.get_mut(instances).remove(&key);
+
+        Ok(())
+    }
+
+    pub(crate) fn resourcestatus(&mut self) -> crate::Result<()> {
+        let category = self.pop()?;
+        let key = self.pop()?;
+
+        let instances = self.find_category(&category)?;
+        let key = key.into_key(&self.heap);
+        if !self.heap.get(instances).contains_key(&key) {
+            return self.push(Object::Boolean(false));
+        }
+
+        self.push(Object::Integer(1))?;
+        self.push(Object::Integer(-1))?;
+        self.push(Object::Boolean(true))
+    }
+
+    pub(crate) fn definefont(&mut self) -> crate::Result<()> {
+        let font = self.pop()?;
+        let key = self.pop()?;
+
+        self.define_font(key, font)
+    }
+
+    pub(crate) fn findfont(&mut self) -> crate::Result<()> {
+        let key = self.pop()?;
+
+        let key = key.into_key(&self.heap);
+        let font = self.heap.get(self.font_directory).get(&key).copied();
+
+        match font {
+            Some(font) => self.push(font),
+            None => Err(Error::new(
+                ErrorKind::InvalidFont,
+                format!("{} not found", self.heap.format(&key)),
+            )),
+        }
+    }
+
+    fn define_font(&mut self, key: Object, font: Object) -> crate::Result<()> {
+        let Object::Dictionary(dict) = font else {
+            return Err(Error::new(ErrorKind::TypeCheck, "expected dictionary"));
+        };
+
+        validate_font(self.heap.get(dict), &self.heap)?;
+
+        self.journal_dict(dict);
+        {
+            let dict = self.heap.get_mut(dict);
+            dict.insert(literal_name("FID"), Object::FontID);
+            dict.set_access(Access::ReadOnly);
+        }
+        self.forget_name(&literal_name("FID"));
+
+        self.insert(self.font_directory, key, font)?;
+
+        self.push(font)
+    }
+
+    fn find_category(&self, category: &Object) -> crate::Result<Handle<DictionaryObject>> {
+        let category = category.into_key(&self.heap);
+
+        match self.heap.get(self.resources).get(&category) {
+            Some(Object::Dictionary(instances)) => Ok(*instances),
+            _ => Err(Error::new(
+                ErrorKind::Undefined,
+                format!("unknown resource category {}", self.heap.format(&category)),
+            )),
+        }
+    }
+}
+
+fn validate_font(font: &DictionaryObject, heap: &Heap) -> crate::Result<()> {
+    let font_type = match font.get(&literal_name("FontType")) {
+        Some(Object::Integer(font_type)) => *font_type,
+        _ => return Err(Error::new(ErrorKind::InvalidFont, "missing FontType")),
+    };
+
+    match font.get(&literal_name("FontMatrix")) {
+        Some(Object::Array(matrix))
+            if heap.get(*matrix).len() == 6 && heap.get(*matrix).iter().all(Object::is_number) => {
+        },
+        _ => return Err(Error::new(ErrorKind::InvalidFont, "invalid FontMatrix")),
+    }
+
+    // Composite fonts (type 0) map through FDepVector instead of an encoding
+    if font_type != 0 && !matches!(font.get(&literal_name("Encoding")), Some(Object::Array(_))) {
+        return Err(Error::new(ErrorKind::InvalidFont, "invalid Encoding"));
+    }
+
+    Ok(())
+}
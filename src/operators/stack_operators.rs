@@ -1,212 +1,169 @@
-use crate::{Context, Error, ErrorKind, Object};
+use crate::{Error, ErrorKind, Interpreter, Object};
 
 use super::usize_to_i32;
 
-pub fn dup(ctx: &mut Context) -> crate::Result<()> {
-    let obj = ctx.pop()?;
+impl Interpreter {
+    pub(crate) fn dup(&mut self) -> crate::Result<()> {
+        let obj = self.pop()?;
 
-    ctx.push(obj.clone());
-    ctx.push(obj);
+        self.push(obj)?;
+        self.push(obj)
+    }
 
-    Ok(())
-}
+    pub(crate) fn exch(&mut self) -> crate::Result<()> {
+        let first = self.pop()?;
+        let second = self.pop()?;
 
-pub fn exch(ctx: &mut Context) -> crate::Result<()> {
-    let first = ctx.pop()?;
-    let second = ctx.pop()?;
+        self.push(first)?;
+        self.push(second)
+    }
 
-    ctx.push(first);
-    ctx.push(second);
+    pub(crate) fn copy(&mut self) -> crate::Result<()> {
+        match self.pop()? {
+            Object::Integer(n) => {
+                let n = usize::try_from(n).or(Err(Error::from(ErrorKind::RangeCheck)))?;
+                if n > self.operand_stack.len() {
+                    return Err(Error::from(ErrorKind::RangeCheck));
+                }
 
-    Ok(())
-}
+                if self.operand_stack.len() + n > self.limits.max_op_stack {
+                    return Err(Error::from(ErrorKind::StackOverflow));
+                }
 
-pub fn copy(ctx: &mut Context) -> crate::Result<()> {
-    match ctx.pop()? {
-        Object::Integer(n) => {
-            let mut original_stack = Vec::new();
-            let mut copy_stack = Vec::new();
-
-            for _ in 0..n {
-                let obj = ctx.pop()?;
-                copy_stack.push(obj.clone());
-                original_stack.push(obj);
-            }
-
-            while let Some(obj) = original_stack.pop() {
-                ctx.push(obj);
-            }
-
-            while let Some(obj) = copy_stack.pop() {
-                ctx.push(obj);
-            }
-
-            Ok(())
-        },
-        Object::Array(dest_idx) => {
-            let obj = ctx.pop()?;
-
-            let source = match obj {
-                Object::Array(idx) => {
-                    let arr = ctx.get_array(idx)?.clone();
-
-                    Ok(arr)
-                },
-                _ => Err(Error::new(ErrorKind::TypeCheck, "expected array")),
-            }?;
-            if source.access().is_exec_only() {
-                return Err(Error::from(ErrorKind::InvalidAccess));
-            }
-
-            let destination = ctx.get_array_mut(dest_idx)?;
-            if !destination.access().is_writeable() {
-                return Err(Error::from(ErrorKind::InvalidAccess));
-            }
-
-            for (index, obj) in source.into_iter().enumerate() {
-                let dest_obj = destination.get_mut(index)?;
-                *dest_obj = obj;
-            }
-
-            ctx.push(Object::Array(dest_idx));
-
-            Ok(())
-        },
-        Object::Dictionary(idx) => {
-            let source = ctx.pop_dict()?.clone();
-            let destination = ctx.get_dict_mut(idx)?;
-
-            for (key, value) in source {
-                destination.insert(key, value);
-            }
-
-            ctx.push(Object::Dictionary(idx));
-
-            Ok(())
-        },
-        Object::String(idx) => {
-            let source = ctx.pop_string()?.clone();
-            let destination = ctx.get_string_mut(idx)?;
-
-            if destination.len() < source.len() {
-                return Err(Error::from(ErrorKind::RangeCheck));
-            }
-
-            for (index, ch) in source.into_iter().enumerate() {
-                let dest_ch = destination.get_mut(index)?;
-                *dest_ch = ch;
-            }
-
-            ctx.push(Object::String(idx));
-
-            Ok(())
-        },
-        _ => Err(Error::new(ErrorKind::TypeCheck, "expected integer")),
-    }
-}
+                let start = self.operand_stack.len() - n;
+                self.operand_stack.extend_from_within(start..);
 
-pub fn roll(ctx: &mut Context) -> crate::Result<()> {
-    let mut top_stack = Vec::new();
-    let mut bottom_stack = Vec::new();
+                Ok(())
+            },
+            Object::Array(destination) => {
+                let source = self.pop_array()?;
+                if !self.heap.get(source).access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-    let j = ctx.pop_int()?;
-    let n = ctx.pop_usize()?;
+                let source = self.heap.get(source).value().to_vec();
+                if !self.heap.get(destination).access().is_writeable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
+                if self.heap.get(destination).len() < source.len() {
+                    return Err(Error::from(ErrorKind::RangeCheck));
+                }
 
-    if n > ctx.operand_stack.len() {
-        return Err(Error::from(ErrorKind::RangeCheck));
-    }
-    let n = usize_to_i32(n)?;
-
-    let j = (n + j) % n;
-    for i in 0..n {
-        let obj = ctx.pop()?;
-        if i < j {
-            bottom_stack.push(obj);
-        } else {
-            top_stack.push(obj);
-        }
-    }
+                self.journal_array(destination);
+                for (index, obj) in source.into_iter().enumerate() {
+                    *self.heap.get_mut(destination).get_mut(index)? = obj;
+                }
 
-    while let Some(obj) = bottom_stack.pop() {
-        ctx.push(obj);
-    }
+                self.push(Object::Array(destination))
+            },
+            Object::Dictionary(destination) => {
+                let source = self.pop_dict()?;
+                if !self.heap.get(source).access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
 
-    while let Some(obj) = top_stack.pop() {
-        ctx.push(obj);
-    }
+                let entries: Vec<(Object, Object)> = self
+                    .heap
+                    .get(source)
+                    .iter()
+                    .map(|(key, value)| (*key, *value))
+                    .collect();
+                for (key, value) in entries {
+                    self.insert(destination, key, value)?;
+                }
 
-    Ok(())
-}
+                self.push(Object::Dictionary(destination))
+            },
+            Object::String(destination) => {
+                let source = self.pop_string()?;
+                if !self.heap.get(source).access().is_readable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
+
+                let source = self.heap.get(source).value().to_vec();
+                if !self.heap.get(destination).access().is_writeable() {
+                    return Err(Error::from(ErrorKind::InvalidAccess));
+                }
+                if self.heap.get(destination).len() < source.len() {
+                    return Err(Error::from(ErrorKind::RangeCheck));
+                }
 
-pub fn index(ctx: &mut Context) -> crate::Result<()> {
-    let idx = ctx.pop_usize()?;
+                self.journal_string(destination);
+                for (index, ch) in source.into_iter().enumerate() {
+                    *self.heap.get_mut(destination).get_mut(index)? = ch;
+                }
 
-    if ctx.operand_stack.is_empty() {
-        return Err(Error::from(ErrorKind::RangeCheck));
+                self.push(Object::String(destination))
+            },
+            _ => Err(Error::new(ErrorKind::TypeCheck, "expected integer")),
+        }
     }
 
-    let Some(idx) = (ctx.operand_stack.len() - 1).checked_sub(idx) else {
-        return Err(Error::from(ErrorKind::RangeCheck));
-    };
+    pub(crate) fn roll(&mut self) -> crate::Result<()> {
+        let j = self.pop_int()?;
+        let n = self.pop_usize()?;
 
-    match ctx.operand_stack.get(idx) {
-        Some(obj) => {
-            ctx.push(obj.clone());
-            Ok(())
-        },
-        None => Err(Error::from(ErrorKind::RangeCheck)),
+        if n > self.operand_stack.len() {
+            return Err(Error::from(ErrorKind::RangeCheck));
+        }
+        if n == 0 {
+            return Ok(());
+        }
+
+        let n = usize_to_i32(n)?;
+        let j = j.rem_euclid(n) as usize;
+
+        let start = self.operand_stack.len() - n as usize;
+        self.operand_stack[start..].rotate_right(j);
+
+        Ok(())
     }
-}
 
-pub fn mark(ctx: &mut Context) -> crate::Result<()> {
-    ctx.push(Object::Mark);
+    pub(crate) fn index(&mut self) -> crate::Result<()> {
+        let idx = self.pop_usize()?;
 
-    Ok(())
-}
+        let Some(idx) = self.operand_stack.len().checked_sub(idx + 1) else {
+            return Err(Error::from(ErrorKind::RangeCheck));
+        };
 
-pub fn clear(ctx: &mut Context) -> crate::Result<()> {
-    ctx.operand_stack.clear();
+        let obj = self.operand_stack[idx];
 
-    Ok(())
-}
+        self.push(obj)
+    }
 
-pub fn count(ctx: &mut Context) -> crate::Result<()> {
-    let i = usize_to_i32(ctx.operand_stack.len())?;
+    pub(crate) fn mark(&mut self) -> crate::Result<()> {
+        self.push(Object::Mark)
+    }
 
-    ctx.push(Object::Integer(i));
+    pub(crate) fn clear(&mut self) -> crate::Result<()> {
+        self.operand_stack.clear();
 
-    Ok(())
-}
+        Ok(())
+    }
 
-pub fn counttomark(ctx: &mut Context) -> crate::Result<()> {
-    let Some((u, _)) = ctx
-        .operand_stack
-        .iter()
-        .rev()
-        .enumerate()
-        .find(|(_, obj)| matches!(obj, Object::Mark))
-    else {
-        return Err(Error::from(ErrorKind::UnmatchedMark));
-    };
+    pub(crate) fn count(&mut self) -> crate::Result<()> {
+        let i = usize_to_i32(self.operand_stack.len())?;
 
-    let i = usize_to_i32(u)?;
+        self.push(Object::Integer(i))
+    }
 
-    ctx.push(Object::Integer(i));
+    pub(crate) fn counttomark(&mut self) -> crate::Result<()> {
+        let Some(u) = self.operand_stack.iter().rev().position(Object::is_mark) else {
+            return Err(Error::from(ErrorKind::UnmatchedMark));
+        };
 
-    Ok(())
-}
+        let i = usize_to_i32(u)?;
 
-pub fn cleartomark(ctx: &mut Context) -> crate::Result<()> {
-    loop {
-        match ctx.pop() {
-            Err(_) => return Err(Error::from(ErrorKind::UnmatchedMark)),
-            Ok(obj) => {
-                if matches!(obj, Object::Mark) {
-                    break;
-                }
-            },
-        }
+        self.push(Object::Integer(i))
     }
 
-    Ok(())
+    pub(crate) fn cleartomark(&mut self) -> crate::Result<()> {
+        let Some(u) = self.operand_stack.iter().rposition(Object::is_mark) else {
+            return Err(Error::from(ErrorKind::UnmatchedMark));
+        };
+
+        self.operand_stack.truncate(u);
+
+        Ok(())
+    }
 }
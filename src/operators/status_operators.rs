@@ -0,0 +1,178 @@
+use std::time::{Duration, Instant};
+
+use super::param_operators::password_matches;
+use crate::{
+    literal_name,
+    object::{Access, Mode},
+    ArrayObject, Error, ErrorKind, Interpreter, Object, StringObject,
+};
+
+impl Interpreter {
+    // Only the page size is kept. The offset and orientation are accepted
+    // for compatibility, and pageparams reports them as zero.
+    pub(crate) fn setpageparams(&mut self) -> crate::Result<()> {
+        let _orientation = self.pop_int()?;
+        let _offset = self.pop_real()?;
+        let height = self.pop()?;
+        let width = self.pop()?;
+
+        for dimension in [width, height] {
+            if !dimension.is_number() {
+                return Err(Error::new(ErrorKind::TypeCheck, "expected number"));
+            }
+            if dimension.into_real()? <= 0.0 {
+                return Err(Error::from(ErrorKind::RangeCheck));
+            }
+        }
+
+        let size = self.heap.alloc(ArrayObject::new(
+            vec![width, height],
+            Access::Unlimited,
+            Mode::Literal,
+        ));
+        self.set_page_param("PageSize", Object::Array(size));
+
+        Ok(())
+    }
+
+    pub(crate) fn pageparams(&mut self) -> crate::Result<()> {
+        let size = match self.page_param("PageSize") {
+            Some(Object::Array(arr)) => self.heap.get(arr).value().to_vec(),
+            _ => Vec::new(),
+        };
+        let [width, height] = size.as_slice() else {
+            return Err(Error::from(ErrorKind::ConfigurationError));
+        };
+        let (width, height) = (*width, *height);
+
+        self.push(width)?;
+        self.push(height)?;
+        self.push(Object::Integer(0))?;
+        self.push(Object::Integer(0))
+    }
+
+    pub(crate) fn pagecount(&mut self) -> crate::Result<()> {
+        self.push(Object::Integer(self.page_count))
+    }
+
+    pub(crate) fn setduplexmode(&mut self) -> crate::Result<()> {
+        let duplex = self.pop_bool()?;
+        self.set_page_param("Duplex", Object::Boolean(duplex));
+
+        Ok(())
+    }
+
+    pub(crate) fn duplexmode(&mut self) -> crate::Result<()> {
+        let duplex = self.page_param("Duplex");
+
+        self.push(duplex.unwrap_or(Object::Boolean(false)))
+    }
+
+    pub(crate) fn settumble(&mut self) -> crate::Result<()> {
+        let tumble = self.pop_bool()?;
+        self.set_page_param("Tumble", Object::Boolean(tumble));
+
+        Ok(())
+    }
+
+    pub(crate) fn tumble(&mut self) -> crate::Result<()> {
+        let tumble = self.page_param("Tumble");
+
+        self.push(tumble.unwrap_or(Object::Boolean(false)))
+    }
+
+    pub(crate) fn setpapertray(&mut self) -> crate::Result<()> {
+        let tray = self.pop_int()?;
+        self.set_page_param("MediaPosition", Object::Integer(tray));
+
+        Ok(())
+    }
+
+    pub(crate) fn papertray(&mut self) -> crate::Result<()> {
+        let tray = match self.page_param("MediaPosition") {
+            Some(Object::Integer(tray)) => tray,
+            _ => 0,
+        };
+
+        self.push(Object::Integer(tray))
+    }
+
+    pub(crate) fn setjobtimeout(&mut self) -> crate::Result<()> {
+        let seconds = self.pop_int()?;
+        let seconds = u64::try_from(seconds).or(Err(Error::from(ErrorKind::RangeCheck)))?;
+
+        // Only the running job is affected, and 0 lifts the limit
+        self.deadline = match seconds {
+            0 => None,
+            seconds => Some(Instant::now() + Duration::from_secs(seconds)),
+        };
+
+        Ok(())
+    }
+
+    pub(crate) fn jobtimeout(&mut self) -> crate::Result<()> {
+        let remaining = match self.deadline {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now()).as_secs(),
+            None => 0,
+        };
+        let remaining = i32::try_from(remaining).unwrap_or(i32::MAX);
+
+        self.push(Object::Integer(remaining))
+    }
+
+    // The defaults new jobs start out with. Only the job timeout is
+    // configurable, through the host.
+    pub(crate) fn defaulttimeouts(&mut self) -> crate::Result<()> {
+        let job = self.job_timeout.map_or(0, |timeout| timeout.as_secs());
+        let job = i32::try_from(job).unwrap_or(i32::MAX);
+        let status_dict = self.heap.get(self.status_dict);
+        let manual = status_dict
+            .get(&literal_name("manualfeedtimeout"))
+            .copied()
+            .unwrap_or(Object::Integer(0));
+        let wait = status_dict
+            .get(&literal_name("waittimeout"))
+            .copied()
+            .unwrap_or(Object::Integer(0));
+
+        self.push(Object::Integer(job))?;
+        self.push(manual)?;
+        self.push(wait)
+    }
+
+    pub(crate) fn printername(&mut self) -> crate::Result<()> {
+        let string = self.pop_string()?;
+        let name = self.identity.product.clone().into_bytes();
+
+        let target = self.heap.get(string);
+        if !target.access().is_writeable() {
+            return Err(Error::from(ErrorKind::InvalidAccess));
+        }
+        if target.len() < name.len() {
+            return Err(Error::from(ErrorKind::RangeCheck));
+        }
+
+        self.journal_string(string);
+        let target = self.heap.get_mut(string);
+        for (index, byte) in name.iter().enumerate() {
+            *target.get_mut(index)? = *byte;
+        }
+        let name = self.heap.alloc(StringObject::from(name));
+
+        self.push(Object::String(name))
+    }
+
+    pub(crate) fn checkpassword(&mut self) -> crate::Result<()> {
+        let password = self.pop()?;
+        if !matches!(password, Object::Integer(_) | Object::String(_)) {
+            return Err(Error::new(
+                ErrorKind::TypeCheck,
+                "expected string or integer",
+            ));
+        }
+
+        let matches = password_matches(&password, &self.system_params_password, &self.heap);
+
+        self.push(Object::Boolean(matches))
+    }
+}
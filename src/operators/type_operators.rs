@@ -1,15 +1,12 @@
-use crate::{
-    object::{Mode, NameObject},
-    Context, Object,
-};
+use crate::{name::NameObject, object::Mode, Interpreter, Object};
 
-pub fn gettype(ctx: &mut Context) -> crate::Result<()> {
-    let obj = ctx.pop()?;
+impl Interpreter {
+    pub(crate) fn gettype(&mut self) -> crate::Result<()> {
+        let obj = self.pop()?;
 
-    let obj_type: &str = obj.into();
-    let name = NameObject::new(obj_type, Mode::Executable);
+        let obj_type: &str = (&obj).into();
+        let name = NameObject::new(obj_type, Mode::Executable);
 
-    ctx.push(Object::Name(name));
-
-    Ok(())
+        self.push(Object::Name(name))
+    }
 }
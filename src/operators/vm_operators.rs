@@ -0,0 +1,247 @@
+use crate::{
+    context::ContextState,
+    dictionary::DictionaryObject,
+    frame::Frame,
+    heap::Handle,
+    literal_name,
+    object::{Access, Mode},
+    string::StringObject,
+    ArrayObject, Error, ErrorKind, Interpreter, Object, Save, MAX_ARRAY_LENGTH, MAX_SAVE_LEVEL,
+};
+
+impl Interpreter {
+    pub(crate) fn save(&mut self) -> crate::Result<()> {
+        if self.save_stack.len() >= MAX_SAVE_LEVEL {
+            return Err(Error::from(ErrorKind::LimitCheck));
+        }
+
+        let save = self.heap.alloc(Save::new(&self.heap));
+        self.save_stack.push(save);
+
+        self.push(Object::Save(save))
+    }
+
+    pub(crate) fn restore(&mut self) -> crate::Result<()> {
+        let Object::Save(save) = self.pop()? else {
+            return Err(Error::new(ErrorKind::TypeCheck, "expected save"));
+        };
+
+        let Some(level) = self.save_stack.iter().position(|other| *other == save) else {
+            return Err(Error::new(
+                ErrorKind::InvalidRestore,
+                "save is no longer valid",
+            ));
+        };
+
+        // Save objects are left out, since restoring invalidates the newer
+        // ones anyway
+        let save = self.heap.get(save);
+        let is_stranded =
+            |obj: &Object| !matches!(obj, Object::Save(_)) && save.precedes(obj, &self.heap);
+        if self.operand_stack.iter().any(is_stranded)
+            || self
+                .dict_stack
+                .iter()
+                .any(|dict| is_stranded(&Object::Dictionary(*dict)))
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidRestore,
+                "objects created since the save are still on the stacks",
+            ));
+        }
+
+        self.restore_to(level);
+
+        Ok(())
+    }
+
+    /// Undoes every save from `level` upwards, innermost first.
+    pub(crate) fn restore_to(&mut self, level: usize) {
+        for save in self.save_stack.split_off(level).into_iter().rev() {
+            Save::restore(save, &mut self.heap);
+        }
+        self.forget_names();
+    }
+
+    pub(crate) fn setglobal(&mut self) -> crate::Result<()> {
+        let global = self.pop_bool()?;
+        self.heap.set_global(global);
+
+        Ok(())
+    }
+
+    pub(crate) fn currentglobal(&mut self) -> crate::Result<()> {
+        self.push(Object::Boolean(self.heap.global()))
+    }
+
+    pub(crate) fn gcheck(&mut self) -> crate::Result<()> {
+        let obj = self.pop()?;
+
+        self.push(Object::Boolean(self.heap.is_global(&obj)))
+    }
+
+    /// Records `arr` in the innermost save before it is first modified, so
+    /// restoring puts it back. Objects in global VM are left out, since save
+    /// and restore only cover local VM. The same goes for the other journals.
+    pub(crate) fn journal_array(&mut self, arr: Handle<ArrayObject>) {
+        if self.heap.is_global(&Object::from(arr)) {
+            return;
+        }
+
+        if let Some(&save) = self.save_stack.last() {
+            if self.heap.get(save).needs_record(arr.index()) {
+                let value = self.heap.get(arr).clone();
+                self.heap.get_mut(save).record_array(arr, value);
+            }
+        }
+    }
+
+    pub(crate) fn journal_dict(&mut self, dict: Handle<DictionaryObject>) {
+        if self.heap.is_global(&Object::from(dict)) {
+            return;
+        }
+
+        if let Some(&save) = self.save_stack.last() {
+            if self.heap.get(save).needs_record(dict.index()) {
+                let value = self.heap.get(dict).clone();
+                self.heap.get_mut(save).record_dict(dict, value);
+            }
+        }
+    }
+
+    pub(crate) fn journal_string(&mut self, string: Handle<StringObject>) {
+        if self.heap.is_global(&Object::from(string)) {
+            return;
+        }
+
+        if let Some(&save) = self.save_stack.last() {
+            if self.heap.get(save).needs_record(string.index()) {
+                let value = self.heap.get(string).clone();
+                self.heap.get_mut(save).record_string(string, value);
+            }
+        }
+    }
+
+    /// Frees every composite object that can no longer be reached from the
+    /// interpreter.
+    pub(crate) fn collect_garbage(&mut self) {
+        let mut roots = Vec::new();
+
+        roots.extend(self.operand_stack.iter().copied());
+        roots.extend(self.transaction_stack.iter().copied());
+        roots.extend(self.execution_stack.iter().flat_map(Frame::objects));
+        roots.extend(self.dict_stack.iter().copied().map(Object::Dictionary));
+
+        for context in &self.contexts {
+            roots.extend(context.operand_stack.iter().copied());
+            roots.extend(context.execution_stack.iter().flat_map(Frame::objects));
+            roots.extend(context.dict_stack.iter().copied().map(Object::Dictionary));
+
+            match context.state {
+                ContextState::Locking(lock) => roots.push(Object::Lock(lock)),
+                ContextState::Waiting(condition, lock) => {
+                    roots.push(Object::Condition(condition));
+                    roots.push(Object::Lock(lock));
+                },
+                _ => {},
+            }
+        }
+
+        roots.extend(
+            [
+                self.error_dict,
+                self.error_info,
+                self.font_directory,
+                self.global_dict,
+                self.page_device,
+                self.resources,
+                self.status_dict,
+                self.user_dict,
+            ]
+            .map(Object::Dictionary),
+        );
+        roots.extend(self.save_stack.iter().copied().map(Object::Save));
+        roots.extend(self.job_save.map(Object::Save));
+        roots.extend(self.streamed_job.map(|job| Object::File(job.file)));
+
+        for dict in [&self.device_params, &self.system_params, &self.user_params] {
+            roots.extend(dict.iter().flat_map(|(key, value)| [*key, *value]));
+        }
+
+        self.forget_names();
+        self.heap.collect(roots);
+    }
+
+    pub(crate) fn defineuserobject(&mut self) -> crate::Result<()> {
+        let obj = self.pop()?;
+        let index = self.pop_usize()?;
+        if index >= MAX_ARRAY_LENGTH {
+            return Err(Error::from(ErrorKind::LimitCheck));
+        }
+
+        let user_objects = match self.user_objects() {
+            Some(user_objects) if index < self.heap.get(user_objects).len() => user_objects,
+            existing => {
+                // Grow geometrically so defining objects in order stays cheap
+                let mut objs = existing
+                    .map(|arr| self.heap.get(arr).value().to_vec())
+                    .unwrap_or_default();
+                let len = (index + 1).max(objs.len() * 2).min(MAX_ARRAY_LENGTH);
+                objs.resize(len, Object::Null(Mode::Literal));
+
+                let user_objects =
+                    self.heap
+                        .alloc(ArrayObject::new(objs, Access::Unlimited, Mode::Literal));
+
+                self.insert(
+                    self.user_dict,
+                    literal_name("UserObjects"),
+                    Object::Array(user_objects),
+                )?;
+
+                user_objects
+            },
+        };
+
+        self.journal_array(user_objects);
+        *self.heap.get_mut(user_objects).get_mut(index)? = obj;
+
+        Ok(())
+    }
+
+    pub(crate) fn execuserobject(&mut self) -> crate::Result<()> {
+        let index = self.pop_usize()?;
+
+        let Some(user_objects) = self.user_objects() else {
+            return Err(Error::new(ErrorKind::Undefined, "UserObjects"));
+        };
+
+        let obj = *self.heap.get(user_objects).get(index)?;
+
+        self.push_frame(Frame::Object(obj))
+    }
+
+    pub(crate) fn undefineuserobject(&mut self) -> crate::Result<()> {
+        let index = self.pop_usize()?;
+
+        let Some(user_objects) = self.user_objects() else {
+            return Err(Error::new(ErrorKind::RangeCheck, "index out of range"));
+        };
+
+        self.journal_array(user_objects);
+        *self.heap.get_mut(user_objects).get_mut(index)? = Object::Null(Mode::Literal);
+
+        Ok(())
+    }
+
+    fn user_objects(&self) -> Option<Handle<ArrayObject>> {
+        match self
+            .heap
+            .get(self.user_dict)
+            .get(&literal_name("UserObjects"))
+        {
+            Some(Object::Array(arr)) => Some(*arr),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,8 @@
+/// A page transmitted by `showpage`, as passed to the host's page handler.
+/// Sizes are in points, taken from the `PageSize` of the page device.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Page {
+    pub height: f32,
+    pub number: i32,
+    pub width: f32,
+}
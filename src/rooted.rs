@@ -0,0 +1,67 @@
+use std::{cell::RefCell, collections::HashMap, fmt, ops::Deref, rc::Rc};
+
+use crate::Object;
+
+/// How many `Rooted` guards hold each heap slot.
+pub(crate) type Pins = Rc<RefCell<HashMap<u32, usize>>>;
+
+/// An object handed out to the host. The garbage collector treats it, and
+/// everything it refers to, as reachable for as long as the guard is alive,
+/// so it stays valid across later calls to `exec`. A copy of the `Object`
+/// taken out of the guard is only valid while the guard is.
+pub struct Rooted {
+    obj: Object,
+    /// The slot the object lives in, if it is composite.
+    index: Option<u32>,
+    pins: Pins,
+}
+
+impl Rooted {
+    pub(crate) fn new(obj: Object, index: Option<u32>, pins: &Pins) -> Self {
+        if let Some(index) = index {
+            *pins.borrow_mut().entry(index).or_default() += 1;
+        }
+
+        Self {
+            obj,
+            index,
+            pins: pins.clone(),
+        }
+    }
+}
+
+impl Clone for Rooted {
+    fn clone(&self) -> Self {
+        Self::new(self.obj, self.index, &self.pins)
+    }
+}
+
+impl fmt::Debug for Rooted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Rooted").field(&self.obj).finish()
+    }
+}
+
+impl Deref for Rooted {
+    type Target = Object;
+
+    fn deref(&self) -> &Self::Target {
+        &self.obj
+    }
+}
+
+impl Drop for Rooted {
+    fn drop(&mut self) {
+        let Some(index) = self.index else {
+            return;
+        };
+
+        let mut pins = self.pins.borrow_mut();
+        if let Some(count) = pins.get_mut(&index) {
+            *count -= 1;
+            if *count == 0 {
+                pins.remove(&index);
+            }
+        }
+    }
+}
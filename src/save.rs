@@ -0,0 +1,106 @@
+use std::{collections::HashSet, mem};
+
+use crate::{
+    array::ArrayObject,
+    dictionary::DictionaryObject,
+    heap::{Handle, Heap},
+    string::StringObject,
+    Object,
+};
+
+/// A snapshot of local VM taken by `save`. Composite objects are copied the
+/// first time they are modified after the save, so `restore` can put them
+/// back the way they were. Objects in global VM are not covered.
+#[derive(Debug, Default)]
+pub struct Save {
+    /// The allocation mode in effect when the save was taken.
+    global: bool,
+    journal: Vec<Snapshot>,
+    seen: HashSet<u32>,
+    /// The heap's serial number when the save was taken.
+    serial: u64,
+}
+
+#[derive(Debug)]
+enum Snapshot {
+    Array(Handle<ArrayObject>, ArrayObject),
+    Dictionary(Handle<DictionaryObject>, DictionaryObject),
+    String(Handle<StringObject>, StringObject),
+}
+
+impl Save {
+    pub(crate) fn new(heap: &Heap) -> Self {
+        Self {
+            global: heap.global(),
+            serial: heap.serial(),
+            ..Self::default()
+        }
+    }
+
+    /// Whether `obj` was created in local VM after the save, so that
+    /// restoring the save would leave it behind as an object that no longer
+    /// exists.
+    pub(crate) fn precedes(&self, obj: &Object, heap: &Heap) -> bool {
+        !heap.is_global(obj) && heap.is_newer(obj, self.serial)
+    }
+
+    /// Whether `index` still has to be recorded before it is modified.
+    pub(crate) fn needs_record(&self, index: u32) -> bool {
+        !self.seen.contains(&index)
+    }
+
+    pub(crate) fn record_array(&mut self, arr: Handle<ArrayObject>, value: ArrayObject) {
+        if self.seen.insert(arr.index()) {
+            self.journal.push(Snapshot::Array(arr, value));
+        }
+    }
+
+    pub(crate) fn record_dict(&mut self, dict: Handle<DictionaryObject>, value: DictionaryObject) {
+        if self.seen.insert(dict.index()) {
+            self.journal.push(Snapshot::Dictionary(dict, value));
+        }
+    }
+
+    pub(crate) fn record_string(&mut self, string: Handle<StringObject>, value: StringObject) {
+        if self.seen.insert(string.index()) {
+            self.journal.push(Snapshot::String(string, value));
+        }
+    }
+
+    /// Every object the journal refers to, which must outlive the save.
+    pub(crate) fn objects(&self) -> Vec<Object> {
+        let mut objs = Vec::new();
+
+        for snapshot in &self.journal {
+            match snapshot {
+                Snapshot::Array(arr, value) => {
+                    objs.push(Object::Array(*arr));
+                    objs.extend(value.iter().copied());
+                },
+                Snapshot::Dictionary(dict, value) => {
+                    objs.push(Object::Dictionary(*dict));
+                    objs.extend(value.iter().flat_map(|(key, value)| [*key, *value]));
+                },
+                Snapshot::String(string, _) => objs.push(Object::String(*string)),
+            }
+        }
+
+        objs
+    }
+
+    pub(crate) fn restore(save: Handle<Save>, heap: &mut Heap) {
+        let save = heap.get_mut(save);
+        let journal = mem::take(&mut save.journal);
+        save.seen.clear();
+        let global = save.global;
+        heap.set_global(global);
+
+        for snapshot in journal.into_iter().rev() {
+            match snapshot {
+                Snapshot::Array(arr, value) => *heap.get_mut(arr) = value,
+                Snapshot::Dictionary(dict, value) => *heap.get_mut(dict) = value,
+                Snapshot::String(string, value) => *heap.get_mut(string) = value,
+            }
+        }
+    }
+}
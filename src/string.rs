@@ -1,9 +1,11 @@
-use std::{fmt, hash, str};
+use crate::{
+    object::{Access, Mode},
+    Error, ErrorKind,
+};
 
-use crate::object::Mode;
-
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct StringObject {
+    access: Access,
     inner: Vec<u8>,
     mode: Mode,
 }
@@ -11,32 +13,58 @@ pub struct StringObject {
 impl StringObject {
     pub fn new<S: Into<String>>(value: S, mode: Mode) -> Self {
         Self {
+            access: Access::default(),
             inner: value.into().bytes().collect(),
             mode,
         }
     }
 
+    pub fn access(&self) -> Access {
+        self.access
+    }
+
+    pub fn get(&self, index: usize) -> crate::Result<u8> {
+        self.inner
+            .get(index)
+            .copied()
+            .ok_or(Error::new(ErrorKind::RangeCheck, "index out of range"))
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> crate::Result<&mut u8> {
+        self.inner
+            .get_mut(index)
+            .ok_or(Error::new(ErrorKind::RangeCheck, "index out of range"))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
     pub fn mode(&self) -> Mode {
         self.mode
     }
 
-    pub fn value(&self) -> &[u8] {
-        &self.inner
+    pub fn set_access(&mut self, access: Access) {
+        self.access = access;
     }
-}
 
-impl fmt::Display for StringObject {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match str::from_utf8(&self.inner) {
-            Ok(s) => s.fmt(f),
-            Err(_) => "(� )".fmt(f),
-        }
+    pub(crate) fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    pub fn value(&self) -> &[u8] {
+        &self.inner
     }
 }
 
 impl From<&str> for StringObject {
     fn from(value: &str) -> Self {
         Self {
+            access: Access::default(),
             inner: value.bytes().collect(),
             mode: Mode::default(),
         }
@@ -46,26 +74,25 @@ impl From<&str> for StringObject {
 impl From<String> for StringObject {
     fn from(value: String) -> Self {
         Self {
+            access: Access::default(),
             inner: value.bytes().collect(),
             mode: Mode::default(),
         }
     }
 }
 
-impl From<StringObject> for Vec<u8> {
-    fn from(value: StringObject) -> Self {
-        value.inner
-    }
-}
-
-impl hash::Hash for StringObject {
-    fn hash<H: hash::Hasher>(&self, state: &mut H) {
-        self.inner.hash(state);
+impl From<Vec<u8>> for StringObject {
+    fn from(value: Vec<u8>) -> Self {
+        Self {
+            access: Access::default(),
+            inner: value,
+            mode: Mode::Literal,
+        }
     }
 }
 
-impl PartialEq for StringObject {
-    fn eq(&self, other: &Self) -> bool {
-        self.inner == other.inner
+impl From<StringObject> for Vec<u8> {
+    fn from(value: StringObject) -> Self {
+        value.inner
     }
 }
@@ -0,0 +1,47 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::heap::Handle;
+
+/// The composite objects a recursive traversal (binding, building functions) is
+/// currently inside of, so it can notice when it loops back into one of them. Each traversal
+/// starts its own, since a handle only identifies an object within a single heap.
+#[derive(Debug, Default)]
+pub(crate) struct Visits(Vec<u32>);
+
+impl Visits {
+    /// Marks a composite object as being traversed for as long as the returned guard is alive,
+    /// or returns `None` if the traversal is already inside of it. The guard stands in for the
+    /// set while traversing the object's children.
+    pub(crate) fn enter<T>(&mut self, handle: Handle<T>) -> Option<Visit<'_>> {
+        let index = handle.index();
+        if self.0.contains(&index) {
+            return None;
+        }
+
+        self.0.push(index);
+
+        Some(Visit(self))
+    }
+}
+
+pub(crate) struct Visit<'a>(&'a mut Visits);
+
+impl Deref for Visit<'_> {
+    type Target = Visits;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl DerefMut for Visit<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+    }
+}
+
+impl Drop for Visit<'_> {
+    fn drop(&mut self) {
+        self.0 .0.pop();
+    }
+}
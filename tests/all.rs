@@ -1,13 +1,47 @@
-use std::error;
+use std::{collections::HashMap, error, fs, io, sync::atomic::Ordering, time::Duration};
+
+use libps::{
+    ErrorKind, FileObject, Function, Identity, Interpreter, JsonOptions, LanguageLevel, Limits,
+    Mode, Rooted,
+};
 
 type TestResult = Result<(), Box<dyn error::Error>>;
 
-fn run_test(_test_name: &str) -> TestResult {
-    /*
+fn run_test(test_name: &str) -> TestResult {
+    run_test_at_level(test_name, LanguageLevel::default())
+}
+
+fn run_test_at_level(test_name: &str, language_level: LanguageLevel) -> TestResult {
+    run_test_with(test_name, |interpreter| {
+        interpreter.set_language_level(language_level)
+    })
+}
+
+fn run_test_with<F>(test_name: &str, setup: F) -> TestResult
+where
+    F: FnOnce(&mut Interpreter),
+{
     let input = fs::read_to_string(format!("tests/{test_name}"))?;
-    let mut ctx = Context::with_debug_utils();
-    evaluate(&mut ctx, &input)?;
-    */
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    setup(&mut interpreter);
+    interpreter.push_file(FileObject::from(input));
+    interpreter.exec()?;
+
+    check_errors(&interpreter)
+}
+
+// The debug utils let a job carry on past its errors so that `asserterror`
+// can check them, so errors a test does not expect only show up afterwards
+fn check_errors(interpreter: &Interpreter) -> TestResult {
+    if let Some(e) = interpreter.last_error() {
+        return Err(format!("job stopped with {e}").into());
+    }
+
+    let error_info: Rooted = interpreter.load_value("$error")?;
+    if interpreter.get_value(*error_info, "newerror")? {
+        let name: String = interpreter.get_value(*error_info, "errorname")?;
+        return Err(format!("unexpected {name}").into());
+    }
 
     Ok(())
 }
@@ -116,3 +150,555 @@ fn test_forall() -> TestResult {
 fn test_dict() -> TestResult {
     run_test("test_dict.ps")
 }
+
+#[test]
+fn test_errors() -> TestResult {
+    run_test("test_errors.ps")
+}
+
+#[test]
+fn test_overflow_recovery() -> TestResult {
+    let limits = Limits::default();
+    let jobs = [
+        (
+            "0 1 600 { } for",
+            ErrorKind::StackOverflow,
+            limits.max_op_stack,
+        ),
+        (
+            "{ 0 dict begin } loop",
+            ErrorKind::DictStackOverflow,
+            limits.max_dict_stack,
+        ),
+        (
+            "/recurse { recurse 1 } def recurse",
+            ErrorKind::ExecStackOverflow,
+            limits.max_exec_stack,
+        ),
+    ];
+
+    let mut interpreter = Interpreter::new(io::sink());
+    for (input, kind, len) in jobs {
+        interpreter.push_file(FileObject::from(String::from(input)));
+        interpreter.exec()?;
+        assert_eq!(Some(kind), interpreter.last_error().map(|e| e.kind()));
+
+        // The stack that overflowed is reset, and its old contents are left
+        // on top of the operand stack
+        let input = "length countdictstack countexecstack";
+        interpreter.push_file(FileObject::from(String::from(input)));
+        interpreter.exec()?;
+        assert!(interpreter.last_error().is_none());
+        assert_eq!(2, interpreter.pop_value::<i32>()?);
+        assert_eq!(3, interpreter.pop_value::<i32>()?);
+        assert_eq!(len, interpreter.pop_value::<i32>()? as usize);
+
+        interpreter.push_file(FileObject::from(String::from("clear")));
+        interpreter.exec()?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_interrupt() -> TestResult {
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    interpreter.push_file(FileObject::from(String::from("/interrupt asserterror")));
+    interpreter.interrupt_flag().store(true, Ordering::Relaxed);
+    interpreter.exec()?;
+
+    check_errors(&interpreter)
+}
+
+#[test]
+fn test_timeout() -> TestResult {
+    let input = "/recordtimeout errordict /timeout get def
+        errordict /timeout { recordtimeout stop } put
+        { {} loop } stopped assert
+        /timeout asserterror";
+
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    interpreter.set_job_timeout(Some(Duration::from_millis(10)));
+    interpreter.push_file(FileObject::from(String::from(input)));
+    interpreter.exec()?;
+
+    check_errors(&interpreter)
+}
+
+#[test]
+fn test_cycles() -> TestResult {
+    run_test("test_cycles.ps")
+}
+
+#[test]
+fn test_execstack() -> TestResult {
+    run_test("test_execstack.ps")
+}
+
+#[test]
+fn test_save() -> TestResult {
+    run_test("test_save.ps")
+}
+
+#[test]
+fn test_userobjects() -> TestResult {
+    run_test("test_userobjects.ps")
+}
+
+#[test]
+fn test_level1() -> TestResult {
+    run_test_at_level("test_level1.ps", LanguageLevel::Level1)
+}
+
+#[test]
+fn test_level1_prelude() -> TestResult {
+    // The prelude has to fit in systemdict even though dictionaries cannot
+    // grow at LanguageLevel 1
+    let mut interpreter =
+        Interpreter::with_language_level(io::stdout().lock(), LanguageLevel::Level1);
+    assert_eq!(LanguageLevel::Level1, interpreter.language_level());
+
+    interpreter.push_file(FileObject::from(String::from(
+        "/hasprompt systemdict /prompt known def",
+    )));
+    interpreter.exec()?;
+
+    assert!(interpreter.last_error().is_none());
+    assert!(interpreter.load_value::<bool>("hasprompt")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_level2() -> TestResult {
+    run_test_at_level("test_level2.ps", LanguageLevel::Level2)
+}
+
+#[test]
+fn test_level3() -> TestResult {
+    run_test_at_level("test_level3.ps", LanguageLevel::Level3)
+}
+
+#[test]
+fn test_binary_tokens() -> TestResult {
+    let mut input = Vec::new();
+    // 16-bit integer, high-order byte first
+    input.extend_from_slice(&[134, 0x01, 0x00]);
+    input.extend_from_slice(b" 256 asserteq ");
+    // 32-bit fixed point with a scale of 1
+    input.extend_from_slice(&[137, 1, 0, 0, 0, 5]);
+    input.extend_from_slice(b" 2.5 asserteq ");
+    // Short string
+    input.extend_from_slice(&[142, 3]);
+    input.extend_from_slice(b"abc (abc) asserteq ");
+    // Executable system name `add`
+    input.extend_from_slice(&[136, 2, 136, 3, 146, 1]);
+    input.extend_from_slice(b" 5 asserteq ");
+    // Homogeneous array of two 16-bit integers, low-order byte first
+    input.extend_from_slice(&[149, 160, 2, 0, 7, 0, 8, 0]);
+    input.extend_from_slice(b" aload pop 8 asserteq 7 asserteq ");
+    // Binary object sequence equivalent to `1 2 add`, executed immediately
+    input.extend_from_slice(&[128, 3, 0, 28]);
+    input.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 1]);
+    input.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 2]);
+    input.extend_from_slice(&[0x83, 0, 0, 0, 0, 0, 0, 1]);
+    input.extend_from_slice(b" 3 asserteq");
+
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    interpreter.push_file(FileObject::new(input, Mode::Executable));
+    interpreter.exec()?;
+
+    check_errors(&interpreter)
+}
+
+#[test]
+fn test_level1_binary_tokens() -> TestResult {
+    // Without binary tokens the bytes scan as a name, which is undefined
+    let mut input = vec![134, 0x01, 0x00];
+    input.extend_from_slice(b" /undefined asserterror handleerror clear");
+
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    interpreter.set_language_level(LanguageLevel::Level1);
+    interpreter.push_file(FileObject::new(input, Mode::Executable));
+    interpreter.exec()?;
+
+    check_errors(&interpreter)
+}
+
+#[test]
+fn test_deterministic() -> TestResult {
+    let input = fs::read_to_string("tests/test_deterministic.ps")?;
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    interpreter.set_deterministic(true);
+    interpreter.push_file(FileObject::from(input));
+    interpreter.exec()?;
+
+    check_errors(&interpreter)
+}
+
+#[test]
+fn test_identity() -> TestResult {
+    let input = "product (LaserWriter) assertdeepeq
+        version (2010.130) assertdeepeq
+        revision 3 asserteq
+        serialnumber 12345 asserteq
+        product dup 0 (l) putinterval
+        /invalidaccess asserterror";
+
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    interpreter.set_identity(Identity {
+        product: String::from("LaserWriter"),
+        revision: 3,
+        serial_number: 12345,
+        version: String::from("2010.130"),
+    });
+    interpreter.push_file(FileObject::from(String::from(input)));
+    interpreter.exec()?;
+
+    check_errors(&interpreter)
+}
+
+#[test]
+fn test_dictstack() -> TestResult {
+    run_test("test_dictstack.ps")
+}
+
+#[test]
+fn test_params() -> TestResult {
+    run_test("test_params.ps")
+}
+
+#[test]
+fn test_limits() -> TestResult {
+    let input = "currentuserparams /MaxOpStack get 100 asserteq
+        << /MaxOpStack 101 >> setuserparams
+        /limitcheck asserterror";
+
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    interpreter.set_limits(Limits {
+        max_op_stack: 100,
+        ..Limits::default()
+    });
+    interpreter.push_file(FileObject::from(String::from(input)));
+    interpreter.exec()?;
+
+    check_errors(&interpreter)
+}
+
+#[test]
+fn test_statusdict() -> TestResult {
+    run_test("test_statusdict.ps")
+}
+
+#[test]
+fn test_jobs() -> TestResult {
+    let jobs = [
+        "/a 1 def",
+        "/a where { pop false assert } if
+        serverdict begin (wrong) exitserver
+        /invalidaccess asserterror
+        handleerror clear end
+        serverdict begin (secret) exitserver
+        countdictstack 3 asserteq
+        /b 2 def",
+        "b 2 asserteq
+        true (wrong) startjob false asserteq
+        save true (secret) startjob false asserteq restore
+        true (secret) startjob assert
+        /c 3 def
+        false (secret) startjob assert
+        /d 4 def",
+        "c 3 asserteq
+        /d where { pop false assert } if",
+    ];
+
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    interpreter.set_start_job_password(b"secret");
+    interpreter.exec_jobs(jobs.map(|job| FileObject::from(String::from(job))))?;
+
+    let input = "true () startjob false asserteq";
+    interpreter.push_file(FileObject::from(String::from(input)));
+    interpreter.exec()?;
+
+    check_errors(&interpreter)
+}
+
+#[test]
+fn test_contexts() -> TestResult {
+    run_test_with("test_contexts.ps", |interpreter| {
+        interpreter.set_display_postscript(true)
+    })
+}
+
+#[test]
+fn test_rand() -> TestResult {
+    run_test("test_rand.ps")
+}
+
+#[test]
+fn test_bind() -> TestResult {
+    run_test("test_bind.ps")
+}
+
+#[test]
+fn test_name_cache() -> TestResult {
+    run_test("test_name_cache.ps")
+}
+
+#[test]
+fn test_currentfile() -> TestResult {
+    run_test("test_currentfile.ps")
+}
+
+#[test]
+fn test_streamed_input() -> TestResult {
+    let chunks: [&[u8]; 6] = [
+        b"1 2 ad",
+        b"d 3 asserteq /sum { ad",
+        b"d } def 1 2 sum 3 asserteq (ab",
+        b"c) (abc) asserteq\ncurrentfile (...) readstring\nxy",
+        b"z",
+        b"\nassert (xyz) asserteq /done true def",
+    ];
+
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    for chunk in chunks {
+        interpreter.feed(chunk)?;
+    }
+    interpreter.end_input()?;
+
+    // The rest of a job is discarded once it stops
+    interpreter.feed(b"/done where assert pop stop ")?;
+    interpreter.feed(b"/after true def")?;
+    interpreter.end_input()?;
+
+    let input = "/after where { pop false assert } if";
+    interpreter.push_file(FileObject::from(String::from(input)));
+    interpreter.exec()?;
+
+    check_errors(&interpreter)
+}
+
+#[test]
+fn test_quit() -> TestResult {
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    interpreter.set_display_postscript(true);
+
+    // A `true` left on the stack is not mistaken for the job having stopped
+    for input in [
+        "true quit /after true def",
+        "{ true quit } stopped /after true def",
+        "mark { quit } fork pop true yield /after true def",
+    ] {
+        interpreter.push_file(FileObject::from(String::from(input)));
+        interpreter.exec()?;
+        assert!(interpreter.has_quit());
+        assert!(interpreter.pop_value::<bool>()?);
+        check_errors(&interpreter)?;
+    }
+
+    interpreter.push_file(FileObject::from(String::from("/after where")));
+    interpreter.exec()?;
+    assert!(!interpreter.has_quit());
+    assert!(!interpreter.pop_value::<bool>()?);
+
+    // The job server goes on to the next job
+    let jobs = ["/a 1 def quit /b 2 def", "/c 3 def"];
+    interpreter.exec_jobs(jobs.map(|job| FileObject::from(String::from(job))))?;
+    assert!(!interpreter.has_quit());
+
+    // The rest of a streamed job is discarded once it quits
+    interpreter.feed(b"quit ")?;
+    interpreter.feed(b"/after true def")?;
+    interpreter.end_input()?;
+    assert!(interpreter.has_quit());
+
+    let input = "/after where { pop false assert } if";
+    interpreter.push_file(FileObject::from(String::from(input)));
+    interpreter.exec()?;
+
+    check_errors(&interpreter)
+}
+
+#[test]
+fn test_image() -> TestResult {
+    let setup = "/sq { dup mul } bind def
+        globaldict /greeting (hello) put
+        /nested << /list [1 2.5 /name (str)] >> def";
+
+    let mut interpreter = Interpreter::with_debug_utils(io::stdout().lock());
+    interpreter.set_language_level(LanguageLevel::Level2);
+    interpreter.push_file(FileObject::from(String::from(setup)));
+    interpreter.exec()?;
+
+    let mut image = Vec::new();
+    interpreter.dump_image(&mut image)?;
+
+    let input = "3 sq 9 asserteq
+        globaldict /greeting get (hello) asserteq
+        nested /list get 3 get (str) asserteq
+        languagelevel 2 asserteq
+        missing /undefined asserterror";
+
+    let mut interpreter = Interpreter::load_image(io::stdout().lock(), image.as_slice())?;
+    assert_eq!(LanguageLevel::Level2, interpreter.language_level());
+    interpreter.push_file(FileObject::from(String::from(input)));
+    interpreter.exec()?;
+
+    // Images are tied to the version that wrote them
+    let mut truncated = image.clone();
+    truncated.truncate(image.len() / 2);
+    image[8] ^= 0xff;
+    for image in [image, truncated] {
+        let result = Interpreter::load_image(io::stdout().lock(), image.as_slice());
+        assert!(result.is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_prelude() -> TestResult {
+    run_test("test_prelude.ps")
+}
+
+#[test]
+fn test_custom_prelude() -> TestResult {
+    // Fails with an undefinedresult unless the definition lands in systemdict
+    let prelude = b"/greeting (hello) def systemdict /greeting known { } { 1 0 idiv } ifelse";
+    Interpreter::with_prelude(io::stdout().lock(), LanguageLevel::default(), prelude)?;
+
+    let result =
+        Interpreter::with_prelude(io::stdout().lock(), LanguageLevel::default(), b"1 0 idiv");
+    assert_eq!(
+        Some(ErrorKind::UndefinedResult),
+        result.err().map(|e| e.kind())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_typed_values() -> TestResult {
+    let mut interpreter = Interpreter::new(io::stdout().lock());
+
+    let mut prices = HashMap::new();
+    prices.insert(String::from("apple"), 1.5);
+    prices.insert(String::from("pear"), 2.0);
+    interpreter.def_value("prices", prices)?;
+    interpreter.def_value("items", vec!["apple", "pear"])?;
+    interpreter.push_value(10)?;
+
+    let input = "prices /apple get mul
+        /total exch def
+        /names [ items { } forall ] def";
+    interpreter.push_file(FileObject::from(String::from(input)));
+    interpreter.exec()?;
+
+    assert_eq!(15.0, interpreter.load_value::<f64>("total")?);
+    assert_eq!(
+        vec![String::from("apple"), String::from("pear")],
+        interpreter.load_value::<Vec<String>>("names")?
+    );
+
+    let user_dict: Rooted = interpreter.load_value("userdict")?;
+    interpreter.put_value(*user_dict, "flag", true)?;
+    assert!(interpreter.get_value::<bool>(*user_dict, "flag")?);
+
+    // Values that do not convert stay on the stack
+    interpreter.push_value(b"bytes".as_slice())?;
+    assert!(interpreter.pop_value::<i32>().is_err());
+    assert_eq!(b"bytes".to_vec(), interpreter.peek_value::<Vec<u8>>()?);
+    assert_eq!("bytes", interpreter.pop_value::<String>()?);
+    assert!(interpreter.pop_value::<i32>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_json() -> TestResult {
+    let options = JsonOptions::default();
+    let mut interpreter = Interpreter::new(io::stdout().lock());
+
+    let data = interpreter.parse_json(r#"{"name": "Widget", "sizes": [1, 2.5]}"#, &options)?;
+    interpreter.def_value("data", data)?;
+
+    let input = "/result <<
+        /label data /name get
+        /total data /sizes get aload pop add
+        /ok true
+        /none null
+    >> def";
+    interpreter.push_file(FileObject::from(String::from(input)));
+    interpreter.exec()?;
+
+    let result: Rooted = interpreter.load_value("result")?;
+    assert_eq!(
+        r#"{"label":"Widget","total":3.5,"ok":true,"none":null}"#,
+        interpreter.to_json(*result, &options)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_rooted_values() -> TestResult {
+    let options = JsonOptions::default();
+    let mut interpreter = Interpreter::new(io::stdout().lock());
+
+    let data = interpreter.parse_json(r#"{"sizes": [1, 2]}"#, &options)?;
+    interpreter.push_file(FileObject::from(String::from("[ (popped) ]")));
+    interpreter.exec()?;
+    let popped: Rooted = interpreter.pop_value()?;
+
+    // Neither value is reachable from the VM, so only being rooted keeps
+    // them from being collected
+    let input = "<< /VMThreshold 1 >> setuserparams
+        0 1 100 { pop 1 array pop } for";
+    interpreter.push_file(FileObject::from(String::from(input)));
+    interpreter.exec()?;
+
+    assert_eq!(r#"{"sizes":[1,2]}"#, interpreter.to_json(*data, &options)?);
+    assert_eq!(r#"["popped"]"#, interpreter.to_json(*popped, &options)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_functions() -> TestResult {
+    let input = "/sampled << /FunctionType 0 /Domain [0 1] /Range [0 1] /Size [3]
+            /BitsPerSample 8 /DataSource (\\000\\377\\000) >> def
+        /exponential << /FunctionType 2 /Domain [0 1] /C0 [0] /C1 [1] /N 2 >> def
+        /stitching << /FunctionType 3 /Domain [0 2] /Bounds [1]
+            /Functions [exponential exponential] /Encode [0 1 0 1] >> def
+        /calculator << /FunctionType 4 /Domain [-10 10] /Range [-100 100]
+            /Function { dup mul 1 add } >> def";
+
+    let mut interpreter = Interpreter::new(io::stdout().lock());
+    interpreter.push_file(FileObject::from(String::from(input)));
+    interpreter.exec()?;
+    assert!(interpreter.last_error().is_none());
+
+    for (name, input, expected) in [
+        ("sampled", 0.25, 0.5),
+        ("exponential", 0.5, 0.25),
+        ("stitching", 1.5, 0.25),
+        ("calculator", 3.0, 10.0),
+    ] {
+        let function: Function = interpreter.load_value(name)?;
+        assert_eq!((1, 1), (function.inputs(), function.outputs()));
+
+        let mut output = [0.0];
+        function.evaluate(&[input], &mut output)?;
+        assert_eq!([expected], output, "{name}");
+    }
+
+    // Dictionaries that are not valid functions are rejected
+    interpreter.push_value(1)?;
+    assert!(interpreter.pop_value::<Function>().is_err());
+    let result = interpreter
+        .get_value::<Function>(*interpreter.load_value::<Rooted>("calculator")?, "Domain");
+    assert!(result.is_err());
+
+    Ok(())
+}